@@ -78,12 +78,25 @@ struct ParsedInfo {
 impl FromStr for ParsedInfo {
     type Err = ();
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let r = Regex::new(r#"\((?P<k1>[a-zA-Z0-9_-]+)\s*,\s*(?P<k2>[a-zA-Z0-9_-]+)\)=\((?P<v1>[a-zA-Z0-9_-]+)\s*,\s*\[(?P<v2>[^\)\]]+)"#).unwrap();
+        // the last key is always the timespan, keys before it (e.g. tenant_id, resource_id) are plain values
+        let r = Regex::new(r#"\((?P<keys>[a-zA-Z0-9_-]+(?:\s*,\s*[a-zA-Z0-9_-]+)+)\)=\((?P<values>(?:[a-zA-Z0-9_-]+\s*,\s*)+)\[(?P<range>[^\)\]]+)"#).unwrap();
         let mut maps = vec![];
         for cap in r.captures_iter(s) {
-            let mut map = HashMap::new();
-            map.insert(cap["k1"].to_string(), cap["v1"].to_string());
-            map.insert(cap["k2"].to_string(), cap["v2"].to_string());
+            let keys: Vec<&str> = cap["keys"].split(',').map(str::trim).collect();
+            let mut values: Vec<&str> = cap["values"]
+                .split(',')
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .collect();
+            values.push(&cap["range"]);
+            if keys.len() != values.len() {
+                return Err(());
+            }
+            let map: HashMap<String, String> = keys
+                .into_iter()
+                .zip(values)
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
             maps.push(Some(map));
         }
         if maps.len() != 2 {
@@ -97,7 +110,7 @@ impl FromStr for ParsedInfo {
 }
 
 fn parse_datetime(s: &str) -> Result<DateTime<Utc>, ()> {
-    Ok(DateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%#z")
+    Ok(DateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f%#z")
         .map_err(|_| ())?
         .with_timezone(&Utc))
 }
@@ -107,6 +120,7 @@ fn parse_datetime(s: &str) -> Result<DateTime<Utc>, ()> {
 mod tests {
     use super::*;
     const ERR_MSG:&str =  "Key (resource_id, timespan)=(ocean-view-room-713, [\"2022-12-26 22:00:00+00\",\"2022-12-30 19:00:00+00\")) conflicts with existing key (resource_id, timespan)=(ocean-view-room-713, [\"2022-12-25 22:00:00+00\",\"2022-12-28 19:00:00+00\")).";
+    const TENANT_ERR_MSG: &str = "Key (tenant_id, resource_id, timespan)=(tenant-1, ocean-view-room-713, [\"2022-12-26 22:00:00+00\",\"2022-12-30 19:00:00+00\")) conflicts with existing key (tenant_id, resource_id, timespan)=(tenant-1, ocean-view-room-713, [\"2022-12-25 22:00:00+00\",\"2022-12-28 19:00:00+00\")).";
     #[test]
     fn parsed_info_should_work() {
         let info: ParsedInfo = ERR_MSG.parse().unwrap();
@@ -122,6 +136,22 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parsed_info_with_tenant_should_work() {
+        let info: ParsedInfo = TENANT_ERR_MSG.parse().unwrap();
+        assert_eq!(info.new["tenant_id"], "tenant-1");
+        assert_eq!(info.new["resource_id"], "ocean-view-room-713");
+        assert_eq!(
+            info.new["timespan"],
+            "\"2022-12-26 22:00:00+00\",\"2022-12-30 19:00:00+00\""
+        );
+        assert_eq!(info.exist["tenant_id"], "tenant-1");
+        assert_eq!(
+            info.exist["timespan"],
+            "\"2022-12-25 22:00:00+00\",\"2022-12-28 19:00:00+00\""
+        );
+    }
+
     #[test]
     fn hashmap_to_reservationwindow_should_work() {
         let mut map = HashMap::new();
@@ -136,6 +166,18 @@ mod tests {
         assert_eq!(window.end.to_rfc3339(), "2022-12-30T19:00:00+00:00");
     }
 
+    #[test]
+    fn fractional_seconds_should_parse() {
+        let mut map = HashMap::new();
+        map.insert("resource_id".to_string(), "ocean-view-room-713".to_string());
+        map.insert(
+            "timespan".to_string(),
+            "\"2022-12-26 22:00:00+00\",\"2022-12-26 23:30:07.312001+00\"".to_string(),
+        );
+        let window: ReservationWindow = map.try_into().unwrap();
+        assert_eq!(window.end.to_rfc3339(), "2022-12-26T23:30:07.312001+00:00");
+    }
+
     #[test]
     fn conflict_error_message_should_parse() {
         let info: ReservationConflictInfo = ERR_MSG.parse().unwrap();
@@ -15,9 +15,19 @@ pub enum Error {
     #[error("Not found reservation")]
     NotFound,
 
+    #[error("Reservation can't be checked in at this time")]
+    CheckInNotAllowed,
+
     #[error("Conflict reservation")]
     ConflictReservation(ReservationConflictInfo),
 
+    /// a multi-resource reservation conflicts on more than one of its resources
+    #[error("Conflict reservations")]
+    ConflictReservations(Vec<ReservationConflictInfo>),
+
+    #[error("Invalid tenant id: {0}")]
+    InvalidTenantId(String),
+
     #[error("Invalid user id: {0}")]
     InvalidUserId(String),
 
@@ -27,6 +37,54 @@ pub enum Error {
     #[error("Invalid resource id: {0}")]
     InvalidResourceId(String),
 
+    #[error("Invalid reservation status: {0}")]
+    InvalidStatus(i32),
+
+    #[error("Invalid rate card: {0}")]
+    InvalidRateCard(String),
+
+    #[error("Invalid opening hours: {0}")]
+    InvalidOpeningHours(String),
+
+    #[error("Invalid row {row}: {reason}")]
+    InvalidRow { row: usize, reason: String },
+
+    #[error("Failed to export: {0}")]
+    ExportFailed(String),
+
+    #[error("Invalid webhook: {0}")]
+    InvalidWebhook(String),
+
+    #[error("Invalid time match: {0}")]
+    InvalidTimeMatch(i32),
+
+    #[error("Invalid search language: {0}")]
+    InvalidSearchLanguage(String),
+
+    #[error("Invalid cursor: {0}")]
+    InvalidCursor(String),
+
+    #[error("Invalid page: {0}")]
+    InvalidPage(i32),
+
+    #[error("Invalid page size: {0}")]
+    InvalidPageSize(i32),
+
+    #[error("Invalid calendar: {0}")]
+    InvalidCalendar(String),
+
+    #[error("Resource {child} can't be a child of {parent}, it would form a cycle")]
+    ResourceCycle { parent: String, child: String },
+
+    #[error("Reservation version mismatch: expected {expected}, found {actual}")]
+    PreconditionFailed { expected: i64, actual: i64 },
+
+    #[error("Invalid idempotency key: {0}")]
+    InvalidIdempotencyKey(String),
+
+    #[error("Idempotency key {0} was already used for another request")]
+    IdempotencyKeyReused(String),
+
     #[error("Unknown error")]
     Unknown,
 }
@@ -35,11 +93,25 @@ impl From<sqlx::Error> for Error {
     fn from(e: sqlx::Error) -> Self {
         match e {
             sqlx::Error::Database(db_err) => {
-                let err: &PgDatabaseError = db_err.downcast_ref();
+                // other databases report conflicts themselves
+                let err = match db_err.try_downcast_ref::<PgDatabaseError>() {
+                    Some(err) => err,
+                    None => return Error::DbError(sqlx::Error::Database(db_err)),
+                };
                 match (err.code(), err.schema(), err.table()) {
-                    ("23P01", Some("rsvp"), Some("reservations")) => {
-                        // convert err detail from str to ReservationConflictInfo
-                        Error::ConflictReservation(err.detail().unwrap().parse().unwrap())
+                    ("23P01", Some("rsvp"), Some("reservations" | "reservation_resources")) => {
+                        // convert err detail from str to ReservationConflictInfo, one conflict per line
+                        let mut infos: Vec<ReservationConflictInfo> = err
+                            .detail()
+                            .unwrap()
+                            .lines()
+                            .map(|line| line.parse().unwrap())
+                            .collect();
+                        if infos.len() == 1 {
+                            Error::ConflictReservation(infos.pop().unwrap())
+                        } else {
+                            Error::ConflictReservations(infos)
+                        }
                     }
                     _ => Error::DbError(sqlx::Error::Database(db_err)),
                 }
@@ -0,0 +1,428 @@
+//! iCalendar (RFC 5545) format of reservations, one VEVENT per reservation.
+//! The booked resource is the event's LOCATION and the extra resources are its RESOURCES.
+//! Only UTC date-times are understood, events with TZID, floating times or all-day dates
+//! are reported as unsupported, each on its own.
+
+use crate::{convert_to_timestamp, convert_to_utc_time, Error, Reservation, ReservationStatus};
+use chrono::{DateTime, Duration, NaiveDateTime, TimeZone, Utc};
+
+const DATE_TIME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+// lines longer than this are folded
+const MAX_LINE_OCTETS: usize = 75;
+
+/// a VCALENDAR with a VEVENT for each reservation
+pub fn to_ics(rsvps: &[Reservation]) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//reservation//reservation service//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+    ];
+    let now = format_time(Utc::now());
+    for rsvp in rsvps {
+        lines.push("BEGIN:VEVENT".into());
+        lines.push(format!("UID:{}", escape(&rsvp.id)));
+        lines.push(format!("DTSTAMP:{}", now));
+        if let Some(start) = &rsvp.start {
+            lines.push(format!(
+                "DTSTART:{}",
+                format_time(convert_to_utc_time(start.clone()))
+            ));
+        }
+        if let Some(end) = &rsvp.end {
+            lines.push(format!(
+                "DTEND:{}",
+                format_time(convert_to_utc_time(end.clone()))
+            ));
+        }
+        lines.push(format!("SUMMARY:{}", escape(&rsvp.resource_id)));
+        lines.push(format!("LOCATION:{}", escape(&rsvp.resource_id)));
+        if !rsvp.extra_resource_ids.is_empty() {
+            let ids: Vec<_> = rsvp
+                .extra_resource_ids
+                .iter()
+                .map(|id| escape(id))
+                .collect();
+            lines.push(format!("RESOURCES:{}", ids.join(",")));
+        }
+        if !rsvp.note.is_empty() {
+            lines.push(format!("DESCRIPTION:{}", escape(&rsvp.note)));
+        }
+        let status = ReservationStatus::from_i32(rsvp.status).and_then(event_status);
+        if let Some(status) = status {
+            lines.push(format!("STATUS:{}", status));
+        }
+        lines.push("END:VEVENT".into());
+    }
+    lines.push("END:VCALENDAR".into());
+
+    let mut ics = String::new();
+    for line in lines {
+        fold(&line, &mut ics);
+    }
+    ics
+}
+
+/// the UID of a VEVENT and its reservation, or why it can't be read
+pub type IcsEvent = (String, Result<Reservation, Error>);
+
+/// reservations of the given tenant and user for the VEVENTs of a VCALENDAR, paired with their UIDs.
+/// cancelled events are skipped, tentative ones become pending and confirmed ones confirmed.
+/// an event that can't be read is returned as an error of its own, only a calendar whose
+/// VEVENTs aren't balanced fails as a whole
+pub fn parse_ics(tid: &str, uid: &str, input: &str) -> Result<Vec<IcsEvent>, Error> {
+    let mut events = Vec::new();
+    let mut event: Option<Vec<String>> = None;
+    for line in unfold(input) {
+        match line.to_ascii_uppercase().as_str() {
+            "BEGIN:VEVENT" => event = Some(Vec::new()),
+            "END:VEVENT" => {
+                let lines = event
+                    .take()
+                    .ok_or_else(|| invalid("END:VEVENT without BEGIN"))?;
+                if let Some(event) = parse_event(tid, uid, lines) {
+                    events.push(event);
+                }
+            }
+            _ => {
+                if let Some(lines) = event.as_mut() {
+                    lines.push(line);
+                }
+            }
+        }
+    }
+    if event.is_some() {
+        return Err(invalid("VEVENT is not closed"));
+    }
+    Ok(events)
+}
+
+/// the UID and reservation of an event, none if it's cancelled
+fn parse_event(tid: &str, uid: &str, lines: Vec<String>) -> Option<IcsEvent> {
+    let props: Vec<Property> = match lines.iter().map(|line| split_property(line)).collect() {
+        Ok(props) => props,
+        Err(e) => return Some((find_uid(&lines), Err(e))),
+    };
+    let get = |key: &str| props.iter().find(|prop| prop.name == key);
+    let event_uid = get("UID")
+        .map(|prop| unescape(&prop.value))
+        .unwrap_or_default();
+    if get("STATUS").map(|prop| prop.value.as_str()) == Some("CANCELLED") {
+        return None;
+    }
+    let rsvp = to_reservation(tid, uid, &event_uid, &props);
+    Some((event_uid, rsvp))
+}
+
+fn to_reservation(
+    tid: &str,
+    uid: &str,
+    event_uid: &str,
+    props: &[Property],
+) -> Result<Reservation, Error> {
+    if event_uid.is_empty() {
+        return Err(invalid("VEVENT without UID"));
+    }
+    let get = |key: &str| props.iter().find(|prop| prop.name == key);
+    let status = match get("STATUS").map(|prop| prop.value.as_str()) {
+        Some("CONFIRMED") => ReservationStatus::Confirmed,
+        _ => ReservationStatus::Pending,
+    };
+    let start = get("DTSTART").map(parse_time).transpose()?;
+    let end = match (get("DTEND"), get("DURATION"), start) {
+        (Some(_), Some(_), _) => return Err(invalid("VEVENT with both DTEND and DURATION")),
+        (Some(end), None, _) => Some(parse_time(end)?),
+        (None, Some(duration), Some(start)) => Some(start + parse_duration(&duration.value)?),
+        _ => None,
+    };
+    let get = |key: &str| get(key).map(|prop| prop.value.as_str());
+    let resource_id = get("LOCATION").map(unescape).unwrap_or_default();
+    let extra_resource_ids = get("RESOURCES")
+        .map(|ids| split_list(ids).iter().map(|id| unescape(id)).collect())
+        .unwrap_or_default();
+
+    Ok(Reservation {
+        user_id: uid.to_string(),
+        status: status as i32,
+        resource_id,
+        start: start.map(convert_to_timestamp),
+        end: end.map(convert_to_timestamp),
+        note: get("DESCRIPTION").map(unescape).unwrap_or_default(),
+        tenant_id: tid.to_string(),
+        extra_resource_ids,
+        ..Default::default()
+    })
+}
+
+/// the UID of an event whose other lines may not be valid
+fn find_uid(lines: &[String]) -> String {
+    lines
+        .iter()
+        .find_map(|line| split_property(line).ok().filter(|prop| prop.name == "UID"))
+        .map(|prop| unescape(&prop.value))
+        .unwrap_or_default()
+}
+
+fn event_status(status: ReservationStatus) -> Option<&'static str> {
+    match status {
+        ReservationStatus::Pending | ReservationStatus::AwaitingApproval => Some("TENTATIVE"),
+        ReservationStatus::Confirmed | ReservationStatus::Blocked => Some("CONFIRMED"),
+        ReservationStatus::Rejected | ReservationStatus::NoShow => Some("CANCELLED"),
+        ReservationStatus::Unknown => None,
+    }
+}
+
+fn format_time(dt: DateTime<Utc>) -> String {
+    dt.format(DATE_TIME_FORMAT).to_string()
+}
+
+/// a UTC date-time, the zone of a TZID or a floating time isn't known here
+fn parse_time(prop: &Property) -> Result<DateTime<Utc>, Error> {
+    if prop.param("VALUE") == Some("DATE") {
+        return Err(invalid(format!("all-day {} is not supported", prop.name)));
+    }
+    if let Some(tzid) = prop.param("TZID") {
+        return Err(invalid(format!(
+            "{} in time zone {} is not supported",
+            prop.name, tzid
+        )));
+    }
+    NaiveDateTime::parse_from_str(&prop.value, DATE_TIME_FORMAT)
+        .map(|dt| Utc.from_utc_datetime(&dt))
+        .map_err(|_| invalid(format!("unsupported date-time {}", prop.value)))
+}
+
+/// a positive duration like P1W or P1DT2H30M, months and years aren't allowed by RFC 5545
+fn parse_duration(value: &str) -> Result<Duration, Error> {
+    let err = || invalid(format!("invalid duration {}", value));
+    let rest = value.strip_prefix('+').unwrap_or(value);
+    let rest = rest.strip_prefix('P').ok_or_else(err)?;
+    let mut duration = Duration::zero();
+    let mut number = String::new();
+    let mut in_time = false;
+    for c in rest.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' if !in_time && number.is_empty() => in_time = true,
+            _ => {
+                // small enough that no unit overflows
+                let n = number.parse::<u32>().map_err(|_| err())? as i64;
+                number.clear();
+                let part = match (c, in_time) {
+                    ('W', false) => Duration::weeks(n),
+                    ('D', false) => Duration::days(n),
+                    ('H', true) => Duration::hours(n),
+                    ('M', true) => Duration::minutes(n),
+                    ('S', true) => Duration::seconds(n),
+                    _ => return Err(err()),
+                };
+                duration = duration.checked_add(&part).ok_or_else(err)?;
+            }
+        }
+    }
+    if !number.is_empty() || duration <= Duration::zero() {
+        return Err(err());
+    }
+    Ok(duration)
+}
+
+/// a content line split into its name, parameters and value
+struct Property {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+impl Property {
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// split a content line, names are upper cased and quoted parameter values unquoted
+fn split_property(line: &str) -> Result<Property, Error> {
+    let (head, value) = line
+        .split_once(':')
+        .ok_or_else(|| invalid(format!("invalid content line {}", line)))?;
+    let mut params = head.split(';');
+    let name = params.next().unwrap_or_default().to_ascii_uppercase();
+    let params = params
+        .map(|param| {
+            let (key, value) = param.split_once('=').unwrap_or((param, ""));
+            (
+                key.to_ascii_uppercase(),
+                value.trim_matches('"').to_string(),
+            )
+        })
+        .collect();
+    Ok(Property {
+        name,
+        params,
+        value: value.to_string(),
+    })
+}
+
+/// join folded lines, a line starting with a space or tab continues the previous one
+fn unfold(input: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in input.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ if line.is_empty() => {}
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+/// append the line to output with CRLF endings, folded at 75 octets without splitting characters
+fn fold(line: &str, output: &mut String) {
+    let mut len = 0;
+    for c in line.chars() {
+        if len + c.len_utf8() > MAX_LINE_OCTETS {
+            output.push_str("\r\n ");
+            len = 1;
+        }
+        output.push(c);
+        len += c.len_utf8();
+    }
+    output.push_str("\r\n");
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+fn unescape(text: &str) -> String {
+    let mut result = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => result.push('\n'),
+            Some(c) => result.push(c),
+            None => {}
+        }
+    }
+    result
+}
+
+/// split a comma separated list, escaped commas stay in the items
+fn split_list(text: &str) -> Vec<String> {
+    let mut items = vec![String::new()];
+    let mut escaped = false;
+    for c in text.chars() {
+        match c {
+            ',' if !escaped => items.push(String::new()),
+            _ => items.last_mut().unwrap().push(c),
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    items
+}
+
+fn invalid(msg: impl Into<String>) -> Error {
+    Error::InvalidCalendar(msg.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reservation() -> Reservation {
+        let mut rsvp = Reservation::new_pending(
+            "tenant_id",
+            "user_id",
+            "room-1",
+            "2022-12-26T12:00:00-0700".parse().unwrap(),
+            "2022-12-26T14:00:00-0700".parse().unwrap(),
+            "weekly sync; bring slides, please\nsecond line",
+        );
+        rsvp.id = "c5d6f7a8".into();
+        rsvp.status = ReservationStatus::Confirmed as i32;
+        rsvp.extra_resource_ids = vec!["projector".into(), "vc-kit".into()];
+        rsvp
+    }
+
+    #[test]
+    fn to_ics_should_write_vevent() {
+        let ics = to_ics(&[reservation()]);
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(ics.contains("UID:c5d6f7a8\r\n"));
+        assert!(ics.contains("DTSTART:20221226T190000Z\r\nDTEND:20221226T210000Z\r\n"));
+        assert!(ics.contains("LOCATION:room-1\r\nRESOURCES:projector,vc-kit\r\n"));
+        assert!(ics.contains("STATUS:CONFIRMED\r\n"));
+        assert!(ics.ends_with("END:VEVENT\r\nEND:VCALENDAR\r\n"));
+        assert!(ics.lines().all(|line| line.len() <= MAX_LINE_OCTETS));
+    }
+
+    #[test]
+    fn parse_ics_should_read_exported_events() {
+        let rsvp = reservation();
+        let events =
+            parse_ics("tenant_id", "user_id", &to_ics(std::slice::from_ref(&rsvp))).unwrap();
+        assert_eq!(events.len(), 1);
+        let (uid, parsed) = &events[0];
+        assert_eq!(uid, "c5d6f7a8");
+        assert_eq!(
+            parsed.as_ref().unwrap(),
+            &Reservation {
+                id: "".into(),
+                ..rsvp
+            }
+        );
+    }
+
+    #[test]
+    fn parse_ics_should_end_events_after_duration() {
+        let ics = "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:1\r\nDTSTART:20221226T190000Z\r\nDURATION:P1DT1H30M\r\nLOCATION:room-1\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+        let events = parse_ics("tenant_id", "user_id", ics).unwrap();
+        let rsvp = events[0].1.as_ref().unwrap();
+        assert_eq!(
+            convert_to_utc_time(rsvp.end.clone().unwrap()),
+            "2022-12-27T20:30:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+
+        assert_eq!(parse_duration("P2W").unwrap(), Duration::weeks(2));
+        assert_eq!(parse_duration("PT45M").unwrap(), Duration::minutes(45));
+        for value in ["", "P", "PT", "-PT1H", "P1M", "PT1D", "P1H", "PT0S", "PT1"] {
+            assert!(parse_duration(value).is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn parse_ics_should_skip_cancelled_and_report_unsupported_events() {
+        let ics = "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:1\r\nSTATUS:CANCELLED\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+        assert!(parse_ics("tenant_id", "user_id", ics).unwrap().is_empty());
+
+        let ics = "BEGIN:VCALENDAR\r\n\
+            BEGIN:VEVENT\r\nUID:tz\r\nDTSTART;TZID=America/Denver:20221226T120000\r\nEND:VEVENT\r\n\
+            BEGIN:VEVENT\r\nUID:day\r\nDTSTART;VALUE=DATE:20221226\r\nEND:VEVENT\r\n\
+            BEGIN:VEVENT\r\nUID:floating\r\nDTSTART:20221226T120000\r\nEND:VEVENT\r\n\
+            BEGIN:VEVENT\r\nUID:broken\r\nno colon\r\nEND:VEVENT\r\n\
+            BEGIN:VEVENT\r\nUID:utc\r\nDTSTART:20221226T190000Z\r\nDTEND:20221226T210000Z\r\nEND:VEVENT\r\n\
+            END:VCALENDAR\r\n";
+        let events = parse_ics("tenant_id", "user_id", ics).unwrap();
+        let uids: Vec<_> = events.iter().map(|(uid, _)| uid.as_str()).collect();
+        assert_eq!(uids, ["tz", "day", "floating", "broken", "utc"]);
+        for (_, event) in &events[..4] {
+            assert!(matches!(event, Err(Error::InvalidCalendar(_))));
+        }
+        assert!(events[4].1.is_ok());
+
+        let ics = "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:1\r\nEND:VCALENDAR\r\n";
+        assert!(matches!(
+            parse_ics("tenant_id", "user_id", ics),
+            Err(Error::InvalidCalendar(_))
+        ));
+    }
+}
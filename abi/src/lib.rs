@@ -1,17 +1,23 @@
 mod error;
+mod ical;
 mod pb;
 mod types;
 mod utils;
 
 pub use error::{Error, ReservationConflict, ReservationConflictInfo, ReservationWindow};
+pub use ical::{parse_ics, to_ics, IcsEvent};
 pub use pb::*;
+pub use types::{utc_offset, Cursor};
 pub use utils::*;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
-#[sqlx(type_name = "reservation_status", rename_all = "lowercase")]
+#[sqlx(type_name = "reservation_status", rename_all = "snake_case")]
 pub enum RsvpStatus {
     Unknown,
     Pending,
     Confirmed,
     Blocked,
+    AwaitingApproval,
+    Rejected,
+    NoShow,
 }
@@ -14,11 +14,74 @@ pub struct Reservation {
     pub end: ::core::option::Option<::prost_types::Timestamp>,
     #[prost(string, tag = "7")]
     pub note: ::prost::alloc::string::String,
+    /// incremented on every change, used for optimistic concurrency
+    #[prost(int64, tag = "8")]
+    pub version: i64,
+    /// reservations of different tenants never see or conflict with each other
+    #[prost(string, tag = "9")]
+    pub tenant_id: ::prost::alloc::string::String,
+    /// other resources booked together with resource_id, reserved and cancelled as one unit
+    #[prost(string, repeated, tag = "10")]
+    pub extra_resource_ids: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// who approved or rejected the reservation, and why
+    #[prost(string, tag = "11")]
+    pub approver_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "12")]
+    pub approval_reason: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "13")]
+    pub checked_in_at: ::core::option::Option<::prost_types::Timestamp>,
+    /// quoted when the reservation was created or last rescheduled, in the smallest currency unit
+    #[prost(int64, tag = "14")]
+    pub price: i64,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Resource {
+    #[prost(string, tag = "1")]
+    pub id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub tenant_id: ::prost::alloc::string::String,
+    #[prost(bool, tag = "3")]
+    pub requires_approval: bool,
+    /// unset means the resource is free of charge
+    #[prost(message, optional, tag = "4")]
+    pub rate_card: ::core::option::Option<RateCard>,
+}
+/// a time of day on some weekdays, local to the rate card or report using it, given as minutes since midnight
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct WeeklyPeriod {
+    /// ISO weekdays, 1 is Monday and 7 is Sunday, empty means every day
+    #[prost(int32, repeated, tag = "1")]
+    pub weekdays: ::prost::alloc::vec::Vec<i32>,
+    #[prost(int32, tag = "2")]
+    pub start_minute: i32,
+    /// exclusive, at most 1440
+    #[prost(int32, tag = "3")]
+    pub end_minute: i32,
+}
+/// prices are in the smallest currency unit
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RateCard {
+    #[prost(int64, tag = "1")]
+    pub hourly_rate: i64,
+    /// charged instead of hourly_rate for time within a peak period
+    #[prost(int64, tag = "2")]
+    pub peak_hourly_rate: i64,
+    #[prost(message, repeated, tag = "3")]
+    pub peak_periods: ::prost::alloc::vec::Vec<WeeklyPeriod>,
+    /// charged if the booked time costs less
+    #[prost(int64, tag = "4")]
+    pub minimum_charge: i64,
+    /// offset of the resource's local time from UTC
+    #[prost(int32, tag = "5")]
+    pub utc_offset_minutes: i32,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ReserveRequest {
     #[prost(message, optional, tag = "1")]
     pub reservation: ::core::option::Option<Reservation>,
+    /// retries with the same key return the original reservation
+    #[prost(string, tag = "2")]
+    pub idempotency_key: ::prost::alloc::string::String,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ReserveResponse {
@@ -27,18 +90,53 @@ pub struct ReserveResponse {
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct UpdateRequest {
+    #[prost(string, tag = "1")]
+    pub id: ::prost::alloc::string::String,
     #[prost(string, tag = "2")]
     pub note: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub idempotency_key: ::prost::alloc::string::String,
+    /// reject the update if the reservation is not at this version, 0 means no check
+    #[prost(int64, tag = "4")]
+    pub expected_version: i64,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct UpdateResponse {
     #[prost(message, optional, tag = "1")]
     pub reservation: ::core::option::Option<Reservation>,
 }
+/// reserve many at once in one transaction, invalid or conflicting rows are reported and skipped
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BulkReserveRequest {
+    #[prost(message, repeated, tag = "1")]
+    pub reservations: ::prost::alloc::vec::Vec<Reservation>,
+    /// check every row against the database and the other rows, but write nothing
+    #[prost(bool, tag = "2")]
+    pub dry_run: bool,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BulkReserveFailure {
+    /// index of the reservation in the request
+    #[prost(int32, tag = "1")]
+    pub row: i32,
+    #[prost(string, tag = "2")]
+    pub reason: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BulkReserveResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub reservations: ::prost::alloc::vec::Vec<Reservation>,
+    #[prost(message, repeated, tag = "2")]
+    pub failures: ::prost::alloc::vec::Vec<BulkReserveFailure>,
+}
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ConfirmRequest {
     #[prost(string, tag = "1")]
     pub id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub idempotency_key: ::prost::alloc::string::String,
+    #[prost(int64, tag = "3")]
+    pub expected_version: i64,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ConfirmResponse {
@@ -49,6 +147,10 @@ pub struct ConfirmResponse {
 pub struct CancelRequest {
     #[prost(string, tag = "1")]
     pub id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub idempotency_key: ::prost::alloc::string::String,
+    #[prost(int64, tag = "3")]
+    pub expected_version: i64,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct CancelResponse {
@@ -83,12 +185,280 @@ pub struct ReservationQuery {
     pub page_size: i32,
     #[prost(bool, tag = "8")]
     pub desc: bool,
+    #[prost(string, tag = "9")]
+    pub tenant_id: ::prost::alloc::string::String,
+    /// next_cursor of the previous page, continues right after it and ignores page
+    #[prost(string, tag = "10")]
+    pub cursor: ::prost::alloc::string::String,
+    /// reservations in any of these statuses, together with status if it's set. none means every status
+    #[prost(enumeration = "ReservationStatus", repeated, tag = "11")]
+    pub statuses: ::prost::alloc::vec::Vec<i32>,
+    /// unset start or end leaves that side of the window open
+    #[prost(enumeration = "TimeMatch", tag = "12")]
+    pub time_match: i32,
+    /// words that must all appear in the note, ignoring case and punctuation
+    #[prost(string, tag = "13")]
+    pub search: ::prost::alloc::string::String,
+    /// text search configuration postgres comes with to stem the words with, like english. empty matches words as they are
+    #[prost(string, tag = "14")]
+    pub search_language: ::prost::alloc::string::String,
+    /// most relevant to search first instead of by start, can't be paged by cursor
+    #[prost(bool, tag = "15")]
+    pub by_relevance: bool,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct QueryRequest {
     #[prost(message, optional, tag = "1")]
     pub query: ::core::option::Option<ReservationQuery>,
 }
+/// counts and booked time of the reservations a query finds, its paging and order are ignored
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AggregateRequest {
+    #[prost(message, optional, tag = "1")]
+    pub query: ::core::option::Option<ReservationQuery>,
+    #[prost(enumeration = "AggregateGroup", tag = "2")]
+    pub group_by: i32,
+    /// days start at midnight at this offset from UTC
+    #[prost(int32, tag = "3")]
+    pub utc_offset_minutes: i32,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Aggregate {
+    /// resource id, user id, status like confirmed or day like 2022-12-25, empty without a group
+    #[prost(string, tag = "1")]
+    pub key: ::prost::alloc::string::String,
+    #[prost(int64, tag = "2")]
+    pub count: i64,
+    /// booked time inside the window of the query
+    #[prost(int64, tag = "3")]
+    pub seconds: i64,
+}
+/// ordered by key
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AggregateResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub aggregates: ::prost::alloc::vec::Vec<Aggregate>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct QueryPageResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub reservations: ::prost::alloc::vec::Vec<Reservation>,
+    /// empty once there is nothing left
+    #[prost(string, tag = "2")]
+    pub next_cursor: ::prost::alloc::string::String,
+}
+/// share of each resource's open time that is booked over a period, rejected reservations don't count
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UtilizationRequest {
+    #[prost(message, optional, tag = "1")]
+    pub start: ::core::option::Option<::prost_types::Timestamp>,
+    #[prost(message, optional, tag = "2")]
+    pub end: ::core::option::Option<::prost_types::Timestamp>,
+    /// empty means every resource booked in the period
+    #[prost(string, repeated, tag = "3")]
+    pub resource_ids: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// only time within opening hours counts, none means around the clock
+    #[prost(message, repeated, tag = "4")]
+    pub opening_hours: ::prost::alloc::vec::Vec<WeeklyPeriod>,
+    /// offset of the local time of hours, weekdays and opening hours from UTC
+    #[prost(int32, tag = "5")]
+    pub utc_offset_minutes: i32,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UtilizationSlot {
+    /// hour of day from 0, or ISO weekday
+    #[prost(int32, tag = "1")]
+    pub key: i32,
+    #[prost(int64, tag = "2")]
+    pub open_seconds: i64,
+    #[prost(int64, tag = "3")]
+    pub booked_seconds: i64,
+    /// booked over open time, 0 if it's never open
+    #[prost(double, tag = "4")]
+    pub ratio: f64,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ResourceUtilization {
+    #[prost(string, tag = "1")]
+    pub resource_id: ::prost::alloc::string::String,
+    #[prost(int64, tag = "2")]
+    pub open_seconds: i64,
+    #[prost(int64, tag = "3")]
+    pub booked_seconds: i64,
+    #[prost(double, tag = "4")]
+    pub ratio: f64,
+    /// 24 hours from midnight
+    #[prost(message, repeated, tag = "5")]
+    pub by_hour: ::prost::alloc::vec::Vec<UtilizationSlot>,
+    /// 7 weekdays from Monday
+    #[prost(message, repeated, tag = "6")]
+    pub by_weekday: ::prost::alloc::vec::Vec<UtilizationSlot>,
+}
+/// ordered by resource id
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UtilizationResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub resources: ::prost::alloc::vec::Vec<ResourceUtilization>,
+}
+/// booking a parent resource blocks all its descendants, and the reverse
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UpdateResourceRequest {
+    #[prost(message, optional, tag = "1")]
+    pub resource: ::core::option::Option<Resource>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UpdateResourceResponse {
+    #[prost(message, optional, tag = "1")]
+    pub resource: ::core::option::Option<Resource>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ApproveRequest {
+    #[prost(string, tag = "1")]
+    pub id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub approver_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub reason: ::prost::alloc::string::String,
+    #[prost(string, tag = "4")]
+    pub idempotency_key: ::prost::alloc::string::String,
+    #[prost(int64, tag = "5")]
+    pub expected_version: i64,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ApproveResponse {
+    #[prost(message, optional, tag = "1")]
+    pub reservation: ::core::option::Option<Reservation>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RejectRequest {
+    #[prost(string, tag = "1")]
+    pub id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub approver_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub reason: ::prost::alloc::string::String,
+    #[prost(string, tag = "4")]
+    pub idempotency_key: ::prost::alloc::string::String,
+    #[prost(int64, tag = "5")]
+    pub expected_version: i64,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RejectResponse {
+    #[prost(message, optional, tag = "1")]
+    pub reservation: ::core::option::Option<Reservation>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CheckInRequest {
+    #[prost(string, tag = "1")]
+    pub id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub idempotency_key: ::prost::alloc::string::String,
+    #[prost(int64, tag = "3")]
+    pub expected_version: i64,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CheckInResponse {
+    #[prost(message, optional, tag = "1")]
+    pub reservation: ::core::option::Option<Reservation>,
+}
+/// price the reservation would have if it were reserved now, nothing is booked
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct QuoteRequest {
+    #[prost(message, optional, tag = "1")]
+    pub reservation: ::core::option::Option<Reservation>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct QuoteResponse {
+    #[prost(int64, tag = "1")]
+    pub price: i64,
+}
+/// move the reservation to a new window, its price is quoted again
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RescheduleRequest {
+    #[prost(string, tag = "1")]
+    pub id: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "2")]
+    pub start: ::core::option::Option<::prost_types::Timestamp>,
+    #[prost(message, optional, tag = "3")]
+    pub end: ::core::option::Option<::prost_types::Timestamp>,
+    #[prost(string, tag = "4")]
+    pub idempotency_key: ::prost::alloc::string::String,
+    #[prost(int64, tag = "5")]
+    pub expected_version: i64,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RescheduleResponse {
+    #[prost(message, optional, tag = "1")]
+    pub reservation: ::core::option::Option<Reservation>,
+}
+/// token of the user's calendar feed, served at /calendar/{token}.ics
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct FeedTokenRequest {
+    #[prost(string, tag = "1")]
+    pub user_id: ::prost::alloc::string::String,
+    /// replace the token, the old feed url stops working
+    #[prost(bool, tag = "2")]
+    pub reset: bool,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct FeedTokenResponse {
+    #[prost(string, tag = "1")]
+    pub token: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct LinkResourcesRequest {
+    #[prost(string, tag = "1")]
+    pub parent_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub child_id: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct LinkResourcesResponse {}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UnlinkResourcesRequest {
+    #[prost(string, tag = "1")]
+    pub parent_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub child_id: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UnlinkResourcesResponse {}
+/// changes are POSTed to url as JSON, signed with HMAC-SHA256 of the secret
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Webhook {
+    #[prost(string, tag = "1")]
+    pub id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub tenant_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub url: ::prost::alloc::string::String,
+    #[prost(string, tag = "4")]
+    pub secret: ::prost::alloc::string::String,
+    /// filters, empty means every resource, user or op
+    #[prost(string, tag = "5")]
+    pub resource_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "6")]
+    pub user_id: ::prost::alloc::string::String,
+    #[prost(enumeration = "ReservationUpdateType", repeated, tag = "7")]
+    pub ops: ::prost::alloc::vec::Vec<i32>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeWebhookRequest {
+    #[prost(message, optional, tag = "1")]
+    pub webhook: ::core::option::Option<Webhook>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeWebhookResponse {
+    #[prost(message, optional, tag = "1")]
+    pub webhook: ::core::option::Option<Webhook>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UnsubscribeWebhookRequest {
+    #[prost(string, tag = "1")]
+    pub id: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UnsubscribeWebhookResponse {}
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ListenRequest {}
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -107,6 +477,11 @@ pub enum ReservationStatus {
     Pending = 1,
     Confirmed = 2,
     Blocked = 3,
+    /// reservations of resources requiring approval start here, only approved ones can be confirmed
+    AwaitingApproval = 4,
+    Rejected = 5,
+    /// not checked in within the grace period, the remaining time was released
+    NoShow = 6,
 }
 impl ReservationStatus {
     /// String value of the enum field names used in the ProtoBuf definition.
@@ -119,6 +494,9 @@ impl ReservationStatus {
             ReservationStatus::Pending => "RESERVATION_STATUS_PENDING",
             ReservationStatus::Confirmed => "RESERVATION_STATUS_CONFIRMED",
             ReservationStatus::Blocked => "RESERVATION_STATUS_BLOCKED",
+            ReservationStatus::AwaitingApproval => "RESERVATION_STATUS_AWAITING_APPROVAL",
+            ReservationStatus::Rejected => "RESERVATION_STATUS_REJECTED",
+            ReservationStatus::NoShow => "RESERVATION_STATUS_NO_SHOW",
         }
     }
 }
@@ -144,11 +522,66 @@ impl ReservationUpdateType {
         }
     }
 }
+/// how the window of a reservation is compared with the window of a query
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum TimeMatch {
+    /// the reservation lies completely inside the window
+    ContainedIn = 0,
+    /// the reservation shares at least one instant with the window
+    Overlaps = 1,
+    /// the reservation covers the whole window
+    Contains = 2,
+    /// the reservation starts inside the window, wherever it ends
+    StartsWithin = 3,
+}
+impl TimeMatch {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            TimeMatch::ContainedIn => "TIME_MATCH_CONTAINED_IN",
+            TimeMatch::Overlaps => "TIME_MATCH_OVERLAPS",
+            TimeMatch::Contains => "TIME_MATCH_CONTAINS",
+            TimeMatch::StartsWithin => "TIME_MATCH_STARTS_WITHIN",
+        }
+    }
+}
+/// what aggregates are grouped by, none gives a single total
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum AggregateGroup {
+    None = 0,
+    /// a reservation counts for every resource it holds, or only the one the query is for
+    Resource = 1,
+    User = 2,
+    Status = 3,
+    /// the day the reservation starts, all its time counts there even if it runs past midnight
+    Day = 4,
+}
+impl AggregateGroup {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            AggregateGroup::None => "AGGREGATE_GROUP_NONE",
+            AggregateGroup::Resource => "AGGREGATE_GROUP_RESOURCE",
+            AggregateGroup::User => "AGGREGATE_GROUP_USER",
+            AggregateGroup::Status => "AGGREGATE_GROUP_STATUS",
+            AggregateGroup::Day => "AGGREGATE_GROUP_DAY",
+        }
+    }
+}
 /// Generated client implementations.
 pub mod reservation_service_client {
     #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
     use tonic::codegen::http::Uri;
     use tonic::codegen::*;
+    /// every call is scoped to the caller's tenant, tenant_id in requests is overwritten by the server
     #[derive(Debug, Clone)]
     pub struct ReservationServiceClient<T> {
         inner: tonic::client::Grpc<T>,
@@ -227,6 +660,22 @@ pub mod reservation_service_client {
                 http::uri::PathAndQuery::from_static("/reservation.ReservationService/reserve");
             self.inner.unary(request.into_request(), path, codec).await
         }
+        pub async fn bulk_reserve(
+            &mut self,
+            request: impl tonic::IntoRequest<super::BulkReserveRequest>,
+        ) -> Result<tonic::Response<super::BulkReserveResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/reservation.ReservationService/bulk_reserve",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
         pub async fn confirm(
             &mut self,
             request: impl tonic::IntoRequest<super::ConfirmRequest>,
@@ -304,6 +753,51 @@ pub mod reservation_service_client {
                 .server_streaming(request.into_request(), path, codec)
                 .await
         }
+        pub async fn query_page(
+            &mut self,
+            request: impl tonic::IntoRequest<super::QueryRequest>,
+        ) -> Result<tonic::Response<super::QueryPageResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path =
+                http::uri::PathAndQuery::from_static("/reservation.ReservationService/query_page");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn aggregate(
+            &mut self,
+            request: impl tonic::IntoRequest<super::AggregateRequest>,
+        ) -> Result<tonic::Response<super::AggregateResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path =
+                http::uri::PathAndQuery::from_static("/reservation.ReservationService/aggregate");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn utilization(
+            &mut self,
+            request: impl tonic::IntoRequest<super::UtilizationRequest>,
+        ) -> Result<tonic::Response<super::UtilizationResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path =
+                http::uri::PathAndQuery::from_static("/reservation.ReservationService/utilization");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
         pub async fn listen(
             &mut self,
             request: impl tonic::IntoRequest<super::ListenRequest>,
@@ -322,57 +816,288 @@ pub mod reservation_service_client {
                 .server_streaming(request.into_request(), path, codec)
                 .await
         }
-    }
-}
-/// Generated server implementations.
-pub mod reservation_service_server {
-    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
-    use tonic::codegen::*;
-    ///Generated trait containing gRPC methods that should be implemented for use with ReservationServiceServer.
-    #[async_trait]
-    pub trait ReservationService: Send + Sync + 'static {
-        async fn reserve(
-            &self,
-            request: tonic::Request<super::ReserveRequest>,
-        ) -> Result<tonic::Response<super::ReserveResponse>, tonic::Status>;
-        async fn confirm(
-            &self,
-            request: tonic::Request<super::ConfirmRequest>,
-        ) -> Result<tonic::Response<super::ConfirmResponse>, tonic::Status>;
-        async fn update(
-            &self,
-            request: tonic::Request<super::UpdateRequest>,
-        ) -> Result<tonic::Response<super::UpdateResponse>, tonic::Status>;
-        async fn cancel(
-            &self,
-            request: tonic::Request<super::CancelRequest>,
-        ) -> Result<tonic::Response<super::CancelResponse>, tonic::Status>;
-        async fn get(
-            &self,
-            request: tonic::Request<super::GetRequest>,
-        ) -> Result<tonic::Response<super::GetResponse>, tonic::Status>;
-        ///Server streaming response type for the query method.
-        type queryStream: futures_core::Stream<Item = Result<super::Reservation, tonic::Status>>
-            + Send
-            + 'static;
-        async fn query(
-            &self,
-            request: tonic::Request<super::QueryRequest>,
-        ) -> Result<tonic::Response<Self::queryStream>, tonic::Status>;
-        ///Server streaming response type for the listen method.
-        type listenStream: futures_core::Stream<Item = Result<super::Reservation, tonic::Status>>
-            + Send
-            + 'static;
-        async fn listen(
-            &self,
-            request: tonic::Request<super::ListenRequest>,
-        ) -> Result<tonic::Response<Self::listenStream>, tonic::Status>;
-    }
-    #[derive(Debug)]
-    pub struct ReservationServiceServer<T: ReservationService> {
-        inner: _Inner<T>,
-        accept_compression_encodings: EnabledCompressionEncodings,
-        send_compression_encodings: EnabledCompressionEncodings,
+        pub async fn link_resources(
+            &mut self,
+            request: impl tonic::IntoRequest<super::LinkResourcesRequest>,
+        ) -> Result<tonic::Response<super::LinkResourcesResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/reservation.ReservationService/link_resources",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn unlink_resources(
+            &mut self,
+            request: impl tonic::IntoRequest<super::UnlinkResourcesRequest>,
+        ) -> Result<tonic::Response<super::UnlinkResourcesResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/reservation.ReservationService/unlink_resources",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn update_resource(
+            &mut self,
+            request: impl tonic::IntoRequest<super::UpdateResourceRequest>,
+        ) -> Result<tonic::Response<super::UpdateResourceResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/reservation.ReservationService/update_resource",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn approve(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ApproveRequest>,
+        ) -> Result<tonic::Response<super::ApproveResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path =
+                http::uri::PathAndQuery::from_static("/reservation.ReservationService/approve");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn reject(
+            &mut self,
+            request: impl tonic::IntoRequest<super::RejectRequest>,
+        ) -> Result<tonic::Response<super::RejectResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path =
+                http::uri::PathAndQuery::from_static("/reservation.ReservationService/reject");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn check_in(
+            &mut self,
+            request: impl tonic::IntoRequest<super::CheckInRequest>,
+        ) -> Result<tonic::Response<super::CheckInResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path =
+                http::uri::PathAndQuery::from_static("/reservation.ReservationService/check_in");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn quote(
+            &mut self,
+            request: impl tonic::IntoRequest<super::QuoteRequest>,
+        ) -> Result<tonic::Response<super::QuoteResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path =
+                http::uri::PathAndQuery::from_static("/reservation.ReservationService/quote");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn reschedule(
+            &mut self,
+            request: impl tonic::IntoRequest<super::RescheduleRequest>,
+        ) -> Result<tonic::Response<super::RescheduleResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path =
+                http::uri::PathAndQuery::from_static("/reservation.ReservationService/reschedule");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn feed_token(
+            &mut self,
+            request: impl tonic::IntoRequest<super::FeedTokenRequest>,
+        ) -> Result<tonic::Response<super::FeedTokenResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path =
+                http::uri::PathAndQuery::from_static("/reservation.ReservationService/feed_token");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn subscribe_webhook(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SubscribeWebhookRequest>,
+        ) -> Result<tonic::Response<super::SubscribeWebhookResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/reservation.ReservationService/subscribe_webhook",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn unsubscribe_webhook(
+            &mut self,
+            request: impl tonic::IntoRequest<super::UnsubscribeWebhookRequest>,
+        ) -> Result<tonic::Response<super::UnsubscribeWebhookResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/reservation.ReservationService/unsubscribe_webhook",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+    }
+}
+/// Generated server implementations.
+pub mod reservation_service_server {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use tonic::codegen::*;
+    ///Generated trait containing gRPC methods that should be implemented for use with ReservationServiceServer.
+    #[async_trait]
+    pub trait ReservationService: Send + Sync + 'static {
+        async fn reserve(
+            &self,
+            request: tonic::Request<super::ReserveRequest>,
+        ) -> Result<tonic::Response<super::ReserveResponse>, tonic::Status>;
+        async fn bulk_reserve(
+            &self,
+            request: tonic::Request<super::BulkReserveRequest>,
+        ) -> Result<tonic::Response<super::BulkReserveResponse>, tonic::Status>;
+        async fn confirm(
+            &self,
+            request: tonic::Request<super::ConfirmRequest>,
+        ) -> Result<tonic::Response<super::ConfirmResponse>, tonic::Status>;
+        async fn update(
+            &self,
+            request: tonic::Request<super::UpdateRequest>,
+        ) -> Result<tonic::Response<super::UpdateResponse>, tonic::Status>;
+        async fn cancel(
+            &self,
+            request: tonic::Request<super::CancelRequest>,
+        ) -> Result<tonic::Response<super::CancelResponse>, tonic::Status>;
+        async fn get(
+            &self,
+            request: tonic::Request<super::GetRequest>,
+        ) -> Result<tonic::Response<super::GetResponse>, tonic::Status>;
+        ///Server streaming response type for the query method.
+        type queryStream: futures_core::Stream<Item = Result<super::Reservation, tonic::Status>>
+            + Send
+            + 'static;
+        async fn query(
+            &self,
+            request: tonic::Request<super::QueryRequest>,
+        ) -> Result<tonic::Response<Self::queryStream>, tonic::Status>;
+        async fn query_page(
+            &self,
+            request: tonic::Request<super::QueryRequest>,
+        ) -> Result<tonic::Response<super::QueryPageResponse>, tonic::Status>;
+        async fn aggregate(
+            &self,
+            request: tonic::Request<super::AggregateRequest>,
+        ) -> Result<tonic::Response<super::AggregateResponse>, tonic::Status>;
+        async fn utilization(
+            &self,
+            request: tonic::Request<super::UtilizationRequest>,
+        ) -> Result<tonic::Response<super::UtilizationResponse>, tonic::Status>;
+        ///Server streaming response type for the listen method.
+        type listenStream: futures_core::Stream<Item = Result<super::Reservation, tonic::Status>>
+            + Send
+            + 'static;
+        async fn listen(
+            &self,
+            request: tonic::Request<super::ListenRequest>,
+        ) -> Result<tonic::Response<Self::listenStream>, tonic::Status>;
+        async fn link_resources(
+            &self,
+            request: tonic::Request<super::LinkResourcesRequest>,
+        ) -> Result<tonic::Response<super::LinkResourcesResponse>, tonic::Status>;
+        async fn unlink_resources(
+            &self,
+            request: tonic::Request<super::UnlinkResourcesRequest>,
+        ) -> Result<tonic::Response<super::UnlinkResourcesResponse>, tonic::Status>;
+        async fn update_resource(
+            &self,
+            request: tonic::Request<super::UpdateResourceRequest>,
+        ) -> Result<tonic::Response<super::UpdateResourceResponse>, tonic::Status>;
+        async fn approve(
+            &self,
+            request: tonic::Request<super::ApproveRequest>,
+        ) -> Result<tonic::Response<super::ApproveResponse>, tonic::Status>;
+        async fn reject(
+            &self,
+            request: tonic::Request<super::RejectRequest>,
+        ) -> Result<tonic::Response<super::RejectResponse>, tonic::Status>;
+        async fn check_in(
+            &self,
+            request: tonic::Request<super::CheckInRequest>,
+        ) -> Result<tonic::Response<super::CheckInResponse>, tonic::Status>;
+        async fn quote(
+            &self,
+            request: tonic::Request<super::QuoteRequest>,
+        ) -> Result<tonic::Response<super::QuoteResponse>, tonic::Status>;
+        async fn reschedule(
+            &self,
+            request: tonic::Request<super::RescheduleRequest>,
+        ) -> Result<tonic::Response<super::RescheduleResponse>, tonic::Status>;
+        async fn feed_token(
+            &self,
+            request: tonic::Request<super::FeedTokenRequest>,
+        ) -> Result<tonic::Response<super::FeedTokenResponse>, tonic::Status>;
+        async fn subscribe_webhook(
+            &self,
+            request: tonic::Request<super::SubscribeWebhookRequest>,
+        ) -> Result<tonic::Response<super::SubscribeWebhookResponse>, tonic::Status>;
+        async fn unsubscribe_webhook(
+            &self,
+            request: tonic::Request<super::UnsubscribeWebhookRequest>,
+        ) -> Result<tonic::Response<super::UnsubscribeWebhookResponse>, tonic::Status>;
+    }
+    /// every call is scoped to the caller's tenant, tenant_id in requests is overwritten by the server
+    #[derive(Debug)]
+    pub struct ReservationServiceServer<T: ReservationService> {
+        inner: _Inner<T>,
+        accept_compression_encodings: EnabledCompressionEncodings,
+        send_compression_encodings: EnabledCompressionEncodings,
     }
     struct _Inner<T>(Arc<T>);
     impl<T: ReservationService> ReservationServiceServer<T> {
@@ -452,6 +1177,40 @@ pub mod reservation_service_server {
                     };
                     Box::pin(fut)
                 }
+                "/reservation.ReservationService/bulk_reserve" => {
+                    #[allow(non_camel_case_types)]
+                    struct bulk_reserveSvc<T: ReservationService>(pub Arc<T>);
+                    impl<T: ReservationService>
+                        tonic::server::UnaryService<super::BulkReserveRequest>
+                        for bulk_reserveSvc<T>
+                    {
+                        type Response = super::BulkReserveResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::BulkReserveRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).bulk_reserve(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = bulk_reserveSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 "/reservation.ReservationService/confirm" => {
                     #[allow(non_camel_case_types)]
                     struct confirmSvc<T: ReservationService>(pub Arc<T>);
@@ -611,23 +1370,51 @@ pub mod reservation_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/reservation.ReservationService/listen" => {
+                "/reservation.ReservationService/query_page" => {
                     #[allow(non_camel_case_types)]
-                    struct listenSvc<T: ReservationService>(pub Arc<T>);
-                    impl<T: ReservationService>
-                        tonic::server::ServerStreamingService<super::ListenRequest>
-                        for listenSvc<T>
+                    struct query_pageSvc<T: ReservationService>(pub Arc<T>);
+                    impl<T: ReservationService> tonic::server::UnaryService<super::QueryRequest> for query_pageSvc<T> {
+                        type Response = super::QueryPageResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::QueryRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).query_page(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = query_pageSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/reservation.ReservationService/aggregate" => {
+                    #[allow(non_camel_case_types)]
+                    struct aggregateSvc<T: ReservationService>(pub Arc<T>);
+                    impl<T: ReservationService> tonic::server::UnaryService<super::AggregateRequest>
+                        for aggregateSvc<T>
                     {
-                        type Response = super::Reservation;
-                        type ResponseStream = T::listenStream;
-                        type Future =
-                            BoxFuture<tonic::Response<Self::ResponseStream>, tonic::Status>;
+                        type Response = super::AggregateResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::ListenRequest>,
+                            request: tonic::Request<super::AggregateRequest>,
                         ) -> Self::Future {
                             let inner = self.0.clone();
-                            let fut = async move { (*inner).listen(request).await };
+                            let fut = async move { (*inner).aggregate(request).await };
                             Box::pin(fut)
                         }
                     }
@@ -636,13 +1423,443 @@ pub mod reservation_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = listenSvc(inner);
+                        let method = aggregateSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
                             accept_compression_encodings,
                             send_compression_encodings,
                         );
-                        let res = grpc.server_streaming(method, req).await;
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/reservation.ReservationService/utilization" => {
+                    #[allow(non_camel_case_types)]
+                    struct utilizationSvc<T: ReservationService>(pub Arc<T>);
+                    impl<T: ReservationService>
+                        tonic::server::UnaryService<super::UtilizationRequest>
+                        for utilizationSvc<T>
+                    {
+                        type Response = super::UtilizationResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::UtilizationRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).utilization(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = utilizationSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/reservation.ReservationService/listen" => {
+                    #[allow(non_camel_case_types)]
+                    struct listenSvc<T: ReservationService>(pub Arc<T>);
+                    impl<T: ReservationService>
+                        tonic::server::ServerStreamingService<super::ListenRequest>
+                        for listenSvc<T>
+                    {
+                        type Response = super::Reservation;
+                        type ResponseStream = T::listenStream;
+                        type Future =
+                            BoxFuture<tonic::Response<Self::ResponseStream>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ListenRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).listen(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = listenSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/reservation.ReservationService/link_resources" => {
+                    #[allow(non_camel_case_types)]
+                    struct link_resourcesSvc<T: ReservationService>(pub Arc<T>);
+                    impl<T: ReservationService>
+                        tonic::server::UnaryService<super::LinkResourcesRequest>
+                        for link_resourcesSvc<T>
+                    {
+                        type Response = super::LinkResourcesResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::LinkResourcesRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).link_resources(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = link_resourcesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/reservation.ReservationService/unlink_resources" => {
+                    #[allow(non_camel_case_types)]
+                    struct unlink_resourcesSvc<T: ReservationService>(pub Arc<T>);
+                    impl<T: ReservationService>
+                        tonic::server::UnaryService<super::UnlinkResourcesRequest>
+                        for unlink_resourcesSvc<T>
+                    {
+                        type Response = super::UnlinkResourcesResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::UnlinkResourcesRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).unlink_resources(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = unlink_resourcesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/reservation.ReservationService/update_resource" => {
+                    #[allow(non_camel_case_types)]
+                    struct update_resourceSvc<T: ReservationService>(pub Arc<T>);
+                    impl<T: ReservationService>
+                        tonic::server::UnaryService<super::UpdateResourceRequest>
+                        for update_resourceSvc<T>
+                    {
+                        type Response = super::UpdateResourceResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::UpdateResourceRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).update_resource(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = update_resourceSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/reservation.ReservationService/approve" => {
+                    #[allow(non_camel_case_types)]
+                    struct approveSvc<T: ReservationService>(pub Arc<T>);
+                    impl<T: ReservationService> tonic::server::UnaryService<super::ApproveRequest> for approveSvc<T> {
+                        type Response = super::ApproveResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ApproveRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).approve(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = approveSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/reservation.ReservationService/reject" => {
+                    #[allow(non_camel_case_types)]
+                    struct rejectSvc<T: ReservationService>(pub Arc<T>);
+                    impl<T: ReservationService> tonic::server::UnaryService<super::RejectRequest> for rejectSvc<T> {
+                        type Response = super::RejectResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::RejectRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).reject(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = rejectSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/reservation.ReservationService/check_in" => {
+                    #[allow(non_camel_case_types)]
+                    struct check_inSvc<T: ReservationService>(pub Arc<T>);
+                    impl<T: ReservationService> tonic::server::UnaryService<super::CheckInRequest> for check_inSvc<T> {
+                        type Response = super::CheckInResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::CheckInRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).check_in(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = check_inSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/reservation.ReservationService/quote" => {
+                    #[allow(non_camel_case_types)]
+                    struct quoteSvc<T: ReservationService>(pub Arc<T>);
+                    impl<T: ReservationService> tonic::server::UnaryService<super::QuoteRequest> for quoteSvc<T> {
+                        type Response = super::QuoteResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::QuoteRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).quote(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = quoteSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/reservation.ReservationService/reschedule" => {
+                    #[allow(non_camel_case_types)]
+                    struct rescheduleSvc<T: ReservationService>(pub Arc<T>);
+                    impl<T: ReservationService>
+                        tonic::server::UnaryService<super::RescheduleRequest> for rescheduleSvc<T>
+                    {
+                        type Response = super::RescheduleResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::RescheduleRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).reschedule(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = rescheduleSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/reservation.ReservationService/feed_token" => {
+                    #[allow(non_camel_case_types)]
+                    struct feed_tokenSvc<T: ReservationService>(pub Arc<T>);
+                    impl<T: ReservationService> tonic::server::UnaryService<super::FeedTokenRequest>
+                        for feed_tokenSvc<T>
+                    {
+                        type Response = super::FeedTokenResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::FeedTokenRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).feed_token(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = feed_tokenSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/reservation.ReservationService/subscribe_webhook" => {
+                    #[allow(non_camel_case_types)]
+                    struct subscribe_webhookSvc<T: ReservationService>(pub Arc<T>);
+                    impl<T: ReservationService>
+                        tonic::server::UnaryService<super::SubscribeWebhookRequest>
+                        for subscribe_webhookSvc<T>
+                    {
+                        type Response = super::SubscribeWebhookResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SubscribeWebhookRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).subscribe_webhook(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = subscribe_webhookSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/reservation.ReservationService/unsubscribe_webhook" => {
+                    #[allow(non_camel_case_types)]
+                    struct unsubscribe_webhookSvc<T: ReservationService>(pub Arc<T>);
+                    impl<T: ReservationService>
+                        tonic::server::UnaryService<super::UnsubscribeWebhookRequest>
+                        for unsubscribe_webhookSvc<T>
+                    {
+                        type Response = super::UnsubscribeWebhookResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::UnsubscribeWebhookRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).unsubscribe_webhook(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = unsubscribe_webhookSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
                         Ok(res)
                     };
                     Box::pin(fut)
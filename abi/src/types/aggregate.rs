@@ -0,0 +1,20 @@
+use crate::{Aggregate, Error};
+use chrono::FixedOffset;
+
+impl Aggregate {
+    pub fn new(key: impl Into<String>, count: i64, seconds: i64) -> Self {
+        Self {
+            key: key.into(),
+            count,
+            seconds,
+        }
+    }
+}
+
+/// the offset of local days from UTC, less than a day either way
+pub fn utc_offset(minutes: i32) -> Result<FixedOffset, Error> {
+    minutes
+        .checked_mul(60)
+        .and_then(FixedOffset::east_opt)
+        .ok_or(Error::InvalidTime)
+}
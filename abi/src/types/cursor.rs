@@ -0,0 +1,82 @@
+use crate::{convert_to_utc_time, Error, QueryPageResponse, Reservation};
+use chrono::{DateTime, TimeZone, Utc};
+use sqlx::types::Uuid;
+
+/// position of a reservation in query order, reservations are ordered by (start, id)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cursor {
+    pub start: DateTime<Utc>,
+    pub id: Uuid,
+}
+
+impl Cursor {
+    pub fn new(start: DateTime<Utc>, id: Uuid) -> Self {
+        Self { start, id }
+    }
+
+    /// the position right after the reservation
+    pub fn after(rsvp: &Reservation) -> Result<Self, Error> {
+        let start = rsvp.start.clone().ok_or(Error::InvalidTime)?;
+        let id =
+            Uuid::parse_str(&rsvp.id).map_err(|_| Error::InvalidReservationId(rsvp.id.clone()))?;
+        Ok(Self::new(convert_to_utc_time(start), id))
+    }
+
+    /// opaque url safe token, 8 bytes of start micros followed by the 16 bytes of the id
+    pub fn encode(&self) -> String {
+        let mut buf = self.start.timestamp_micros().to_be_bytes().to_vec();
+        buf.extend_from_slice(self.id.as_bytes());
+        base64::encode_config(buf, base64::URL_SAFE_NO_PAD)
+    }
+
+    pub fn decode(token: &str) -> Result<Self, Error> {
+        let invalid = || Error::InvalidCursor(token.to_string());
+        let buf = base64::decode_config(token, base64::URL_SAFE_NO_PAD).map_err(|_| invalid())?;
+        if buf.len() != 24 {
+            return Err(invalid());
+        }
+        let micros = i64::from_be_bytes(buf[..8].try_into().unwrap());
+        let start = Utc.timestamp_opt(micros.div_euclid(1_000_000), 0).single();
+        let start = start.ok_or_else(invalid)?
+            + chrono::Duration::microseconds(micros.rem_euclid(1_000_000));
+        let id = Uuid::from_slice(&buf[8..]).map_err(|_| invalid())?;
+        Ok(Self::new(start, id))
+    }
+}
+
+impl QueryPageResponse {
+    /// a full page may be followed by more, a short one is the last
+    pub fn new(reservations: Vec<Reservation>, page_size: i32) -> Result<Self, Error> {
+        let next_cursor = match reservations.last() {
+            Some(last) if reservations.len() as i32 >= page_size => Cursor::after(last)?.encode(),
+            _ => String::new(),
+        };
+        Ok(Self {
+            reservations,
+            next_cursor,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_should_round_trip() {
+        let cursor = Cursor::new(
+            "2022-12-25T12:00:00.123456Z".parse().unwrap(),
+            "67e55044-10b1-426f-9247-bb680e5fe0c8".parse().unwrap(),
+        );
+        let token = cursor.encode();
+        assert_eq!(Cursor::decode(&token).unwrap(), cursor);
+    }
+
+    #[test]
+    fn garbage_cursor_should_be_rejected() {
+        for token in ["", "not a cursor", "AAAA"] {
+            let err = Cursor::decode(token).unwrap_err();
+            assert!(matches!(err, Error::InvalidCursor(_)), "{:?}", err);
+        }
+    }
+}
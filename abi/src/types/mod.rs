@@ -3,9 +3,20 @@ use chrono::{DateTime, Utc};
 use prost_types::Timestamp;
 use sqlx::postgres::types::PgRange;
 
+mod aggregate;
+mod cursor;
+mod rate_card;
 mod reservation;
 mod reservation_query;
 mod reservation_status;
+mod reservation_update_type;
+mod resource;
+mod utilization;
+mod webhook;
+mod weekly_period;
+
+pub use aggregate::utc_offset;
+pub use cursor::Cursor;
 
 pub fn validate_range(start: Option<&Timestamp>, end: Option<&Timestamp>) -> Result<(), Error> {
     if start.is_none() || end.is_none() {
@@ -0,0 +1,180 @@
+use crate::{Error, RateCard};
+use chrono::{DateTime, Datelike, Duration, FixedOffset, Timelike, Utc};
+
+const MINUTES_PER_DAY: i32 = 24 * 60;
+
+impl RateCard {
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.hourly_rate < 0 || self.peak_hourly_rate < 0 || self.minimum_charge < 0 {
+            return Err(Error::InvalidRateCard("negative price".into()));
+        }
+
+        if self.utc_offset_minutes.abs() >= MINUTES_PER_DAY {
+            return Err(Error::InvalidRateCard(format!(
+                "utc offset {} out of range",
+                self.utc_offset_minutes
+            )));
+        }
+
+        for period in &self.peak_periods {
+            period.validate(Error::InvalidRateCard)?;
+        }
+
+        Ok(())
+    }
+
+    /// price of booking the window with every card, e.g. for each resource of a reservation
+    pub fn quote_all<'a>(
+        cards: impl IntoIterator<Item = &'a RateCard>,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<i64, Error> {
+        cards.into_iter().try_fold(0i64, |total, card| {
+            total
+                .checked_add(card.quote(start, end)?)
+                .ok_or_else(out_of_range)
+        })
+    }
+
+    /// price of booking the window, peak and off-peak time are charged at their own rates
+    pub fn quote(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<i64, Error> {
+        if start >= end {
+            return Ok(0);
+        }
+
+        let offset = FixedOffset::east_opt(self.utc_offset_minutes * 60).unwrap();
+        let end = end.with_timezone(&offset);
+        let mut current = start.with_timezone(&offset);
+        let (mut peak, mut off_peak) = (0i128, 0i128);
+        // within a segment the rate doesn't change
+        while current < end {
+            let next = self.next_boundary(current).min(end);
+            let seconds = (next - current).num_seconds() as i128;
+            if self.is_peak(current) {
+                peak += seconds;
+            } else {
+                off_peak += seconds;
+            }
+            current = next;
+        }
+
+        let cost = peak * self.peak_hourly_rate as i128 + off_peak * self.hourly_rate as i128;
+        // round to the nearest unit
+        let cost = i64::try_from((cost + 1800) / 3600).map_err(|_| out_of_range())?;
+        Ok(cost.max(self.minimum_charge))
+    }
+
+    fn is_peak(&self, t: DateTime<FixedOffset>) -> bool {
+        let weekday = t.weekday().number_from_monday() as i32;
+        let second = t.num_seconds_from_midnight() as i32;
+        self.peak_periods.iter().any(|p| {
+            p.applies_on(weekday) && p.start_minute * 60 <= second && second < p.end_minute * 60
+        })
+    }
+
+    /// the next time after t where a peak period starts or ends, or the next midnight
+    fn next_boundary(&self, t: DateTime<FixedOffset>) -> DateTime<FixedOffset> {
+        let second = t.num_seconds_from_midnight() as i32;
+        let next = self
+            .peak_periods
+            .iter()
+            .flat_map(|p| [p.start_minute * 60, p.end_minute * 60])
+            .filter(|s| *s > second)
+            .min()
+            .unwrap_or(MINUTES_PER_DAY * 60);
+        t - Duration::nanoseconds(t.nanosecond() as i64) + Duration::seconds((next - second) as i64)
+    }
+}
+
+fn out_of_range() -> Error {
+    Error::InvalidRateCard("price out of range".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WeeklyPeriod;
+
+    fn card() -> RateCard {
+        RateCard {
+            hourly_rate: 1000,
+            peak_hourly_rate: 3000,
+            // weekdays 9:00 - 17:00
+            peak_periods: vec![WeeklyPeriod::new([1, 2, 3, 4, 5], 9 * 60, 17 * 60)],
+            minimum_charge: 500,
+            utc_offset_minutes: 0,
+        }
+    }
+
+    fn quote(card: &RateCard, start: &str, end: &str) -> i64 {
+        card.quote(start.parse().unwrap(), end.parse().unwrap())
+            .unwrap()
+    }
+
+    #[test]
+    fn quote_should_charge_peak_and_off_peak_separately() {
+        // 2022-12-26 is a monday, 2 off-peak hours then 2 peak hours
+        let price = quote(&card(), "2022-12-26T07:00:00Z", "2022-12-26T11:00:00Z");
+        assert_eq!(price, 2 * 1000 + 2 * 3000);
+    }
+
+    #[test]
+    fn quote_should_use_off_peak_on_weekends() {
+        // 2022-12-24 is a saturday
+        let price = quote(&card(), "2022-12-24T09:00:00Z", "2022-12-24T12:00:00Z");
+        assert_eq!(price, 3 * 1000);
+    }
+
+    #[test]
+    fn quote_should_span_days() {
+        // friday 16:00 to saturday 10:00, one peak hour and 17 off-peak hours
+        let price = quote(&card(), "2022-12-30T16:00:00Z", "2022-12-31T10:00:00Z");
+        assert_eq!(price, 3000 + 17 * 1000);
+    }
+
+    #[test]
+    fn quote_should_apply_minimum_charge() {
+        let price = quote(&card(), "2022-12-24T09:00:00Z", "2022-12-24T09:15:00Z");
+        assert_eq!(price, 500);
+    }
+
+    #[test]
+    fn quote_should_use_local_time() {
+        let card = RateCard {
+            utc_offset_minutes: -7 * 60,
+            ..card()
+        };
+        // 16:00 - 18:00 UTC is 9:00 - 11:00 at UTC-7
+        let price = quote(&card, "2022-12-26T16:00:00Z", "2022-12-26T18:00:00Z");
+        assert_eq!(price, 2 * 3000);
+    }
+
+    #[test]
+    fn quote_should_reject_prices_out_of_range() {
+        let card = RateCard {
+            hourly_rate: i64::MAX,
+            ..card()
+        };
+        let (start, end) = (
+            "2022-12-24T09:00:00Z".parse().unwrap(),
+            "2022-12-24T10:00:00Z".parse().unwrap(),
+        );
+        assert_eq!(card.quote(start, end).unwrap(), i64::MAX);
+        assert!(RateCard::quote_all([&card, &card], start, end).is_err());
+        let end = "2022-12-24T11:00:00Z".parse().unwrap();
+        assert!(matches!(
+            card.quote(start, end).unwrap_err(),
+            Error::InvalidRateCard(_)
+        ));
+    }
+
+    #[test]
+    fn invalid_rate_card_should_reject() {
+        let mut card = card();
+        card.peak_periods[0].end_minute = 25 * 60;
+        assert!(card.validate().is_err());
+        card.peak_periods[0].end_minute = 17 * 60;
+        card.peak_periods[0].weekdays.push(8);
+        assert!(card.validate().is_err());
+    }
+}
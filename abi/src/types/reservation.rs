@@ -1,4 +1,4 @@
-use std::ops::Bound;
+use std::{collections::HashSet, ops::Bound};
 
 use super::{get_timespan, validate_range};
 use crate::{convert_to_timestamp, Error, Reservation, ReservationStatus, RsvpStatus};
@@ -11,6 +11,7 @@ use sqlx::{
 
 impl Reservation {
     pub fn new_pending(
+        tid: impl Into<String>,
         uid: impl Into<String>,
         rid: impl Into<String>,
         start: DateTime<FixedOffset>,
@@ -25,10 +26,21 @@ impl Reservation {
             start: Some(convert_to_timestamp(start.with_timezone(&Utc))),
             end: Some(convert_to_timestamp(end.with_timezone(&Utc))),
             note: note.into(),
+            version: 0,
+            tenant_id: tid.into(),
+            extra_resource_ids: vec![],
+            approver_id: "".to_string(),
+            approval_reason: "".to_string(),
+            checked_in_at: None,
+            price: 0,
         }
     }
 
     pub fn validate(&self) -> Result<(), Error> {
+        if self.tenant_id.is_empty() {
+            return Err(Error::InvalidTenantId(self.tenant_id.clone()));
+        }
+
         if self.user_id.is_empty() {
             return Err(Error::InvalidUserId(self.user_id.clone()));
         }
@@ -37,6 +49,13 @@ impl Reservation {
             return Err(Error::InvalidResourceId(self.resource_id.clone()));
         }
 
+        let mut seen = HashSet::from([self.resource_id.as_str()]);
+        for rid in &self.extra_resource_ids {
+            if rid.is_empty() || !seen.insert(rid.as_str()) {
+                return Err(Error::InvalidResourceId(rid.clone()));
+            }
+        }
+
         validate_range(self.start.as_ref(), self.end.as_ref())?;
 
         // if self.start.is_none() || self.end.is_none() {
@@ -53,6 +72,20 @@ impl Reservation {
         Ok(())
     }
 
+    /// the status a new reservation asks for, the others are only reached through its lifecycle.
+    /// one that sets no status is pending
+    pub fn requested_status(&self) -> Result<ReservationStatus, Error> {
+        match ReservationStatus::from_i32(self.status) {
+            Some(ReservationStatus::Unknown) => Ok(ReservationStatus::Pending),
+            Some(
+                status @ (ReservationStatus::Pending
+                | ReservationStatus::Confirmed
+                | ReservationStatus::Blocked),
+            ) => Ok(status),
+            _ => Err(Error::InvalidStatus(self.status)),
+        }
+    }
+
     pub fn get_timespan(&self) -> PgRange<DateTime<Utc>> {
         get_timespan(self.start.as_ref(), self.end.as_ref())
     }
@@ -73,6 +106,19 @@ impl FromRow<'_, PgRow> for Reservation {
             start: Some(convert_to_timestamp(range.start.unwrap())),
             end: Some(convert_to_timestamp(range.end.unwrap())),
             note: row.get("note"),
+            version: row.get("version"),
+            tenant_id: row.get("tenant_id"),
+            extra_resource_ids: row.get("extra_resource_ids"),
+            approver_id: row
+                .get::<Option<String>, &str>("approver_id")
+                .unwrap_or_default(),
+            approval_reason: row
+                .get::<Option<String>, &str>("approval_reason")
+                .unwrap_or_default(),
+            checked_in_at: row
+                .get::<Option<DateTime<Utc>>, &str>("checked_in_at")
+                .map(convert_to_timestamp),
+            price: row.get("price"),
         })
     }
 }
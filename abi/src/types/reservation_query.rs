@@ -1,11 +1,51 @@
-use super::{get_timespan, validate_range};
-use crate::{convert_to_timestamp, Error, ReservationQuery, ReservationStatus};
+use crate::{
+    convert_to_timestamp, convert_to_utc_time, split_words, Cursor, Error, ReservationQuery,
+    ReservationStatus, TimeMatch,
+};
 use chrono::{DateTime, Utc};
 use sqlx::postgres::types::PgRange;
+use std::ops::Bound;
+
+/// largest page a query may ask for
+const MAX_PAGE_SIZE: i32 = 1000;
+
+/// text search configurations that come with postgres, the ones search_language may name
+const SEARCH_LANGUAGES: [&str; 29] = [
+    "arabic",
+    "armenian",
+    "basque",
+    "catalan",
+    "danish",
+    "dutch",
+    "english",
+    "finnish",
+    "french",
+    "german",
+    "greek",
+    "hindi",
+    "hungarian",
+    "indonesian",
+    "irish",
+    "italian",
+    "lithuanian",
+    "nepali",
+    "norwegian",
+    "portuguese",
+    "romanian",
+    "russian",
+    "serbian",
+    "simple",
+    "spanish",
+    "swedish",
+    "tamil",
+    "turkish",
+    "yiddish",
+];
 
 #[allow(clippy::too_many_arguments)]
 impl ReservationQuery {
     pub fn new(
+        tid: impl Into<String>,
         uid: impl Into<String>,
         rid: impl Into<String>,
         start: DateTime<Utc>,
@@ -24,15 +64,172 @@ impl ReservationQuery {
             page,
             page_size,
             desc,
+            tenant_id: tid.into(),
+            cursor: String::new(),
+            statuses: vec![],
+            time_match: TimeMatch::ContainedIn as i32,
+            search: String::new(),
+            search_language: String::new(),
+            by_relevance: false,
+        }
+    }
+
+    /// only reservations whose note has all the words
+    pub fn with_search(mut self, text: impl Into<String>) -> Self {
+        self.search = text.into();
+        self
+    }
+
+    /// the words of search, lowercased. none means no search
+    pub fn get_search_terms(&self) -> Vec<String> {
+        split_words(&self.search).collect()
+    }
+
+    pub fn with_time_match(mut self, time_match: TimeMatch) -> Self {
+        self.time_match = time_match as i32;
+        self
+    }
+
+    /// start and end of the window, none where it's open
+    pub fn get_window(&self) -> (Option<DateTime<Utc>>, Option<DateTime<Utc>>) {
+        (
+            self.start.clone().map(convert_to_utc_time),
+            self.end.clone().map(convert_to_utc_time),
+        )
+    }
+
+    /// also match these statuses, a query without any status matches every status
+    pub fn with_statuses(mut self, statuses: impl IntoIterator<Item = ReservationStatus>) -> Self {
+        self.statuses
+            .extend(statuses.into_iter().map(|status| status as i32));
+        self
+    }
+
+    /// the statuses to match, empty means every status
+    pub fn get_statuses(&self) -> Vec<ReservationStatus> {
+        let mut statuses: Vec<ReservationStatus> = self
+            .statuses
+            .iter()
+            .chain([&self.status])
+            .filter_map(|status| ReservationStatus::from_i32(*status))
+            .filter(|status| *status != ReservationStatus::Unknown)
+            .collect();
+        statuses.sort();
+        statuses.dedup();
+        statuses
+    }
+
+    /// continue after a previous page instead of at page
+    pub fn with_cursor(mut self, cursor: impl Into<String>) -> Self {
+        self.cursor = cursor.into();
+        self
+    }
+
+    pub fn get_cursor(&self) -> Result<Option<Cursor>, Error> {
+        if self.cursor.is_empty() {
+            return Ok(None);
+        }
+        Cursor::decode(&self.cursor).map(Some)
+    }
+
+    /// rows to skip, a cursor already points at the right place
+    pub fn offset(&self) -> i64 {
+        if !self.cursor.is_empty() {
+            return 0;
         }
+        // a page past every row skips them all
+        (self.page as i64 - 1)
+            .max(0)
+            .checked_mul(self.page_size.max(0) as i64)
+            .unwrap_or(i64::MAX)
     }
 
     pub fn get_timespan(&self) -> PgRange<DateTime<Utc>> {
-        get_timespan(self.start.as_ref(), self.end.as_ref())
+        let (start, end) = self.get_window();
+        PgRange {
+            start: start.map_or(Bound::Unbounded, Bound::Included),
+            end: end.map_or(Bound::Unbounded, Bound::Excluded),
+        }
     }
 
     pub fn validate(&self) -> Result<(), Error> {
-        validate_range(self.start.as_ref(), self.end.as_ref())?;
+        if self.tenant_id.is_empty() {
+            return Err(Error::InvalidTenantId(self.tenant_id.clone()));
+        }
+        if let (Some(start), Some(end)) = self.get_window() {
+            if start >= end {
+                return Err(Error::InvalidTime);
+            }
+        }
+        if TimeMatch::from_i32(self.time_match).is_none() {
+            return Err(Error::InvalidTimeMatch(self.time_match));
+        }
+        if self.page < 1 {
+            return Err(Error::InvalidPage(self.page));
+        }
+        if !(1..=MAX_PAGE_SIZE).contains(&self.page_size) {
+            return Err(Error::InvalidPageSize(self.page_size));
+        }
+        if let Some(status) = self
+            .statuses
+            .iter()
+            .chain([&self.status])
+            .find(|status| ReservationStatus::from_i32(**status).is_none())
+        {
+            return Err(Error::InvalidStatus(*status));
+        }
+        if !self.search_language.is_empty()
+            && !SEARCH_LANGUAGES.contains(&self.search_language.as_str())
+        {
+            return Err(Error::InvalidSearchLanguage(self.search_language.clone()));
+        }
+        if self.get_cursor()?.is_some() && self.by_relevance {
+            return Err(Error::InvalidCursor(self.cursor.clone()));
+        }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(page: i32, page_size: i32) -> ReservationQuery {
+        ReservationQuery::new(
+            "tenant",
+            "",
+            "",
+            "2022-12-26T00:00:00Z".parse().unwrap(),
+            "2022-12-27T00:00:00Z".parse().unwrap(),
+            ReservationStatus::Pending,
+            page,
+            page_size,
+            false,
+        )
+    }
+
+    #[test]
+    fn invalid_page_should_reject() {
+        assert!(query(1, 1).validate().is_ok());
+        assert!(query(1, MAX_PAGE_SIZE).validate().is_ok());
+        for page in [0, -1, i32::MIN] {
+            let err = query(page, 10).validate().unwrap_err();
+            assert!(matches!(err, Error::InvalidPage(_)), "{:?}", err);
+        }
+        for page_size in [0, -1, MAX_PAGE_SIZE + 1, i32::MAX] {
+            let err = query(1, page_size).validate().unwrap_err();
+            assert!(matches!(err, Error::InvalidPageSize(_)), "{:?}", err);
+        }
+    }
+
+    #[test]
+    fn offset_should_not_overflow() {
+        assert_eq!(query(1, 10).offset(), 0);
+        assert_eq!(query(3, 10).offset(), 20);
+        assert_eq!(
+            query(i32::MAX, MAX_PAGE_SIZE).offset(),
+            (i32::MAX as i64 - 1) * MAX_PAGE_SIZE as i64
+        );
+        assert_eq!(query(3, 10).with_cursor("cursor").offset(), 0);
+    }
+}
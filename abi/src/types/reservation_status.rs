@@ -9,6 +9,9 @@ impl From<RsvpStatus> for ReservationStatus {
             RsvpStatus::Pending => ReservationStatus::Pending,
             RsvpStatus::Confirmed => ReservationStatus::Confirmed,
             RsvpStatus::Blocked => ReservationStatus::Blocked,
+            RsvpStatus::AwaitingApproval => ReservationStatus::AwaitingApproval,
+            RsvpStatus::Rejected => ReservationStatus::Rejected,
+            RsvpStatus::NoShow => ReservationStatus::NoShow,
         }
     }
 }
@@ -20,6 +23,9 @@ impl fmt::Display for ReservationStatus {
             ReservationStatus::Blocked => write!(f, "blocked"),
             ReservationStatus::Confirmed => write!(f, "confirmed"),
             ReservationStatus::Unknown => write!(f, "unknown"),
+            ReservationStatus::AwaitingApproval => write!(f, "awaiting_approval"),
+            ReservationStatus::Rejected => write!(f, "rejected"),
+            ReservationStatus::NoShow => write!(f, "no_show"),
         }
     }
 }
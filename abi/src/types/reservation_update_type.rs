@@ -0,0 +1,25 @@
+use std::fmt;
+
+use crate::ReservationUpdateType;
+
+impl From<&str> for ReservationUpdateType {
+    fn from(op: &str) -> Self {
+        match op {
+            "create" => ReservationUpdateType::Create,
+            "update" => ReservationUpdateType::Update,
+            "delete" => ReservationUpdateType::Delete,
+            _ => ReservationUpdateType::Unknown,
+        }
+    }
+}
+
+impl fmt::Display for ReservationUpdateType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReservationUpdateType::Create => write!(f, "create"),
+            ReservationUpdateType::Update => write!(f, "update"),
+            ReservationUpdateType::Delete => write!(f, "delete"),
+            ReservationUpdateType::Unknown => write!(f, "unknown"),
+        }
+    }
+}
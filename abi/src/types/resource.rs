@@ -0,0 +1,50 @@
+use crate::{Error, RateCard, Resource};
+use prost::Message;
+use sqlx::{postgres::PgRow, FromRow, Row};
+
+impl Resource {
+    pub fn new(tid: impl Into<String>, id: impl Into<String>, requires_approval: bool) -> Self {
+        Self {
+            id: id.into(),
+            tenant_id: tid.into(),
+            requires_approval,
+            rate_card: None,
+        }
+    }
+
+    pub fn with_rate_card(mut self, rate_card: RateCard) -> Self {
+        self.rate_card = Some(rate_card);
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.tenant_id.is_empty() {
+            return Err(Error::InvalidTenantId(self.tenant_id.clone()));
+        }
+
+        if self.id.is_empty() {
+            return Err(Error::InvalidResourceId(self.id.clone()));
+        }
+
+        if let Some(rate_card) = &self.rate_card {
+            rate_card.validate()?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromRow<'_, PgRow> for Resource {
+    fn from_row(row: &PgRow) -> Result<Self, sqlx::Error> {
+        Ok(Self {
+            id: row.get("id"),
+            tenant_id: row.get("tenant_id"),
+            requires_approval: row.get("requires_approval"),
+            rate_card: row
+                .get::<Option<Vec<u8>>, &str>("rate_card")
+                .map(|bytes| RateCard::decode(bytes.as_slice()))
+                .transpose()
+                .map_err(|e| sqlx::Error::Decode(Box::new(e)))?,
+        })
+    }
+}
@@ -0,0 +1,23 @@
+use super::validate_range;
+use crate::{Error, UtilizationRequest};
+
+const MINUTES_PER_DAY: i32 = 24 * 60;
+
+impl UtilizationRequest {
+    pub fn validate(&self) -> Result<(), Error> {
+        validate_range(self.start.as_ref(), self.end.as_ref())?;
+
+        if self.utc_offset_minutes.abs() >= MINUTES_PER_DAY {
+            return Err(Error::InvalidOpeningHours(format!(
+                "utc offset {} out of range",
+                self.utc_offset_minutes
+            )));
+        }
+
+        for hours in &self.opening_hours {
+            hours.validate(Error::InvalidOpeningHours)?;
+        }
+
+        Ok(())
+    }
+}
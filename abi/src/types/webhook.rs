@@ -0,0 +1,62 @@
+use crate::{Error, ReservationUpdateType, Webhook};
+use sqlx::{postgres::PgRow, types::Uuid, FromRow, Row};
+
+impl Webhook {
+    pub fn new(tid: impl Into<String>, url: impl Into<String>, secret: impl Into<String>) -> Self {
+        Self {
+            tenant_id: tid.into(),
+            url: url.into(),
+            secret: secret.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.tenant_id.is_empty() {
+            return Err(Error::InvalidTenantId(self.tenant_id.clone()));
+        }
+
+        if !(self.url.starts_with("http://") || self.url.starts_with("https://")) {
+            return Err(Error::InvalidWebhook(format!("invalid url {}", self.url)));
+        }
+
+        if self.secret.is_empty() {
+            return Err(Error::InvalidWebhook("secret is required".into()));
+        }
+
+        let known = |op: &i32| {
+            !matches!(
+                ReservationUpdateType::from_i32(*op),
+                None | Some(ReservationUpdateType::Unknown)
+            )
+        };
+        if !self.ops.iter().all(known) {
+            return Err(Error::InvalidWebhook("unknown op".into()));
+        }
+
+        Ok(())
+    }
+}
+
+impl FromRow<'_, PgRow> for Webhook {
+    fn from_row(row: &PgRow) -> Result<Self, sqlx::Error> {
+        let id: Uuid = row.get("id");
+        let ops: Vec<String> = row.get("ops");
+        Ok(Self {
+            id: id.to_string(),
+            tenant_id: row.get("tenant_id"),
+            url: row.get("url"),
+            secret: row.get("secret"),
+            resource_id: row
+                .get::<Option<String>, &str>("resource_id")
+                .unwrap_or_default(),
+            user_id: row
+                .get::<Option<String>, &str>("user_id")
+                .unwrap_or_default(),
+            ops: ops
+                .iter()
+                .map(|op| ReservationUpdateType::from(op.as_str()) as i32)
+                .collect(),
+        })
+    }
+}
@@ -0,0 +1,35 @@
+use crate::{Error, WeeklyPeriod};
+
+const MINUTES_PER_DAY: i32 = 24 * 60;
+
+impl WeeklyPeriod {
+    pub fn new(weekdays: impl Into<Vec<i32>>, start_minute: i32, end_minute: i32) -> Self {
+        Self {
+            weekdays: weekdays.into(),
+            start_minute,
+            end_minute,
+        }
+    }
+
+    /// the period must be a non-empty part of a day on ISO weekdays, invalid builds the error
+    /// of whatever the period belongs to
+    pub fn validate(&self, invalid: fn(String) -> Error) -> Result<(), Error> {
+        if self.start_minute < 0
+            || self.start_minute >= self.end_minute
+            || self.end_minute > MINUTES_PER_DAY
+        {
+            return Err(invalid(format!(
+                "period {}-{} out of range",
+                self.start_minute, self.end_minute
+            )));
+        }
+        if let Some(day) = self.weekdays.iter().find(|d| !(1..=7).contains(*d)) {
+            return Err(invalid(format!("invalid weekday {}", day)));
+        }
+        Ok(())
+    }
+
+    pub fn applies_on(&self, weekday: i32) -> bool {
+        self.weekdays.is_empty() || self.weekdays.contains(&weekday)
+    }
+}
@@ -0,0 +1,7 @@
+DROP TABLE calendar_feeds;
+DROP TABLE user_no_shows;
+DROP TABLE idempotency_keys;
+DROP TABLE resources;
+DROP TABLE resource_relations;
+DROP TABLE reservation_resources;
+DROP TABLE reservations;
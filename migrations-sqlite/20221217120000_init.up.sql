@@ -0,0 +1,94 @@
+-- SQLite 没有 tstzrange 和 EXCLUDE 约束，时间段以 [start_at, end_at) 的 UTC 微秒数存储，冲突检测在事务中完成
+CREATE TABLE reservations (
+  id TEXT NOT NULL,
+  tenant_id TEXT NOT NULL,
+  user_id TEXT NOT NULL,
+  status TEXT NOT NULL DEFAULT 'pending',
+  resource_id TEXT NOT NULL,
+  -- JSON 数组
+  extra_resource_ids TEXT NOT NULL DEFAULT '[]',
+  start_at INTEGER NOT NULL,
+  end_at INTEGER NOT NULL,
+  note TEXT,
+  version INTEGER NOT NULL DEFAULT 1,
+  approver_id TEXT,
+  approval_reason TEXT,
+  checked_in_at INTEGER,
+  price INTEGER NOT NULL DEFAULT 0,
+
+  CONSTRAINT reservations_pkey PRIMARY KEY (id),
+  CONSTRAINT reservations_timespan CHECK (start_at < end_at)
+);
+
+CREATE INDEX reservation_user_id_idx ON reservations (tenant_id, user_id);
+CREATE INDEX reservations_start_at_idx ON reservations (tenant_id, start_at);
+
+-- 预订占用的每个资源一行，被拒绝的预订不占用资源
+CREATE TABLE reservation_resources (
+  reservation_id TEXT NOT NULL REFERENCES reservations (id) ON DELETE CASCADE,
+  tenant_id TEXT NOT NULL,
+  resource_id TEXT NOT NULL,
+  start_at INTEGER NOT NULL,
+  end_at INTEGER NOT NULL,
+
+  CONSTRAINT reservation_resources_pkey PRIMARY KEY (reservation_id, resource_id)
+);
+
+CREATE INDEX reservation_resources_resource_id_idx ON reservation_resources (tenant_id, resource_id, start_at);
+
+-- 资源之间的父子关系
+CREATE TABLE resource_relations (
+  tenant_id TEXT NOT NULL,
+  parent_id TEXT NOT NULL,
+  child_id TEXT NOT NULL,
+
+  CONSTRAINT resource_relations_pkey PRIMARY KEY (tenant_id, parent_id, child_id),
+  CONSTRAINT resource_relations_not_self CHECK (parent_id <> child_id)
+);
+
+CREATE INDEX resource_relations_child_idx ON resource_relations (tenant_id, child_id);
+
+-- 资源的配置，rate_card 为 protobuf 编码的 RateCard
+CREATE TABLE resources (
+  tenant_id TEXT NOT NULL,
+  id TEXT NOT NULL,
+  requires_approval INTEGER NOT NULL DEFAULT 0,
+  rate_card BLOB,
+
+  CONSTRAINT resources_pkey PRIMARY KEY (tenant_id, id)
+);
+
+-- 每个幂等键对应的操作、请求参数的摘要及其结果
+CREATE TABLE idempotency_keys (
+  tenant_id TEXT NOT NULL,
+  key TEXT NOT NULL,
+  op TEXT NOT NULL,
+  fingerprint BLOB NOT NULL,
+  response BLOB,
+  created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+
+  CONSTRAINT idempotency_keys_pkey PRIMARY KEY (tenant_id, key)
+);
+
+-- 过期的幂等键按创建时间定期删除
+CREATE INDEX idempotency_keys_created_at_idx ON idempotency_keys (created_at);
+
+-- 每个用户的爽约次数
+CREATE TABLE user_no_shows (
+  tenant_id TEXT NOT NULL,
+  user_id TEXT NOT NULL,
+  count INTEGER NOT NULL DEFAULT 0,
+
+  CONSTRAINT user_no_shows_pkey PRIMARY KEY (tenant_id, user_id)
+);
+
+-- 日历订阅地址中的密钥
+CREATE TABLE calendar_feeds (
+  tenant_id TEXT NOT NULL,
+  user_id TEXT NOT NULL,
+  token TEXT NOT NULL,
+  created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+
+  CONSTRAINT calendar_feeds_pkey PRIMARY KEY (tenant_id, user_id),
+  CONSTRAINT calendar_feeds_token_key UNIQUE (token)
+);
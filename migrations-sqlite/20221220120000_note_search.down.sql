@@ -0,0 +1,4 @@
+DROP TRIGGER reservation_notes_delete;
+DROP TRIGGER reservation_notes_update;
+DROP TRIGGER reservation_notes_insert;
+DROP TABLE reservation_notes;
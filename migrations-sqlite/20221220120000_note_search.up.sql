@@ -0,0 +1,23 @@
+-- 备注全文检索，由触发器与 reservations 保持同步
+CREATE VIRTUAL TABLE reservation_notes USING fts5(
+  tenant_id UNINDEXED,
+  reservation_id UNINDEXED,
+  note
+);
+
+INSERT INTO reservation_notes (tenant_id, reservation_id, note)
+  SELECT tenant_id, id, note FROM reservations WHERE note IS NOT NULL;
+
+CREATE TRIGGER reservation_notes_insert AFTER INSERT ON reservations WHEN NEW.note IS NOT NULL BEGIN
+  INSERT INTO reservation_notes (tenant_id, reservation_id, note) VALUES (NEW.tenant_id, NEW.id, NEW.note);
+END;
+
+CREATE TRIGGER reservation_notes_update AFTER UPDATE OF note ON reservations BEGIN
+  DELETE FROM reservation_notes WHERE reservation_id = OLD.id;
+  INSERT INTO reservation_notes (tenant_id, reservation_id, note)
+    SELECT NEW.tenant_id, NEW.id, NEW.note WHERE NEW.note IS NOT NULL;
+END;
+
+CREATE TRIGGER reservation_notes_delete AFTER DELETE ON reservations BEGIN
+  DELETE FROM reservation_notes WHERE reservation_id = OLD.id;
+END;
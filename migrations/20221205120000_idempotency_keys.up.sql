@@ -0,0 +1,13 @@
+-- 记录每个幂等键对应的操作、请求参数的摘要及其结果，重试时直接返回首次的结果
+CREATE TABLE rsvp.idempotency_keys (
+  key VARCHAR(64) NOT NULL,
+  op VARCHAR(16) NOT NULL,
+  fingerprint BYTEA NOT NULL,
+  response BYTEA,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+
+  CONSTRAINT idempotency_keys_pkey PRIMARY KEY (key)
+);
+
+-- 过期的幂等键按创建时间定期删除
+CREATE INDEX idempotency_keys_created_at_idx ON rsvp.idempotency_keys (created_at);
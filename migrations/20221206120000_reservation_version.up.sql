@@ -0,0 +1,12 @@
+ALTER TABLE rsvp.reservations ADD COLUMN version BIGINT NOT NULL DEFAULT 1;
+
+-- 每次更新预订时递增版本号，用于乐观并发控制
+CREATE OR REPLACE FUNCTION rsvp.reservations_version_trigger() RETURNS TRIGGER AS $$
+BEGIN
+  NEW.version := OLD.version + 1;
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER reservations_version_trigger BEFORE UPDATE ON rsvp.reservations FOR EACH ROW EXECUTE PROCEDURE rsvp.reservations_version_trigger();
+
@@ -0,0 +1,49 @@
+DROP FUNCTION rsvp.query;
+
+ALTER TABLE rsvp.idempotency_keys DROP CONSTRAINT idempotency_keys_pkey;
+ALTER TABLE rsvp.idempotency_keys DROP COLUMN tenant_id;
+ALTER TABLE rsvp.idempotency_keys ADD CONSTRAINT idempotency_keys_pkey PRIMARY KEY (key);
+
+DROP INDEX rsvp.reservations_resource_id_idx;
+DROP INDEX rsvp.reservation_user_id_idx;
+ALTER TABLE rsvp.reservations DROP CONSTRAINT reservations_conflict;
+ALTER TABLE rsvp.reservations DROP COLUMN tenant_id;
+ALTER TABLE rsvp.reservations ADD CONSTRAINT reservations_conflict EXCLUDE USING gist (resource_id WITH =, timespan WITH &&);
+CREATE INDEX reservations_resource_id_idx ON rsvp.reservations (resource_id);
+CREATE INDEX reservation_user_id_idx ON rsvp.reservations (user_id);
+
+CREATE OR REPLACE FUNCTION rsvp.query(
+  uid text,
+  rid text,
+  during TSTZRANGE,
+  status rsvp.reservation_status,
+  page integer DEFAULT 1,
+  page_size integer DEFAULT 10,
+  is_desc bool DEFAULT FALSE
+  ) RETURNS TABLE (LIKE rsvp.reservations) AS $$
+DECLARE
+  _sql text;
+BEGIN
+  _sql := format(
+    'SELECT * FROM rsvp.reservations WHERE %L @> timespan AND status = %L AND %s ORDER BY lower(timespan) %s LIMIT %L::integer OFFSET %L::integer',
+    during,
+    status,
+    CASE
+      WHEN uid IS NULL AND rid IS NULL THEN 'TRUE'
+      WHEN uid IS NULL THEN 'resource_id=' || quote_literal(rid)
+      WHEN rid IS NULL THEN 'user_id=' || quote_literal(uid)
+      ELSE 'user_id=' || quote_literal(uid) || ' AND resource_id=' || quote_literal(rid)
+    END,
+    CASE
+      WHEN is_desc THEN 'DESC'
+      ELSE 'ASC'
+    END,
+    page_size,
+    (page - 1) * page_size
+  );
+  -- log the sql
+  RAISE NOTICE '%', _sql;
+
+  RETURN QUERY EXECUTE _sql;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,56 @@
+ALTER TABLE rsvp.reservations ADD COLUMN tenant_id VARCHAR(64) NOT NULL DEFAULT 'default';
+ALTER TABLE rsvp.reservations ALTER COLUMN tenant_id DROP DEFAULT;
+
+-- 冲突检测限定在同一租户内，不同租户的相同资源id互不影响
+ALTER TABLE rsvp.reservations DROP CONSTRAINT reservations_conflict;
+ALTER TABLE rsvp.reservations ADD CONSTRAINT reservations_conflict EXCLUDE USING gist (tenant_id WITH =, resource_id WITH =, timespan WITH &&);
+
+DROP INDEX rsvp.reservations_resource_id_idx;
+DROP INDEX rsvp.reservation_user_id_idx;
+CREATE INDEX reservations_resource_id_idx ON rsvp.reservations (tenant_id, resource_id);
+CREATE INDEX reservation_user_id_idx ON rsvp.reservations (tenant_id, user_id);
+
+-- 幂等键同样按租户隔离
+ALTER TABLE rsvp.idempotency_keys ADD COLUMN tenant_id VARCHAR(64) NOT NULL DEFAULT 'default';
+ALTER TABLE rsvp.idempotency_keys ALTER COLUMN tenant_id DROP DEFAULT;
+ALTER TABLE rsvp.idempotency_keys DROP CONSTRAINT idempotency_keys_pkey;
+ALTER TABLE rsvp.idempotency_keys ADD CONSTRAINT idempotency_keys_pkey PRIMARY KEY (tenant_id, key);
+
+DROP FUNCTION rsvp.query;
+CREATE OR REPLACE FUNCTION rsvp.query(
+  tid text,
+  uid text,
+  rid text,
+  during TSTZRANGE,
+  status rsvp.reservation_status,
+  page integer DEFAULT 1,
+  page_size integer DEFAULT 10,
+  is_desc bool DEFAULT FALSE
+  ) RETURNS TABLE (LIKE rsvp.reservations) AS $$
+DECLARE
+  _sql text;
+BEGIN
+  _sql := format(
+    'SELECT * FROM rsvp.reservations WHERE tenant_id = %L AND %L @> timespan AND status = %L AND %s ORDER BY lower(timespan) %s LIMIT %L::integer OFFSET %L::integer',
+    tid,
+    during,
+    status,
+    CASE
+      WHEN uid IS NULL AND rid IS NULL THEN 'TRUE'
+      WHEN uid IS NULL THEN 'resource_id=' || quote_literal(rid)
+      WHEN rid IS NULL THEN 'user_id=' || quote_literal(uid)
+      ELSE 'user_id=' || quote_literal(uid) || ' AND resource_id=' || quote_literal(rid)
+    END,
+    CASE
+      WHEN is_desc THEN 'DESC'
+      ELSE 'ASC'
+    END,
+    page_size,
+    (page - 1) * page_size
+  );
+  -- log the sql
+  RAISE NOTICE '%', _sql;
+
+  RETURN QUERY EXECUTE _sql;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,4 @@
+DROP TRIGGER reservations_hierarchy_trigger ON rsvp.reservations;
+DROP FUNCTION rsvp.reservations_hierarchy_trigger;
+DROP FUNCTION rsvp.related_resources;
+DROP TABLE rsvp.resource_relations;
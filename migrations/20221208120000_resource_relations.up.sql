@@ -0,0 +1,62 @@
+-- 资源之间的父子关系，例如大厅AB可拆分为大厅A和大厅B
+CREATE TABLE rsvp.resource_relations (
+  tenant_id VARCHAR(64) NOT NULL,
+  parent_id VARCHAR(64) NOT NULL,
+  child_id VARCHAR(64) NOT NULL,
+
+  CONSTRAINT resource_relations_pkey PRIMARY KEY (tenant_id, parent_id, child_id),
+  CONSTRAINT resource_relations_not_self CHECK (parent_id <> child_id)
+);
+
+CREATE INDEX resource_relations_child_idx ON rsvp.resource_relations (tenant_id, child_id);
+
+-- 资源的所有祖先和后代
+CREATE OR REPLACE FUNCTION rsvp.related_resources(tid text, rid text) RETURNS TABLE (resource_id VARCHAR(64)) AS $$
+  WITH RECURSIVE ancestors(id) AS (
+    SELECT parent_id FROM rsvp.resource_relations WHERE tenant_id = tid AND child_id = rid
+    UNION
+    SELECT r.parent_id FROM rsvp.resource_relations r JOIN ancestors a ON r.child_id = a.id WHERE r.tenant_id = tid
+  ), descendants(id) AS (
+    SELECT child_id FROM rsvp.resource_relations WHERE tenant_id = tid AND parent_id = rid
+    UNION
+    SELECT r.child_id FROM rsvp.resource_relations r JOIN descendants d ON r.parent_id = d.id WHERE r.tenant_id = tid
+  )
+  SELECT id FROM ancestors UNION SELECT id FROM descendants;
+$$ LANGUAGE sql STABLE;
+
+-- 预订父资源时与子资源的预订冲突，反之亦然；相同资源的冲突仍由 reservations_conflict 约束检测
+CREATE OR REPLACE FUNCTION rsvp.reservations_hierarchy_trigger() RETURNS TRIGGER AS $$
+DECLARE
+  _exist rsvp.reservations;
+BEGIN
+  IF NOT EXISTS (SELECT 1 FROM rsvp.related_resources(NEW.tenant_id, NEW.resource_id)) THEN
+    RETURN NEW;
+  END IF;
+
+  -- 串行化同一租户内有层级关系资源的预订，避免并发插入时漏检
+  PERFORM pg_advisory_xact_lock(hashtext('rsvp.resource_relations'), hashtext(NEW.tenant_id));
+
+  SELECT * INTO _exist FROM rsvp.reservations
+    WHERE tenant_id = NEW.tenant_id
+      AND id <> NEW.id
+      AND resource_id IN (SELECT resource_id FROM rsvp.related_resources(NEW.tenant_id, NEW.resource_id))
+      AND timespan && NEW.timespan
+    LIMIT 1;
+
+  IF FOUND THEN
+    RAISE EXCEPTION 'conflicting key value violates exclusion constraint "reservations_conflict"'
+      USING ERRCODE = 'exclusion_violation',
+        SCHEMA = 'rsvp',
+        TABLE = 'reservations',
+        CONSTRAINT = 'reservations_conflict',
+        DETAIL = format(
+          'Key (tenant_id, resource_id, timespan)=(%s, %s, %s) conflicts with existing key (tenant_id, resource_id, timespan)=(%s, %s, %s).',
+          NEW.tenant_id, NEW.resource_id, NEW.timespan, _exist.tenant_id, _exist.resource_id, _exist.timespan
+        );
+  END IF;
+
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER reservations_hierarchy_trigger BEFORE INSERT OR UPDATE OF tenant_id, resource_id, timespan ON rsvp.reservations FOR EACH ROW EXECUTE PROCEDURE rsvp.reservations_hierarchy_trigger();
@@ -0,0 +1,83 @@
+DROP TRIGGER reservations_resources_trigger ON rsvp.reservations;
+DROP FUNCTION rsvp.reservations_resources_trigger;
+DROP TRIGGER reservation_resources_hierarchy_trigger ON rsvp.reservation_resources;
+DROP FUNCTION rsvp.reservation_resources_hierarchy_trigger;
+DROP TABLE rsvp.reservation_resources;
+
+ALTER TABLE rsvp.reservations DROP COLUMN extra_resource_ids;
+ALTER TABLE rsvp.reservations ADD CONSTRAINT reservations_conflict EXCLUDE USING gist (tenant_id WITH =, resource_id WITH =, timespan WITH &&);
+
+-- 预订父资源时与子资源的预订冲突，反之亦然；相同资源的冲突仍由 reservations_conflict 约束检测
+CREATE OR REPLACE FUNCTION rsvp.reservations_hierarchy_trigger() RETURNS TRIGGER AS $$
+DECLARE
+  _exist rsvp.reservations;
+BEGIN
+  IF NOT EXISTS (SELECT 1 FROM rsvp.related_resources(NEW.tenant_id, NEW.resource_id)) THEN
+    RETURN NEW;
+  END IF;
+
+  -- 串行化同一租户内有层级关系资源的预订，避免并发插入时漏检
+  PERFORM pg_advisory_xact_lock(hashtext('rsvp.resource_relations'), hashtext(NEW.tenant_id));
+
+  SELECT * INTO _exist FROM rsvp.reservations
+    WHERE tenant_id = NEW.tenant_id
+      AND id <> NEW.id
+      AND resource_id IN (SELECT resource_id FROM rsvp.related_resources(NEW.tenant_id, NEW.resource_id))
+      AND timespan && NEW.timespan
+    LIMIT 1;
+
+  IF FOUND THEN
+    RAISE EXCEPTION 'conflicting key value violates exclusion constraint "reservations_conflict"'
+      USING ERRCODE = 'exclusion_violation',
+        SCHEMA = 'rsvp',
+        TABLE = 'reservations',
+        CONSTRAINT = 'reservations_conflict',
+        DETAIL = format(
+          'Key (tenant_id, resource_id, timespan)=(%s, %s, %s) conflicts with existing key (tenant_id, resource_id, timespan)=(%s, %s, %s).',
+          NEW.tenant_id, NEW.resource_id, NEW.timespan, _exist.tenant_id, _exist.resource_id, _exist.timespan
+        );
+  END IF;
+
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER reservations_hierarchy_trigger BEFORE INSERT OR UPDATE OF tenant_id, resource_id, timespan ON rsvp.reservations FOR EACH ROW EXECUTE PROCEDURE rsvp.reservations_hierarchy_trigger();
+
+CREATE OR REPLACE FUNCTION rsvp.query(
+  tid text,
+  uid text,
+  rid text,
+  during TSTZRANGE,
+  status rsvp.reservation_status,
+  page integer DEFAULT 1,
+  page_size integer DEFAULT 10,
+  is_desc bool DEFAULT FALSE
+  ) RETURNS TABLE (LIKE rsvp.reservations) AS $$
+DECLARE
+  _sql text;
+BEGIN
+  _sql := format(
+    'SELECT * FROM rsvp.reservations WHERE tenant_id = %L AND %L @> timespan AND status = %L AND %s ORDER BY lower(timespan) %s LIMIT %L::integer OFFSET %L::integer',
+    tid,
+    during,
+    status,
+    CASE
+      WHEN uid IS NULL AND rid IS NULL THEN 'TRUE'
+      WHEN uid IS NULL THEN 'resource_id=' || quote_literal(rid)
+      WHEN rid IS NULL THEN 'user_id=' || quote_literal(uid)
+      ELSE 'user_id=' || quote_literal(uid) || ' AND resource_id=' || quote_literal(rid)
+    END,
+    CASE
+      WHEN is_desc THEN 'DESC'
+      ELSE 'ASC'
+    END,
+    page_size,
+    (page - 1) * page_size
+  );
+  -- log the sql
+  RAISE NOTICE '%', _sql;
+
+  RETURN QUERY EXECUTE _sql;
+END;
+$$ LANGUAGE plpgsql;
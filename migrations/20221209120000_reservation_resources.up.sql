@@ -0,0 +1,135 @@
+-- 一个预订可以同时占用多个资源，resource_id 之外的资源记录在 extra_resource_ids 中
+ALTER TABLE rsvp.reservations ADD COLUMN extra_resource_ids VARCHAR(64)[] NOT NULL DEFAULT '{}';
+
+-- 预订占用的每个资源一行，冲突检测在这张表上进行
+CREATE TABLE rsvp.reservation_resources (
+  reservation_id uuid NOT NULL REFERENCES rsvp.reservations (id) ON DELETE CASCADE,
+  tenant_id VARCHAR(64) NOT NULL,
+  resource_id VARCHAR(64) NOT NULL,
+  timespan TSTZRANGE NOT NULL,
+
+  CONSTRAINT reservation_resources_pkey PRIMARY KEY (reservation_id, resource_id),
+  CONSTRAINT reservation_resources_conflict EXCLUDE USING gist (tenant_id WITH =, resource_id WITH =, timespan WITH &&)
+);
+
+CREATE INDEX reservation_resources_resource_id_idx ON rsvp.reservation_resources (tenant_id, resource_id);
+
+INSERT INTO rsvp.reservation_resources (reservation_id, tenant_id, resource_id, timespan)
+  SELECT id, tenant_id, resource_id, timespan FROM rsvp.reservations;
+
+ALTER TABLE rsvp.reservations DROP CONSTRAINT reservations_conflict;
+
+DROP TRIGGER reservations_hierarchy_trigger ON rsvp.reservations;
+DROP FUNCTION rsvp.reservations_hierarchy_trigger;
+
+-- 预订父资源时与子资源的预订冲突，反之亦然
+CREATE OR REPLACE FUNCTION rsvp.reservation_resources_hierarchy_trigger() RETURNS TRIGGER AS $$
+DECLARE
+  _exist rsvp.reservation_resources;
+BEGIN
+  IF NOT EXISTS (SELECT 1 FROM rsvp.related_resources(NEW.tenant_id, NEW.resource_id)) THEN
+    RETURN NEW;
+  END IF;
+
+  -- 串行化同一租户内有层级关系资源的预订，避免并发插入时漏检
+  PERFORM pg_advisory_xact_lock(hashtext('rsvp.resource_relations'), hashtext(NEW.tenant_id));
+
+  SELECT * INTO _exist FROM rsvp.reservation_resources
+    WHERE tenant_id = NEW.tenant_id
+      AND reservation_id <> NEW.reservation_id
+      AND resource_id IN (SELECT resource_id FROM rsvp.related_resources(NEW.tenant_id, NEW.resource_id))
+      AND timespan && NEW.timespan
+    LIMIT 1;
+
+  IF FOUND THEN
+    RAISE EXCEPTION 'conflicting key value violates exclusion constraint "reservation_resources_conflict"'
+      USING ERRCODE = 'exclusion_violation',
+        SCHEMA = 'rsvp',
+        TABLE = 'reservation_resources',
+        CONSTRAINT = 'reservation_resources_conflict',
+        DETAIL = format(
+          'Key (tenant_id, resource_id, timespan)=(%s, %s, %s) conflicts with existing key (tenant_id, resource_id, timespan)=(%s, %s, %s).',
+          NEW.tenant_id, NEW.resource_id, NEW.timespan, _exist.tenant_id, _exist.resource_id, _exist.timespan
+        );
+  END IF;
+
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER reservation_resources_hierarchy_trigger BEFORE INSERT ON rsvp.reservation_resources FOR EACH ROW EXECUTE PROCEDURE rsvp.reservation_resources_hierarchy_trigger();
+
+-- 同步预订占用的资源，收集每个资源的冲突后一并报错（DETAIL 中每行一个冲突）
+CREATE OR REPLACE FUNCTION rsvp.reservations_resources_trigger() RETURNS TRIGGER AS $$
+DECLARE
+  _rid VARCHAR(64);
+  _detail text;
+  _details text[] := '{}';
+BEGIN
+  IF TG_OP = 'UPDATE' THEN
+    DELETE FROM rsvp.reservation_resources WHERE reservation_id = OLD.id;
+  END IF;
+
+  FOREACH _rid IN ARRAY array_prepend(NEW.resource_id, NEW.extra_resource_ids) LOOP
+    BEGIN
+      INSERT INTO rsvp.reservation_resources (reservation_id, tenant_id, resource_id, timespan)
+        VALUES (NEW.id, NEW.tenant_id, _rid, NEW.timespan);
+    EXCEPTION WHEN exclusion_violation THEN
+      GET STACKED DIAGNOSTICS _detail = PG_EXCEPTION_DETAIL;
+      _details := array_append(_details, _detail);
+    END;
+  END LOOP;
+
+  IF array_length(_details, 1) > 0 THEN
+    RAISE EXCEPTION 'conflicting key value violates exclusion constraint "reservation_resources_conflict"'
+      USING ERRCODE = 'exclusion_violation',
+        SCHEMA = 'rsvp',
+        TABLE = 'reservations',
+        CONSTRAINT = 'reservation_resources_conflict',
+        DETAIL = array_to_string(_details, E'\n');
+  END IF;
+
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER reservations_resources_trigger AFTER INSERT OR UPDATE OF tenant_id, resource_id, extra_resource_ids, timespan ON rsvp.reservations FOR EACH ROW EXECUTE PROCEDURE rsvp.reservations_resources_trigger();
+
+-- 通过任意一个资源都能查到多资源预订
+CREATE OR REPLACE FUNCTION rsvp.query(
+  tid text,
+  uid text,
+  rid text,
+  during TSTZRANGE,
+  status rsvp.reservation_status,
+  page integer DEFAULT 1,
+  page_size integer DEFAULT 10,
+  is_desc bool DEFAULT FALSE
+  ) RETURNS TABLE (LIKE rsvp.reservations) AS $$
+DECLARE
+  _sql text;
+BEGIN
+  _sql := format(
+    'SELECT * FROM rsvp.reservations WHERE tenant_id = %L AND %L @> timespan AND status = %L AND %s ORDER BY lower(timespan) %s LIMIT %L::integer OFFSET %L::integer',
+    tid,
+    during,
+    status,
+    CASE
+      WHEN uid IS NULL AND rid IS NULL THEN 'TRUE'
+      WHEN uid IS NULL THEN 'id IN (SELECT reservation_id FROM rsvp.reservation_resources WHERE tenant_id=' || quote_literal(tid) || ' AND resource_id=' || quote_literal(rid) || ')'
+      WHEN rid IS NULL THEN 'user_id=' || quote_literal(uid)
+      ELSE 'user_id=' || quote_literal(uid) || ' AND id IN (SELECT reservation_id FROM rsvp.reservation_resources WHERE tenant_id=' || quote_literal(tid) || ' AND resource_id=' || quote_literal(rid) || ')'
+    END,
+    CASE
+      WHEN is_desc THEN 'DESC'
+      ELSE 'ASC'
+    END,
+    page_size,
+    (page - 1) * page_size
+  );
+  -- log the sql
+  RAISE NOTICE '%', _sql;
+
+  RETURN QUERY EXECUTE _sql;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,3 @@
+-- 新的枚举值不能在添加它的事务中使用，所以单独放在一个迁移中
+ALTER TYPE rsvp.reservation_status ADD VALUE 'awaiting_approval';
+ALTER TYPE rsvp.reservation_status ADD VALUE 'rejected';
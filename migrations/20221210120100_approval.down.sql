@@ -0,0 +1,43 @@
+DELETE FROM rsvp.reservations WHERE status = 'rejected';
+UPDATE rsvp.reservations SET status = 'pending' WHERE status = 'awaiting_approval';
+
+-- 同步预订占用的资源，收集每个资源的冲突后一并报错（DETAIL 中每行一个冲突）
+CREATE OR REPLACE FUNCTION rsvp.reservations_resources_trigger() RETURNS TRIGGER AS $$
+DECLARE
+  _rid VARCHAR(64);
+  _detail text;
+  _details text[] := '{}';
+BEGIN
+  IF TG_OP = 'UPDATE' THEN
+    DELETE FROM rsvp.reservation_resources WHERE reservation_id = OLD.id;
+  END IF;
+
+  FOREACH _rid IN ARRAY array_prepend(NEW.resource_id, NEW.extra_resource_ids) LOOP
+    BEGIN
+      INSERT INTO rsvp.reservation_resources (reservation_id, tenant_id, resource_id, timespan)
+        VALUES (NEW.id, NEW.tenant_id, _rid, NEW.timespan);
+    EXCEPTION WHEN exclusion_violation THEN
+      GET STACKED DIAGNOSTICS _detail = PG_EXCEPTION_DETAIL;
+      _details := array_append(_details, _detail);
+    END;
+  END LOOP;
+
+  IF array_length(_details, 1) > 0 THEN
+    RAISE EXCEPTION 'conflicting key value violates exclusion constraint "reservation_resources_conflict"'
+      USING ERRCODE = 'exclusion_violation',
+        SCHEMA = 'rsvp',
+        TABLE = 'reservations',
+        CONSTRAINT = 'reservation_resources_conflict',
+        DETAIL = array_to_string(_details, E'\n');
+  END IF;
+
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER reservations_resources_trigger ON rsvp.reservations;
+CREATE TRIGGER reservations_resources_trigger AFTER INSERT OR UPDATE OF tenant_id, resource_id, extra_resource_ids, timespan ON rsvp.reservations FOR EACH ROW EXECUTE PROCEDURE rsvp.reservations_resources_trigger();
+
+ALTER TABLE rsvp.reservations DROP COLUMN approval_reason;
+ALTER TABLE rsvp.reservations DROP COLUMN approver_id;
+DROP TABLE rsvp.resources;
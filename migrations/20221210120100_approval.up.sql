@@ -0,0 +1,59 @@
+-- 资源的配置，未配置的资源使用默认值
+CREATE TABLE rsvp.resources (
+  tenant_id VARCHAR(64) NOT NULL,
+  id VARCHAR(64) NOT NULL,
+  -- 需要审批的资源，其预订先处于 awaiting_approval 状态，审批通过后才能确认
+  requires_approval BOOLEAN NOT NULL DEFAULT FALSE,
+
+  CONSTRAINT resources_pkey PRIMARY KEY (tenant_id, id)
+);
+
+ALTER TABLE rsvp.reservations ADD COLUMN approver_id VARCHAR(64);
+ALTER TABLE rsvp.reservations ADD COLUMN approval_reason TEXT;
+
+-- 同步预订占用的资源，收集每个资源的冲突后一并报错（DETAIL 中每行一个冲突）
+CREATE OR REPLACE FUNCTION rsvp.reservations_resources_trigger() RETURNS TRIGGER AS $$
+DECLARE
+  _rid VARCHAR(64);
+  _detail text;
+  _details text[] := '{}';
+BEGIN
+  IF TG_OP = 'UPDATE' THEN
+    IF OLD.tenant_id = NEW.tenant_id AND OLD.resource_id = NEW.resource_id
+      AND OLD.extra_resource_ids = NEW.extra_resource_ids AND OLD.timespan = NEW.timespan
+      AND (OLD.status = 'rejected') = (NEW.status = 'rejected') THEN
+      RETURN NULL;
+    END IF;
+    DELETE FROM rsvp.reservation_resources WHERE reservation_id = OLD.id;
+  END IF;
+
+  -- 被拒绝的预订不再占用资源
+  IF NEW.status = 'rejected' THEN
+    RETURN NULL;
+  END IF;
+
+  FOREACH _rid IN ARRAY array_prepend(NEW.resource_id, NEW.extra_resource_ids) LOOP
+    BEGIN
+      INSERT INTO rsvp.reservation_resources (reservation_id, tenant_id, resource_id, timespan)
+        VALUES (NEW.id, NEW.tenant_id, _rid, NEW.timespan);
+    EXCEPTION WHEN exclusion_violation THEN
+      GET STACKED DIAGNOSTICS _detail = PG_EXCEPTION_DETAIL;
+      _details := array_append(_details, _detail);
+    END;
+  END LOOP;
+
+  IF array_length(_details, 1) > 0 THEN
+    RAISE EXCEPTION 'conflicting key value violates exclusion constraint "reservation_resources_conflict"'
+      USING ERRCODE = 'exclusion_violation',
+        SCHEMA = 'rsvp',
+        TABLE = 'reservations',
+        CONSTRAINT = 'reservation_resources_conflict',
+        DETAIL = array_to_string(_details, E'\n');
+  END IF;
+
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER reservations_resources_trigger ON rsvp.reservations;
+CREATE TRIGGER reservations_resources_trigger AFTER INSERT OR UPDATE OF tenant_id, resource_id, extra_resource_ids, timespan, status ON rsvp.reservations FOR EACH ROW EXECUTE PROCEDURE rsvp.reservations_resources_trigger();
@@ -0,0 +1,48 @@
+-- 枚举值无法直接删除，需要重建类型
+DROP FUNCTION rsvp.query;
+DROP TRIGGER reservations_resources_trigger ON rsvp.reservations;
+ALTER TYPE rsvp.reservation_status RENAME TO reservation_status_old;
+CREATE TYPE rsvp.reservation_status AS ENUM('unknown', 'pending', 'confirmed', 'blocked', 'awaiting_approval', 'rejected');
+ALTER TABLE rsvp.reservations ALTER COLUMN status DROP DEFAULT;
+ALTER TABLE rsvp.reservations ALTER COLUMN status TYPE rsvp.reservation_status USING status::text::rsvp.reservation_status;
+ALTER TABLE rsvp.reservations ALTER COLUMN status SET DEFAULT 'pending';
+DROP TYPE rsvp.reservation_status_old;
+CREATE TRIGGER reservations_resources_trigger AFTER INSERT OR UPDATE OF tenant_id, resource_id, extra_resource_ids, timespan, status ON rsvp.reservations FOR EACH ROW EXECUTE PROCEDURE rsvp.reservations_resources_trigger();
+
+CREATE OR REPLACE FUNCTION rsvp.query(
+  tid text,
+  uid text,
+  rid text,
+  during TSTZRANGE,
+  status rsvp.reservation_status,
+  page integer DEFAULT 1,
+  page_size integer DEFAULT 10,
+  is_desc bool DEFAULT FALSE
+  ) RETURNS TABLE (LIKE rsvp.reservations) AS $$
+DECLARE
+  _sql text;
+BEGIN
+  _sql := format(
+    'SELECT * FROM rsvp.reservations WHERE tenant_id = %L AND %L @> timespan AND status = %L AND %s ORDER BY lower(timespan) %s LIMIT %L::integer OFFSET %L::integer',
+    tid,
+    during,
+    status,
+    CASE
+      WHEN uid IS NULL AND rid IS NULL THEN 'TRUE'
+      WHEN uid IS NULL THEN 'id IN (SELECT reservation_id FROM rsvp.reservation_resources WHERE tenant_id=' || quote_literal(tid) || ' AND resource_id=' || quote_literal(rid) || ')'
+      WHEN rid IS NULL THEN 'user_id=' || quote_literal(uid)
+      ELSE 'user_id=' || quote_literal(uid) || ' AND id IN (SELECT reservation_id FROM rsvp.reservation_resources WHERE tenant_id=' || quote_literal(tid) || ' AND resource_id=' || quote_literal(rid) || ')'
+    END,
+    CASE
+      WHEN is_desc THEN 'DESC'
+      ELSE 'ASC'
+    END,
+    page_size,
+    (page - 1) * page_size
+  );
+  -- log the sql
+  RAISE NOTICE '%', _sql;
+
+  RETURN QUERY EXECUTE _sql;
+END;
+$$ LANGUAGE plpgsql;
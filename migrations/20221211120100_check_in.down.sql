@@ -0,0 +1,4 @@
+DROP TABLE rsvp.user_no_shows;
+DROP INDEX rsvp.reservations_not_checked_in_idx;
+UPDATE rsvp.reservations SET status = 'confirmed' WHERE status = 'no_show';
+ALTER TABLE rsvp.reservations DROP COLUMN checked_in_at;
@@ -0,0 +1,13 @@
+ALTER TABLE rsvp.reservations ADD COLUMN checked_in_at TIMESTAMPTZ;
+
+-- 便于后台任务查找未签到的预订
+CREATE INDEX reservations_not_checked_in_idx ON rsvp.reservations (lower(timespan)) WHERE checked_in_at IS NULL AND status IN ('pending', 'confirmed');
+
+-- 每个用户的爽约次数，供预订策略使用
+CREATE TABLE rsvp.user_no_shows (
+  tenant_id VARCHAR(64) NOT NULL,
+  user_id VARCHAR(64) NOT NULL,
+  count BIGINT NOT NULL DEFAULT 0,
+
+  CONSTRAINT user_no_shows_pkey PRIMARY KEY (tenant_id, user_id)
+);
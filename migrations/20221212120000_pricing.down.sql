@@ -0,0 +1,2 @@
+ALTER TABLE rsvp.reservations DROP COLUMN price;
+ALTER TABLE rsvp.resources DROP COLUMN rate_card;
@@ -0,0 +1,5 @@
+-- 资源的价目表（protobuf 编码的 RateCard），为空表示免费
+ALTER TABLE rsvp.resources ADD COLUMN rate_card BYTEA;
+
+-- 创建或改期时报出的价格，单位为最小货币单位
+ALTER TABLE rsvp.reservations ADD COLUMN price BIGINT NOT NULL DEFAULT 0;
@@ -0,0 +1 @@
+DROP TABLE rsvp.calendar_feeds;
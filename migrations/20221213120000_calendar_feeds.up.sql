@@ -0,0 +1,10 @@
+-- 每个用户的日历订阅地址中的密钥，泄露后可重新生成
+CREATE TABLE rsvp.calendar_feeds (
+  tenant_id VARCHAR(64) NOT NULL,
+  user_id VARCHAR(64) NOT NULL,
+  token VARCHAR(64) NOT NULL DEFAULT replace(gen_random_uuid()::text || gen_random_uuid()::text, '-', ''),
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+
+  CONSTRAINT calendar_feeds_pkey PRIMARY KEY (tenant_id, user_id),
+  CONSTRAINT calendar_feeds_token_key UNIQUE (token)
+);
@@ -0,0 +1,26 @@
+DROP TRIGGER reservation_changes_webhooks_trigger ON rsvp.reservation_changes;
+DROP FUNCTION rsvp.reservation_changes_webhooks_trigger;
+DROP TABLE rsvp.webhook_dead_letters;
+DROP TABLE rsvp.webhook_deliveries;
+DROP TABLE rsvp.webhooks;
+
+CREATE OR REPLACE FUNCTION rsvp.reservations_trigger() RETURNS TRIGGER AS $$
+BEGIN
+  IF TG_OP = 'INSERT' THEN
+    INSERT INTO rsvp.reservation_changes (reservation_id, op) VALUES (NEW.id, 'create');
+  ELSIF TG_OP = 'UPDATE' THEN
+    IF OLD.status<>NEW.status THEN
+      INSERT INTO rsvp.reservation_changes (reservation_id, op) VALUES (NEW.id, 'update');
+    END IF;
+  ELSIF TG_OP = 'DELETE' THEN
+    INSERT INTO rsvp.reservation_changes (reservation_id, op) VALUES (OLD.id, 'delete');
+  END IF;
+  NOTIFY reservation_update;
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+ALTER TABLE rsvp.reservation_changes DROP COLUMN created_at;
+ALTER TABLE rsvp.reservation_changes DROP COLUMN reservation;
+ALTER TABLE rsvp.reservation_changes DROP COLUMN tenant_id;
+ALTER TABLE rsvp.reservation_changes DROP CONSTRAINT reservation_changes_pkey;
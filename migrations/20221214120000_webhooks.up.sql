@@ -0,0 +1,80 @@
+-- 记录变更时的预订快照，删除之后也能推送完整的预订数据
+ALTER TABLE rsvp.reservation_changes ADD CONSTRAINT reservation_changes_pkey PRIMARY KEY (id);
+ALTER TABLE rsvp.reservation_changes ADD COLUMN tenant_id VARCHAR(64);
+ALTER TABLE rsvp.reservation_changes ADD COLUMN reservation JSONB;
+ALTER TABLE rsvp.reservation_changes ADD COLUMN created_at TIMESTAMPTZ NOT NULL DEFAULT now();
+
+CREATE OR REPLACE FUNCTION rsvp.reservations_trigger() RETURNS TRIGGER AS $$
+BEGIN
+  IF TG_OP = 'INSERT' THEN
+    INSERT INTO rsvp.reservation_changes (reservation_id, op, tenant_id, reservation) VALUES (NEW.id, 'create', NEW.tenant_id, to_jsonb(NEW));
+  ELSIF TG_OP = 'UPDATE' THEN
+    IF OLD.status<>NEW.status THEN
+      INSERT INTO rsvp.reservation_changes (reservation_id, op, tenant_id, reservation) VALUES (NEW.id, 'update', NEW.tenant_id, to_jsonb(NEW));
+    END IF;
+  ELSIF TG_OP = 'DELETE' THEN
+    INSERT INTO rsvp.reservation_changes (reservation_id, op, tenant_id, reservation) VALUES (OLD.id, 'delete', OLD.tenant_id, to_jsonb(OLD));
+  END IF;
+  NOTIFY reservation_update;
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+-- webhook 订阅，过滤条件为空表示不过滤
+CREATE TABLE rsvp.webhooks (
+  id uuid NOT NULL DEFAULT gen_random_uuid(),
+  tenant_id VARCHAR(64) NOT NULL,
+  url TEXT NOT NULL,
+  -- 用于对推送内容做 HMAC 签名
+  secret TEXT NOT NULL,
+  resource_id VARCHAR(64),
+  user_id VARCHAR(64),
+  ops rsvp.reservation_update_type[] NOT NULL DEFAULT '{}',
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+
+  CONSTRAINT webhooks_pkey PRIMARY KEY (id)
+);
+
+CREATE INDEX webhooks_tenant_id_idx ON rsvp.webhooks (tenant_id);
+
+-- 待投递的推送，失败后按退避时间重试
+CREATE TABLE rsvp.webhook_deliveries (
+  id BIGSERIAL NOT NULL,
+  webhook_id uuid NOT NULL REFERENCES rsvp.webhooks (id) ON DELETE CASCADE,
+  change_id INTEGER NOT NULL REFERENCES rsvp.reservation_changes (id) ON DELETE CASCADE,
+  attempts INTEGER NOT NULL DEFAULT 0,
+  next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  last_error TEXT,
+
+  CONSTRAINT webhook_deliveries_pkey PRIMARY KEY (id)
+);
+
+CREATE INDEX webhook_deliveries_next_attempt_at_idx ON rsvp.webhook_deliveries (next_attempt_at);
+
+-- 多次重试仍失败的推送
+CREATE TABLE rsvp.webhook_dead_letters (
+  id BIGINT NOT NULL,
+  webhook_id uuid NOT NULL REFERENCES rsvp.webhooks (id) ON DELETE CASCADE,
+  change_id INTEGER NOT NULL REFERENCES rsvp.reservation_changes (id) ON DELETE CASCADE,
+  attempts INTEGER NOT NULL,
+  last_error TEXT,
+  failed_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+
+  CONSTRAINT webhook_dead_letters_pkey PRIMARY KEY (id)
+);
+
+-- 记录变更时为匹配的订阅创建待投递的推送，与变更在同一事务中
+CREATE OR REPLACE FUNCTION rsvp.reservation_changes_webhooks_trigger() RETURNS TRIGGER AS $$
+BEGIN
+  INSERT INTO rsvp.webhook_deliveries (webhook_id, change_id)
+    SELECT w.id, NEW.id FROM rsvp.webhooks w
+    WHERE w.tenant_id = NEW.tenant_id
+      AND (w.user_id IS NULL OR w.user_id = NEW.reservation->>'user_id')
+      AND (w.resource_id IS NULL OR w.resource_id = NEW.reservation->>'resource_id'
+        OR NEW.reservation->'extra_resource_ids' ? w.resource_id)
+      AND (cardinality(w.ops) = 0 OR NEW.op = ANY(w.ops));
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER reservation_changes_webhooks_trigger AFTER INSERT ON rsvp.reservation_changes FOR EACH ROW EXECUTE PROCEDURE rsvp.reservation_changes_webhooks_trigger();
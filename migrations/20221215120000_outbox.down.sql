@@ -0,0 +1,4 @@
+DROP TRIGGER reservations_outbox_trigger ON rsvp.reservations;
+DROP FUNCTION rsvp.reservations_outbox_trigger;
+DROP TABLE rsvp.outbox_offsets;
+DROP TABLE rsvp.outbox;
@@ -0,0 +1,39 @@
+-- 事务性发件箱：每次变更都在同一事务中写入，由服务中的转发器投递到外部
+CREATE TABLE rsvp.outbox (
+  id BIGSERIAL NOT NULL,
+  -- 写入事件的事务号，只有小于当前快照 xmin 的事务才确定已结束，按 (txid, id) 顺序读取不会遗漏
+  txid BIGINT NOT NULL DEFAULT txid_current(),
+  tenant_id VARCHAR(64) NOT NULL,
+  op rsvp.reservation_update_type NOT NULL,
+  reservation JSONB NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+
+  CONSTRAINT outbox_pkey PRIMARY KEY (id)
+);
+
+CREATE INDEX outbox_txid_id_idx ON rsvp.outbox (txid, id);
+
+-- 每个消费者已投递到的位置
+CREATE TABLE rsvp.outbox_offsets (
+  consumer VARCHAR(256) NOT NULL,
+  txid BIGINT NOT NULL,
+  id BIGINT NOT NULL,
+  updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+
+  CONSTRAINT outbox_offsets_pkey PRIMARY KEY (consumer)
+);
+
+CREATE OR REPLACE FUNCTION rsvp.reservations_outbox_trigger() RETURNS TRIGGER AS $$
+BEGIN
+  IF TG_OP = 'INSERT' THEN
+    INSERT INTO rsvp.outbox (tenant_id, op, reservation) VALUES (NEW.tenant_id, 'create', to_jsonb(NEW));
+  ELSIF TG_OP = 'UPDATE' THEN
+    INSERT INTO rsvp.outbox (tenant_id, op, reservation) VALUES (NEW.tenant_id, 'update', to_jsonb(NEW));
+  ELSIF TG_OP = 'DELETE' THEN
+    INSERT INTO rsvp.outbox (tenant_id, op, reservation) VALUES (OLD.tenant_id, 'delete', to_jsonb(OLD));
+  END IF;
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER reservations_outbox_trigger AFTER INSERT OR UPDATE OR DELETE ON rsvp.reservations FOR EACH ROW EXECUTE PROCEDURE rsvp.reservations_outbox_trigger();
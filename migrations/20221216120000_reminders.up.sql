@@ -0,0 +1,16 @@
+-- 预订开始前的提醒，在创建或改期时生成，取消预订时一并删除
+CREATE TABLE rsvp.reminders (
+  id BIGSERIAL NOT NULL,
+  reservation_id uuid NOT NULL REFERENCES rsvp.reservations (id) ON DELETE CASCADE,
+  -- 提前多久提醒
+  lead_time INTERVAL NOT NULL,
+  remind_at TIMESTAMPTZ NOT NULL,
+  -- 发送中的提醒在此之前不会被再次取出
+  locked_until TIMESTAMPTZ,
+  sent_at TIMESTAMPTZ,
+
+  CONSTRAINT reminders_pkey PRIMARY KEY (id),
+  CONSTRAINT reminders_lead_time_key UNIQUE (reservation_id, lead_time)
+);
+
+CREATE INDEX reminders_remind_at_idx ON rsvp.reminders (remind_at) WHERE sent_at IS NULL;
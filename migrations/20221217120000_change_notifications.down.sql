@@ -0,0 +1,15 @@
+CREATE OR REPLACE FUNCTION rsvp.reservations_trigger() RETURNS TRIGGER AS $$
+BEGIN
+  IF TG_OP = 'INSERT' THEN
+    INSERT INTO rsvp.reservation_changes (reservation_id, op, tenant_id, reservation) VALUES (NEW.id, 'create', NEW.tenant_id, to_jsonb(NEW));
+  ELSIF TG_OP = 'UPDATE' THEN
+    IF OLD.status<>NEW.status THEN
+      INSERT INTO rsvp.reservation_changes (reservation_id, op, tenant_id, reservation) VALUES (NEW.id, 'update', NEW.tenant_id, to_jsonb(NEW));
+    END IF;
+  ELSIF TG_OP = 'DELETE' THEN
+    INSERT INTO rsvp.reservation_changes (reservation_id, op, tenant_id, reservation) VALUES (OLD.id, 'delete', OLD.tenant_id, to_jsonb(OLD));
+  END IF;
+  NOTIFY reservation_update;
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
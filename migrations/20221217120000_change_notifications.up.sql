@@ -0,0 +1,40 @@
+-- 每次变更都带上预订及其占用的资源发出通知（包括不改变状态的更新），供各服务实例的缓存失效使用；
+-- 资源过多使通知超过 8000 字节的上限时不带资源列表，由缓存丢弃该租户的全部资源；
+-- reservation_changes 的记录规则保持不变
+CREATE OR REPLACE FUNCTION rsvp.reservations_trigger() RETURNS TRIGGER AS $$
+DECLARE
+  _rsvp rsvp.reservations;
+  _resource_ids VARCHAR(64)[] := '{}';
+  _payload text;
+BEGIN
+  IF TG_OP = 'INSERT' THEN
+    INSERT INTO rsvp.reservation_changes (reservation_id, op, tenant_id, reservation) VALUES (NEW.id, 'create', NEW.tenant_id, to_jsonb(NEW));
+  ELSIF TG_OP = 'UPDATE' THEN
+    IF OLD.status<>NEW.status THEN
+      INSERT INTO rsvp.reservation_changes (reservation_id, op, tenant_id, reservation) VALUES (NEW.id, 'update', NEW.tenant_id, to_jsonb(NEW));
+    END IF;
+  ELSIF TG_OP = 'DELETE' THEN
+    INSERT INTO rsvp.reservation_changes (reservation_id, op, tenant_id, reservation) VALUES (OLD.id, 'delete', OLD.tenant_id, to_jsonb(OLD));
+  END IF;
+
+  -- 更新前后占用的资源都需要失效
+  IF TG_OP <> 'INSERT' THEN
+    _rsvp := OLD;
+    _resource_ids := array_prepend(OLD.resource_id, OLD.extra_resource_ids);
+  END IF;
+  IF TG_OP <> 'DELETE' THEN
+    _rsvp := NEW;
+    _resource_ids := _resource_ids || array_prepend(NEW.resource_id, NEW.extra_resource_ids);
+  END IF;
+  _payload := json_build_object(
+    'tenant_id', _rsvp.tenant_id,
+    'id', _rsvp.id,
+    'resource_ids', _resource_ids
+  )::text;
+  IF octet_length(_payload) >= 8000 THEN
+    _payload := json_build_object('tenant_id', _rsvp.tenant_id, 'id', _rsvp.id)::text;
+  END IF;
+  PERFORM pg_notify('reservation_update', _payload);
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,55 @@
+-- 游标分页：按 (lower(timespan), id) 排序，从上一页最后一条之后继续
+CREATE INDEX reservations_tenant_start_id_idx ON rsvp.reservations (tenant_id, lower(timespan), id);
+
+DROP FUNCTION rsvp.query;
+CREATE OR REPLACE FUNCTION rsvp.query(
+  tid text,
+  uid text,
+  rid text,
+  during TSTZRANGE,
+  status rsvp.reservation_status,
+  page integer DEFAULT 1,
+  page_size integer DEFAULT 10,
+  is_desc bool DEFAULT FALSE,
+  cursor_start timestamptz DEFAULT NULL,
+  cursor_id uuid DEFAULT NULL
+  ) RETURNS TABLE (LIKE rsvp.reservations) AS $$
+DECLARE
+  _sql text;
+BEGIN
+  _sql := format(
+    'SELECT * FROM rsvp.reservations WHERE tenant_id = %L AND %L @> timespan AND status = %L AND %s AND %s ORDER BY lower(timespan) %s, id %s LIMIT %L::integer OFFSET %L::bigint',
+    tid,
+    during,
+    status,
+    CASE
+      WHEN uid IS NULL AND rid IS NULL THEN 'TRUE'
+      WHEN uid IS NULL THEN 'id IN (SELECT reservation_id FROM rsvp.reservation_resources WHERE tenant_id=' || quote_literal(tid) || ' AND resource_id=' || quote_literal(rid) || ')'
+      WHEN rid IS NULL THEN 'user_id=' || quote_literal(uid)
+      ELSE 'user_id=' || quote_literal(uid) || ' AND id IN (SELECT reservation_id FROM rsvp.reservation_resources WHERE tenant_id=' || quote_literal(tid) || ' AND resource_id=' || quote_literal(rid) || ')'
+    END,
+    CASE
+      WHEN cursor_start IS NULL THEN 'TRUE'
+      WHEN is_desc THEN format('(lower(timespan), id) < (%L::timestamptz, %L::uuid)', cursor_start, cursor_id)
+      ELSE format('(lower(timespan), id) > (%L::timestamptz, %L::uuid)', cursor_start, cursor_id)
+    END,
+    CASE
+      WHEN is_desc THEN 'DESC'
+      ELSE 'ASC'
+    END,
+    CASE
+      WHEN is_desc THEN 'DESC'
+      ELSE 'ASC'
+    END,
+    page_size,
+    CASE
+      WHEN cursor_start IS NULL THEN (page - 1)::bigint * page_size
+      ELSE 0
+    END
+  );
+  -- log the sql
+  RAISE NOTICE '%', _sql;
+
+  RETURN QUERY EXECUTE _sql;
+END;
+$$ LANGUAGE plpgsql;
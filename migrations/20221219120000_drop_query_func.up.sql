@@ -0,0 +1,2 @@
+-- 查询条件改由 ReservationFilter 在 Rust 中拼装并绑定参数
+DROP FUNCTION rsvp.query;
@@ -0,0 +1 @@
+DROP INDEX rsvp.reservations_note_search_idx;
@@ -0,0 +1,2 @@
+-- 备注全文检索，默认使用 simple 配置（不做词干提取），查询时也须写成 to_tsvector('simple', note) 才能用上索引
+CREATE INDEX reservations_note_search_idx ON rsvp.reservations USING GIN (to_tsvector('simple', note));
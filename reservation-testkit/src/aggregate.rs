@@ -0,0 +1,122 @@
+use crate::{december, new_rsvp, tenant};
+use abi::{Aggregate, AggregateGroup, ReservationStatus, TimeMatch};
+use reservation::Rsvp;
+
+pub async fn aggregate_should_group_in_window(rsvp: &(impl Rsvp + Sync)) {
+    let tid = tenant("aggregate");
+    let mut rsvps = vec![];
+    for (uid, rid, start, end) in [
+        (
+            "alice",
+            "room-1",
+            "2022-12-10T10:00:00Z",
+            "2022-12-10T12:00:00Z",
+        ),
+        (
+            "bob",
+            "room-1",
+            "2022-12-10T14:00:00Z",
+            "2022-12-10T15:00:00Z",
+        ),
+        (
+            "alice",
+            "room-2",
+            "2022-12-11T23:00:00Z",
+            "2022-12-12T01:00:00Z",
+        ),
+        // only the hour in december is counted
+        (
+            "alice",
+            "room-3",
+            "2022-11-30T23:00:00Z",
+            "2022-12-01T01:00:00Z",
+        ),
+    ] {
+        let mut input = new_rsvp(&tid, uid, rid, start, end);
+        if rsvps.is_empty() {
+            input.extra_resource_ids = vec!["room-2".into()];
+        }
+        rsvps.push(rsvp.reserve(input, None).await.unwrap());
+    }
+    rsvp.change_status(tid.clone(), rsvps[1].id.clone(), None, None)
+        .await
+        .unwrap();
+
+    let query =
+        december(&tid, "", "", ReservationStatus::Unknown).with_time_match(TimeMatch::Overlaps);
+    let aggregate =
+        |group_by, utc_offset_minutes| rsvp.aggregate(query.clone(), group_by, utc_offset_minutes);
+    let hours = |key: &str, count, hours: i64| Aggregate::new(key, count, hours * 3600);
+
+    assert_eq!(
+        aggregate(AggregateGroup::None, 0).await.unwrap(),
+        vec![hours("", 4, 6)]
+    );
+    // a reservation counts for each of its resources
+    assert_eq!(
+        aggregate(AggregateGroup::Resource, 0).await.unwrap(),
+        vec![
+            hours("room-1", 2, 3),
+            hours("room-2", 2, 4),
+            hours("room-3", 1, 1)
+        ]
+    );
+    assert_eq!(
+        aggregate(AggregateGroup::User, 0).await.unwrap(),
+        vec![hours("alice", 3, 5), hours("bob", 1, 1)]
+    );
+    assert_eq!(
+        aggregate(AggregateGroup::Status, 0).await.unwrap(),
+        vec![hours("confirmed", 1, 1), hours("pending", 3, 5)]
+    );
+    assert_eq!(
+        aggregate(AggregateGroup::Day, 0).await.unwrap(),
+        vec![
+            hours("2022-11-30", 1, 1),
+            hours("2022-12-10", 2, 3),
+            hours("2022-12-11", 1, 2)
+        ]
+    );
+    assert_eq!(
+        aggregate(AggregateGroup::Day, 120).await.unwrap(),
+        vec![
+            hours("2022-12-01", 1, 1),
+            hours("2022-12-10", 2, 3),
+            hours("2022-12-12", 1, 2)
+        ]
+    );
+
+    // the filters of the query apply
+    let query = december(&tid, "", "room-2", ReservationStatus::Pending);
+    assert_eq!(
+        rsvp.aggregate(query.clone(), AggregateGroup::User, 0)
+            .await
+            .unwrap(),
+        vec![hours("alice", 2, 4)]
+    );
+    // the other resources of those reservations aren't counted
+    assert_eq!(
+        rsvp.aggregate(query, AggregateGroup::Resource, 0)
+            .await
+            .unwrap(),
+        vec![hours("room-2", 2, 4)]
+    );
+    let query = december(&tid, "carol", "", ReservationStatus::Unknown);
+    assert_eq!(
+        rsvp.aggregate(query.clone(), AggregateGroup::None, 0)
+            .await
+            .unwrap(),
+        vec![hours("", 0, 0)]
+    );
+    assert!(rsvp
+        .aggregate(query.clone(), AggregateGroup::Day, 0)
+        .await
+        .unwrap()
+        .is_empty());
+
+    let err = rsvp
+        .aggregate(query, AggregateGroup::Day, 24 * 60)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, abi::Error::InvalidTime), "{:?}", err);
+}
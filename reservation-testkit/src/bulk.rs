@@ -0,0 +1,137 @@
+use crate::{december, new_rsvp, tenant};
+use abi::ReservationStatus;
+use reservation::Rsvp;
+
+pub async fn bulk_reserve_should_skip_bad_rows(rsvp: &(impl Rsvp + Sync)) {
+    let tid = tenant("bulk");
+    rsvp.reserve(
+        new_rsvp(
+            &tid,
+            "alice",
+            "room-1",
+            "2022-12-10T10:00:00Z",
+            "2022-12-10T12:00:00Z",
+        ),
+        None,
+    )
+    .await
+    .unwrap();
+
+    let rows = vec![
+        // conflicts with the existing reservation
+        new_rsvp(
+            &tid,
+            "bob",
+            "room-1",
+            "2022-12-10T11:00:00Z",
+            "2022-12-10T13:00:00Z",
+        ),
+        new_rsvp(
+            &tid,
+            "bob",
+            "room-2",
+            "2022-12-10T11:00:00Z",
+            "2022-12-10T13:00:00Z",
+        ),
+        // conflicts with the row above
+        new_rsvp(
+            &tid,
+            "bob",
+            "room-2",
+            "2022-12-10T12:00:00Z",
+            "2022-12-10T14:00:00Z",
+        ),
+        new_rsvp(
+            &tid,
+            "",
+            "room-3",
+            "2022-12-10T11:00:00Z",
+            "2022-12-10T13:00:00Z",
+        ),
+        new_rsvp(
+            &tid,
+            "bob",
+            "room-3",
+            "2022-12-10T13:00:00Z",
+            "2022-12-10T14:00:00Z",
+        ),
+    ];
+
+    let dry_run = rsvp.bulk_reserve(rows.clone(), true).await.unwrap();
+    assert_eq!(dry_run.reserved.len(), 2);
+    assert!(dry_run
+        .reserved
+        .iter()
+        .all(|r| r.id.is_empty() && r.version == 0));
+    let rejected: Vec<_> = dry_run.rejected.iter().map(|(row, _)| *row).collect();
+    assert_eq!(rejected, vec![0, 2, 3]);
+    assert!(matches!(
+        dry_run.rejected[0].1,
+        abi::Error::ConflictReservation(_)
+    ));
+    assert!(matches!(
+        dry_run.rejected[1].1,
+        abi::Error::ConflictReservation(_)
+    ));
+    assert!(matches!(
+        dry_run.rejected[2].1,
+        abi::Error::InvalidUserId(_)
+    ));
+    let query = december(&tid, "bob", "", ReservationStatus::Pending);
+    assert!(rsvp.query(query.clone()).await.unwrap().is_empty());
+
+    let result = rsvp.bulk_reserve(rows, false).await.unwrap();
+    let rejected: Vec<_> = result.rejected.iter().map(|(row, _)| *row).collect();
+    assert_eq!(rejected, vec![0, 2, 3]);
+    assert_eq!(result.reserved.len(), 2);
+    for reserved in &result.reserved {
+        assert_eq!(reserved.version, 1);
+        assert_eq!(
+            &rsvp.get(tid.clone(), reserved.id.clone()).await.unwrap(),
+            reserved
+        );
+    }
+    assert_eq!(rsvp.query(query).await.unwrap(), result.reserved);
+
+    // an empty batch does nothing
+    let result = rsvp.bulk_reserve(vec![], false).await.unwrap();
+    assert!(result.reserved.is_empty() && result.rejected.is_empty());
+}
+
+pub async fn bulk_reserve_should_fail_on_other_errors(rsvp: &(impl Rsvp + Sync)) {
+    let tid = tenant("bulk-fail");
+    let card = abi::RateCard {
+        hourly_rate: i64::MAX,
+        peak_hourly_rate: 0,
+        peak_periods: vec![],
+        minimum_charge: 0,
+        utc_offset_minutes: 0,
+    };
+    rsvp.update_resource(abi::Resource::new(&tid, "vault", false).with_rate_card(card))
+        .await
+        .unwrap();
+
+    // the first row is fine and the second can't be priced, neither is kept
+    let rows = vec![
+        new_rsvp(
+            &tid,
+            "alice",
+            "room",
+            "2022-12-10T10:00:00Z",
+            "2022-12-10T12:00:00Z",
+        ),
+        new_rsvp(
+            &tid,
+            "alice",
+            "vault",
+            "2022-12-10T10:00:00Z",
+            "2022-12-10T12:00:00Z",
+        ),
+    ];
+    for dry_run in [true, false] {
+        let err = rsvp.bulk_reserve(rows.clone(), dry_run).await.unwrap_err();
+        assert!(matches!(err, abi::Error::InvalidRateCard(_)), "{:?}", err);
+    }
+    let query = december(&tid, "alice", "", ReservationStatus::Pending);
+    assert!(rsvp.query(query).await.unwrap().is_empty());
+}
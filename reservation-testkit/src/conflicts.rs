@@ -0,0 +1,283 @@
+use crate::{conflicts, december, new_rsvp, tenant};
+use abi::{ReservationConflictInfo, ReservationStatus};
+use reservation::Rsvp;
+
+pub async fn overlapping_windows_should_conflict(rsvp: &(impl Rsvp + Sync)) {
+    let tid = tenant("overlap");
+    rsvp.reserve(
+        new_rsvp(
+            &tid,
+            "alice",
+            "room",
+            "2022-12-10T10:00:00Z",
+            "2022-12-10T12:00:00Z",
+        ),
+        None,
+    )
+    .await
+    .unwrap();
+
+    let windows = [
+        // identical
+        ("2022-12-10T10:00:00Z", "2022-12-10T12:00:00Z"),
+        // overlaps the start
+        ("2022-12-10T09:00:00Z", "2022-12-10T10:00:01Z"),
+        // overlaps the end
+        ("2022-12-10T11:59:59Z", "2022-12-10T13:00:00Z"),
+        // inside
+        ("2022-12-10T10:30:00Z", "2022-12-10T11:00:00Z"),
+        // around
+        ("2022-12-10T09:00:00Z", "2022-12-10T13:00:00Z"),
+    ];
+    for (start, end) in windows {
+        let err = rsvp
+            .reserve(new_rsvp(&tid, "bob", "room", start, end), None)
+            .await
+            .unwrap_err();
+        if let abi::Error::ConflictReservation(ReservationConflictInfo::Parsed(info)) = err {
+            assert_eq!(info.new.rid, "room");
+            assert_eq!(
+                info.new.start,
+                start.parse::<chrono::DateTime<chrono::Utc>>().unwrap()
+            );
+            assert_eq!(info.exist.rid, "room");
+            assert_eq!(info.exist.start.to_rfc3339(), "2022-12-10T10:00:00+00:00");
+            assert_eq!(info.exist.end.to_rfc3339(), "2022-12-10T12:00:00+00:00");
+        } else {
+            panic!("expect conflict for {} - {}, got {:?}", start, end, err);
+        }
+    }
+
+    // a conflicting reservation leaves nothing behind
+    let query = december(&tid, "bob", "", ReservationStatus::Pending);
+    assert!(rsvp.query(query).await.unwrap().is_empty());
+}
+
+pub async fn adjacent_windows_should_not_conflict(rsvp: &(impl Rsvp + Sync)) {
+    let tid = tenant("adjacent");
+    for (start, end) in [
+        ("2022-12-10T10:00:00Z", "2022-12-10T12:00:00Z"),
+        // ends when the first one starts
+        ("2022-12-10T08:00:00Z", "2022-12-10T10:00:00Z"),
+        // starts when the first one ends
+        ("2022-12-10T12:00:00Z", "2022-12-10T14:00:00Z"),
+    ] {
+        rsvp.reserve(new_rsvp(&tid, "alice", "room", start, end), None)
+            .await
+            .unwrap();
+    }
+}
+
+pub async fn other_resources_and_tenants_should_not_conflict(rsvp: &(impl Rsvp + Sync)) {
+    let tid = tenant("isolation");
+    let other = tenant("isolation");
+    let first = new_rsvp(
+        &tid,
+        "alice",
+        "room",
+        "2022-12-10T10:00:00Z",
+        "2022-12-10T12:00:00Z",
+    );
+    let first = rsvp.reserve(first, None).await.unwrap();
+
+    let mut same_window = first.clone();
+    same_window.id.clear();
+    same_window.resource_id = "other-room".into();
+    rsvp.reserve(same_window.clone(), None).await.unwrap();
+
+    same_window.resource_id = "room".into();
+    same_window.tenant_id = other;
+    let second = rsvp.reserve(same_window, None).await.unwrap();
+    assert_ne!(first.id, second.id);
+}
+
+pub async fn multiple_resources_should_report_each_conflict(rsvp: &(impl Rsvp + Sync)) {
+    let tid = tenant("multi");
+    let mut first = new_rsvp(
+        &tid,
+        "alice",
+        "room-1",
+        "2022-12-10T10:00:00Z",
+        "2022-12-10T12:00:00Z",
+    );
+    first.extra_resource_ids = vec!["projector".into(), "vc-kit".into()];
+    let first = rsvp.reserve(first, None).await.unwrap();
+    assert_eq!(first.extra_resource_ids, vec!["projector", "vc-kit"]);
+
+    let mut second = new_rsvp(
+        &tid,
+        "bob",
+        "room-2",
+        "2022-12-10T11:00:00Z",
+        "2022-12-10T13:00:00Z",
+    );
+    second.extra_resource_ids = vec!["projector".into(), "vc-kit".into()];
+    let err = rsvp.reserve(second.clone(), None).await.unwrap_err();
+    assert_eq!(
+        conflicts(err),
+        vec![
+            ("projector".to_string(), "projector".to_string()),
+            ("vc-kit".to_string(), "vc-kit".to_string())
+        ]
+    );
+
+    // one shared resource is a single conflict
+    second.extra_resource_ids = vec!["vc-kit".into()];
+    let err = rsvp.reserve(second.clone(), None).await.unwrap_err();
+    assert!(matches!(err, abi::Error::ConflictReservation(_)));
+
+    // found by any of its resources
+    for rid in ["room-1", "projector", "vc-kit"] {
+        let query = december(&tid, "", rid, ReservationStatus::Pending);
+        assert_eq!(rsvp.query(query).await.unwrap(), vec![first.clone()]);
+    }
+
+    // cancelling releases all of its resources at once
+    rsvp.delete(tid.clone(), first.id, None, None)
+        .await
+        .unwrap();
+    second.extra_resource_ids = vec!["projector".into(), "vc-kit".into()];
+    rsvp.reserve(second, None).await.unwrap();
+}
+
+pub async fn related_resources_should_conflict(rsvp: &(impl Rsvp + Sync)) {
+    let tid = tenant("related");
+    for (parent, child) in [
+        ("floor", "hall-ab"),
+        ("hall-ab", "hall-a"),
+        ("hall-ab", "hall-b"),
+    ] {
+        rsvp.link_resources(tid.clone(), parent.into(), child.into())
+            .await
+            .unwrap();
+    }
+    // linking twice is fine
+    rsvp.link_resources(tid.clone(), "hall-ab".into(), "hall-a".into())
+        .await
+        .unwrap();
+
+    let booked = rsvp
+        .reserve(
+            new_rsvp(
+                &tid,
+                "alice",
+                "hall-a",
+                "2022-12-10T10:00:00Z",
+                "2022-12-10T12:00:00Z",
+            ),
+            None,
+        )
+        .await
+        .unwrap();
+
+    // ancestors of a booked resource are blocked, and the other way round
+    for rid in ["hall-ab", "floor"] {
+        let err = rsvp
+            .reserve(
+                new_rsvp(
+                    &tid,
+                    "bob",
+                    rid,
+                    "2022-12-10T11:00:00Z",
+                    "2022-12-10T13:00:00Z",
+                ),
+                None,
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(
+            conflicts(err),
+            vec![(rid.to_string(), "hall-a".to_string())]
+        );
+    }
+
+    // siblings and other tenants don't block each other
+    rsvp.reserve(
+        new_rsvp(
+            &tid,
+            "bob",
+            "hall-b",
+            "2022-12-10T10:00:00Z",
+            "2022-12-10T12:00:00Z",
+        ),
+        None,
+    )
+    .await
+    .unwrap();
+    rsvp.reserve(
+        new_rsvp(
+            &tenant("related"),
+            "bob",
+            "hall-ab",
+            "2022-12-10T10:00:00Z",
+            "2022-12-10T12:00:00Z",
+        ),
+        None,
+    )
+    .await
+    .unwrap();
+
+    // once unlinked the parent is independent
+    rsvp.delete(tid.clone(), booked.id, None, None)
+        .await
+        .unwrap();
+    rsvp.unlink_resources(tid.clone(), "hall-ab".into(), "hall-b".into())
+        .await
+        .unwrap();
+    rsvp.reserve(
+        new_rsvp(
+            &tid,
+            "carol",
+            "hall-ab",
+            "2022-12-10T10:00:00Z",
+            "2022-12-10T12:00:00Z",
+        ),
+        None,
+    )
+    .await
+    .unwrap();
+}
+
+pub async fn resource_cycle_should_reject(rsvp: &(impl Rsvp + Sync)) {
+    let tid = tenant("cycle");
+    rsvp.link_resources(tid.clone(), "a".into(), "b".into())
+        .await
+        .unwrap();
+    rsvp.link_resources(tid.clone(), "b".into(), "c".into())
+        .await
+        .unwrap();
+
+    for (parent, child) in [("c", "a"), ("b", "a"), ("a", "a")] {
+        let err = rsvp
+            .link_resources(tid.clone(), parent.into(), child.into())
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(&err, abi::Error::ResourceCycle { parent: p, child: c } if p == parent && c == child),
+            "{:?}",
+            err
+        );
+    }
+    let err = rsvp
+        .link_resources(tid.clone(), "".into(), "a".into())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, abi::Error::InvalidResourceId(_)));
+
+    let err = rsvp
+        .unlink_resources(tid.clone(), "a".into(), "c".into())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, abi::Error::NotFound));
+
+    // the same relation in another tenant is no cycle
+    rsvp.link_resources(tenant("cycle"), "c".into(), "a".into())
+        .await
+        .unwrap();
+    rsvp.unlink_resources(tid.clone(), "b".into(), "c".into())
+        .await
+        .unwrap();
+    rsvp.link_resources(tid, "c".into(), "a".into())
+        .await
+        .unwrap();
+}
@@ -0,0 +1,42 @@
+use crate::tenant;
+use reservation::Rsvp;
+
+pub async fn feed_token_should_be_stable_until_reset(rsvp: &(impl Rsvp + Sync)) {
+    let tid = tenant("feed");
+    let token = rsvp.feed_token(tid.clone(), "alice".into()).await.unwrap();
+    assert!(token.len() >= 32);
+    assert_eq!(
+        rsvp.feed_token(tid.clone(), "alice".into()).await.unwrap(),
+        token
+    );
+    assert_eq!(
+        rsvp.feed_owner(token.clone()).await.unwrap(),
+        (tid.clone(), "alice".to_string())
+    );
+
+    // every user of every tenant has a token of their own
+    let bob = rsvp.feed_token(tid.clone(), "bob".into()).await.unwrap();
+    let other = rsvp
+        .feed_token(tenant("feed"), "alice".into())
+        .await
+        .unwrap();
+    assert_ne!(bob, token);
+    assert_ne!(other, token);
+
+    let reset = rsvp
+        .reset_feed_token(tid.clone(), "alice".into())
+        .await
+        .unwrap();
+    assert_ne!(reset, token);
+    assert_eq!(
+        rsvp.feed_token(tid.clone(), "alice".into()).await.unwrap(),
+        reset
+    );
+    let err = rsvp.feed_owner(token).await.unwrap_err();
+    assert!(matches!(err, abi::Error::NotFound), "{:?}", err);
+
+    let err = rsvp.feed_token(tid.clone(), "".into()).await.unwrap_err();
+    assert!(matches!(err, abi::Error::InvalidUserId(_)), "{:?}", err);
+    let err = rsvp.reset_feed_token(tid, "".into()).await.unwrap_err();
+    assert!(matches!(err, abi::Error::InvalidUserId(_)), "{:?}", err);
+}
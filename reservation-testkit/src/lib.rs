@@ -0,0 +1,118 @@
+//! behaviour every `Rsvp` implementation must share, run it against a fresh backend with
+//! `reservation_testkit::run(&rsvp).await`. each scenario works in a tenant of its own,
+//! so they can share one backend and be run one by one as well.
+//! backends are expected to use the default check-in policy
+mod aggregate;
+mod bulk;
+mod conflicts;
+mod feeds;
+mod lifecycle;
+mod query;
+mod resources;
+mod schedule;
+
+use abi::{ReservationConflictInfo, ReservationQuery, ReservationStatus};
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use reservation::Rsvp;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+pub use aggregate::*;
+pub use bulk::*;
+pub use conflicts::*;
+pub use feeds::*;
+pub use lifecycle::*;
+pub use query::*;
+pub use resources::*;
+pub use schedule::*;
+
+/// id of a reservation that never exists
+pub const MISSING_ID: &str = "9c1b3e4a-7d2f-4c6b-8a5e-000000000000";
+
+/// run every scenario in order
+pub async fn run(rsvp: &(impl Rsvp + Sync)) {
+    reserve_should_assign_id_and_version(rsvp).await;
+    invalid_reservation_should_reject(rsvp).await;
+    overlapping_windows_should_conflict(rsvp).await;
+    adjacent_windows_should_not_conflict(rsvp).await;
+    other_resources_and_tenants_should_not_conflict(rsvp).await;
+    multiple_resources_should_report_each_conflict(rsvp).await;
+    related_resources_should_conflict(rsvp).await;
+    resource_cycle_should_reject(rsvp).await;
+    status_transitions_should_follow_rules(rsvp).await;
+    approval_should_gate_confirmation(rsvp).await;
+    versions_should_guard_updates(rsvp).await;
+    idempotency_keys_should_replay_per_op(rsvp).await;
+    idempotency_keys_should_expire(rsvp).await;
+    missing_reservations_should_not_be_found(rsvp).await;
+    query_should_filter(rsvp).await;
+    query_should_match_time(rsvp).await;
+    query_should_search_notes(rsvp).await;
+    query_should_order_and_paginate(rsvp).await;
+    query_should_continue_after_cursor(rsvp).await;
+    invalid_query_should_reject(rsvp).await;
+    aggregate_should_group_in_window(rsvp).await;
+    check_in_should_only_work_around_start(rsvp).await;
+    release_no_shows_should_free_remaining_time(rsvp).await;
+    reschedule_should_move_and_price(rsvp).await;
+    feed_token_should_be_stable_until_reset(rsvp).await;
+    bulk_reserve_should_skip_bad_rows(rsvp).await;
+    bulk_reserve_should_fail_on_other_errors(rsvp).await;
+}
+
+/// a tenant no other scenario or earlier run uses
+fn tenant(name: &str) -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    format!(
+        "{}-{}-{}",
+        name,
+        Utc::now().timestamp_nanos_opt().unwrap(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// current time in whole seconds, backends may store less than nanoseconds
+fn now() -> DateTime<Utc> {
+    Utc::now().duration_trunc(Duration::seconds(1)).unwrap()
+}
+
+fn new_rsvp(tid: &str, uid: &str, rid: &str, start: &str, end: &str) -> abi::Reservation {
+    abi::Reservation::new_pending(
+        tid,
+        uid,
+        rid,
+        start.parse().unwrap(),
+        end.parse().unwrap(),
+        "note",
+    )
+}
+
+/// first page of ten of the reservations in december 2022, ascending
+fn december(tid: &str, uid: &str, rid: &str, status: ReservationStatus) -> ReservationQuery {
+    ReservationQuery::new(
+        tid,
+        uid,
+        rid,
+        "2022-12-01T00:00:00Z".parse().unwrap(),
+        "2023-01-01T00:00:00Z".parse().unwrap(),
+        status,
+        1,
+        10,
+        false,
+    )
+}
+
+/// (new, existing) resource of each conflict reported by the error
+fn conflicts(err: abi::Error) -> Vec<(String, String)> {
+    let infos = match err {
+        abi::Error::ConflictReservation(info) => vec![info],
+        abi::Error::ConflictReservations(infos) => infos,
+        e => panic!("expect conflict, got {:?}", e),
+    };
+    infos
+        .into_iter()
+        .map(|info| match info {
+            ReservationConflictInfo::Parsed(conflict) => (conflict.new.rid, conflict.exist.rid),
+            ReservationConflictInfo::Unparsed(s) => panic!("unparsed conflict {}", s),
+        })
+        .collect()
+}
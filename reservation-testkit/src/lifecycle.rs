@@ -0,0 +1,554 @@
+use crate::{december, new_rsvp, tenant, MISSING_ID};
+use abi::ReservationStatus;
+use chrono::{Duration, Utc};
+use reservation::Rsvp;
+
+pub async fn reserve_should_assign_id_and_version(rsvp: &(impl Rsvp + Sync)) {
+    let tid = tenant("reserve");
+    let mut input = new_rsvp(
+        &tid,
+        "alice",
+        "room",
+        "2022-12-10T10:00:00-0700",
+        "2022-12-10T12:00:00-0700",
+    );
+    input.note = "quarterly review".into();
+    let reserved = rsvp.reserve(input.clone(), None).await.unwrap();
+    assert!(!reserved.id.is_empty());
+    assert_eq!(reserved.version, 1);
+    assert_eq!(reserved.status, ReservationStatus::Pending as i32);
+    assert_eq!(reserved.price, 0);
+    assert_eq!(reserved.start, input.start);
+    assert_eq!(reserved.end, input.end);
+    assert_eq!(reserved.note, "quarterly review");
+    assert_eq!(
+        rsvp.get(tid.clone(), reserved.id.clone()).await.unwrap(),
+        reserved
+    );
+
+    // the requested status is kept
+    let mut blocked = new_rsvp(
+        &tid,
+        "admin",
+        "room",
+        "2022-12-11T10:00:00Z",
+        "2022-12-11T12:00:00Z",
+    );
+    blocked.status = ReservationStatus::Blocked as i32;
+    let blocked = rsvp.reserve(blocked, None).await.unwrap();
+    assert_eq!(blocked.status, ReservationStatus::Blocked as i32);
+    assert_ne!(blocked.id, reserved.id);
+
+    // one that sets no status is pending
+    let mut unset = new_rsvp(
+        &tid,
+        "bob",
+        "room",
+        "2022-12-12T10:00:00Z",
+        "2022-12-12T12:00:00Z",
+    );
+    unset.status = ReservationStatus::Unknown as i32;
+    let unset = rsvp.reserve(unset, None).await.unwrap();
+    assert_eq!(unset.status, ReservationStatus::Pending as i32);
+}
+
+pub async fn invalid_reservation_should_reject(rsvp: &(impl Rsvp + Sync)) {
+    let tid = tenant("invalid");
+    let valid = new_rsvp(
+        &tid,
+        "alice",
+        "room",
+        "2022-12-10T10:00:00Z",
+        "2022-12-10T12:00:00Z",
+    );
+
+    let mut input = valid.clone();
+    input.tenant_id.clear();
+    let err = rsvp.reserve(input, None).await.unwrap_err();
+    assert!(matches!(err, abi::Error::InvalidTenantId(_)), "{:?}", err);
+
+    let mut input = valid.clone();
+    input.user_id.clear();
+    let err = rsvp.reserve(input, None).await.unwrap_err();
+    assert!(matches!(err, abi::Error::InvalidUserId(_)), "{:?}", err);
+
+    let mut input = valid.clone();
+    input.resource_id.clear();
+    let err = rsvp.reserve(input, None).await.unwrap_err();
+    assert!(matches!(err, abi::Error::InvalidResourceId(_)), "{:?}", err);
+
+    let mut input = valid.clone();
+    input.extra_resource_ids = vec!["projector".into(), "room".into()];
+    let err = rsvp.reserve(input, None).await.unwrap_err();
+    assert!(
+        matches!(&err, abi::Error::InvalidResourceId(rid) if rid == "room"),
+        "{:?}",
+        err
+    );
+
+    // empty and backwards windows
+    let mut input = valid.clone();
+    input.end = input.start.clone();
+    let err = rsvp.reserve(input, None).await.unwrap_err();
+    assert!(matches!(err, abi::Error::InvalidTime), "{:?}", err);
+
+    let mut input = valid.clone();
+    std::mem::swap(&mut input.start, &mut input.end);
+    let err = rsvp.reserve(input, None).await.unwrap_err();
+    assert!(matches!(err, abi::Error::InvalidTime), "{:?}", err);
+
+    let mut input = valid.clone();
+    input.end = None;
+    let err = rsvp.reserve(input, None).await.unwrap_err();
+    assert!(matches!(err, abi::Error::InvalidTime), "{:?}", err);
+
+    // statuses only reached through the lifecycle can't be asked for
+    for status in [
+        ReservationStatus::AwaitingApproval,
+        ReservationStatus::Rejected,
+        ReservationStatus::NoShow,
+    ] {
+        let mut input = valid.clone();
+        input.status = status as i32;
+        let err = rsvp.reserve(input, None).await.unwrap_err();
+        assert!(
+            matches!(err, abi::Error::InvalidStatus(s) if s == status as i32),
+            "{:?}",
+            err
+        );
+    }
+
+    // nothing was stored
+    let query = december(&tid, "alice", "", ReservationStatus::Pending);
+    assert!(rsvp.query(query).await.unwrap().is_empty());
+}
+
+pub async fn status_transitions_should_follow_rules(rsvp: &(impl Rsvp + Sync)) {
+    let tid = tenant("status");
+    let reserved = rsvp
+        .reserve(
+            new_rsvp(
+                &tid,
+                "alice",
+                "room",
+                "2022-12-10T10:00:00Z",
+                "2022-12-10T12:00:00Z",
+            ),
+            None,
+        )
+        .await
+        .unwrap();
+
+    let confirmed = rsvp
+        .change_status(tid.clone(), reserved.id.clone(), None, None)
+        .await
+        .unwrap();
+    assert_eq!(confirmed.status, ReservationStatus::Confirmed as i32);
+    assert_eq!(confirmed.version, 2);
+
+    // only pending reservations can be confirmed
+    let err = rsvp
+        .change_status(tid.clone(), reserved.id.clone(), None, None)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, abi::Error::NotFound), "{:?}", err);
+
+    let mut blocked = new_rsvp(
+        &tid,
+        "admin",
+        "room",
+        "2022-12-11T10:00:00Z",
+        "2022-12-11T12:00:00Z",
+    );
+    blocked.status = ReservationStatus::Blocked as i32;
+    let blocked = rsvp.reserve(blocked, None).await.unwrap();
+    let err = rsvp
+        .change_status(tid.clone(), blocked.id.clone(), None, None)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, abi::Error::NotFound), "{:?}", err);
+
+    // notes can be changed in any status, deleting returns the last state
+    let updated = rsvp
+        .update_note(
+            tid.clone(),
+            blocked.id.clone(),
+            "maintenance".into(),
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(updated.note, "maintenance");
+    assert_eq!(updated.status, ReservationStatus::Blocked as i32);
+    let deleted = rsvp
+        .delete(tid.clone(), blocked.id.clone(), None, None)
+        .await
+        .unwrap();
+    assert_eq!(deleted, updated);
+    let err = rsvp.get(tid.clone(), blocked.id).await.unwrap_err();
+    assert!(matches!(err, abi::Error::NotFound), "{:?}", err);
+
+    assert_eq!(rsvp.get(tid, reserved.id).await.unwrap(), confirmed);
+}
+
+pub async fn versions_should_guard_updates(rsvp: &(impl Rsvp + Sync)) {
+    let tid = tenant("version");
+    let reserved = rsvp
+        .reserve(
+            new_rsvp(
+                &tid,
+                "alice",
+                "room",
+                "2022-12-10T10:00:00Z",
+                "2022-12-10T12:00:00Z",
+            ),
+            None,
+        )
+        .await
+        .unwrap();
+
+    let first = rsvp
+        .update_note(
+            tid.clone(),
+            reserved.id.clone(),
+            "first".into(),
+            Some(1),
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(first.version, 2);
+
+    let err = rsvp
+        .update_note(
+            tid.clone(),
+            reserved.id.clone(),
+            "second".into(),
+            Some(1),
+            None,
+        )
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(
+            err,
+            abi::Error::PreconditionFailed {
+                expected: 1,
+                actual: 2
+            }
+        ),
+        "{:?}",
+        err
+    );
+    let err = rsvp
+        .change_status(tid.clone(), reserved.id.clone(), Some(3), None)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, abi::Error::PreconditionFailed { .. }),
+        "{:?}",
+        err
+    );
+    let err = rsvp
+        .delete(tid.clone(), reserved.id.clone(), Some(1), None)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, abi::Error::PreconditionFailed { .. }),
+        "{:?}",
+        err
+    );
+
+    // a failed update changes nothing
+    assert_eq!(
+        rsvp.get(tid.clone(), reserved.id.clone()).await.unwrap(),
+        first
+    );
+    let key = Some("version-key".to_string());
+    let confirmed = rsvp
+        .change_status(tid.clone(), reserved.id.clone(), Some(2), key.clone())
+        .await
+        .unwrap();
+    assert_eq!(confirmed.version, 3);
+
+    // a key retried with another expected version is not a retry
+    let err = rsvp
+        .change_status(tid.clone(), reserved.id.clone(), Some(3), key)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, abi::Error::IdempotencyKeyReused(_)),
+        "{:?}",
+        err
+    );
+    rsvp.delete(tid, reserved.id, Some(3), None).await.unwrap();
+}
+
+pub async fn idempotency_keys_should_replay_per_op(rsvp: &(impl Rsvp + Sync)) {
+    let tid = tenant("idempotency");
+    let input = new_rsvp(
+        &tid,
+        "alice",
+        "room",
+        "2022-12-10T10:00:00Z",
+        "2022-12-10T12:00:00Z",
+    );
+    let key = Some("reserve-key".to_string());
+    let first = rsvp.reserve(input.clone(), key.clone()).await.unwrap();
+    // a retry would conflict if it reserved again
+    let retried = rsvp.reserve(input.clone(), key.clone()).await.unwrap();
+    assert_eq!(first, retried);
+    let query = december(&tid, "alice", "", ReservationStatus::Pending);
+    assert_eq!(rsvp.query(query).await.unwrap(), vec![first.clone()]);
+
+    // the same key with another payload is not a retry
+    let mut moved = input.clone();
+    moved.note = "moved".into();
+    let err = rsvp.reserve(moved, key.clone()).await.unwrap_err();
+    assert!(
+        matches!(err, abi::Error::IdempotencyKeyReused(_)),
+        "{:?}",
+        err
+    );
+
+    // the result is replayed even after the reservation changed
+    let key = Some("note-key".to_string());
+    let noted = rsvp
+        .update_note(tid.clone(), first.id.clone(), "a".into(), None, key.clone())
+        .await
+        .unwrap();
+    rsvp.update_note(tid.clone(), first.id.clone(), "b".into(), None, None)
+        .await
+        .unwrap();
+    let replayed = rsvp
+        .update_note(tid.clone(), first.id.clone(), "a".into(), None, key.clone())
+        .await
+        .unwrap();
+    assert_eq!(noted, replayed);
+    assert_eq!(
+        rsvp.get(tid.clone(), first.id.clone()).await.unwrap().note,
+        "b"
+    );
+
+    let err = rsvp
+        .update_note(tid.clone(), first.id.clone(), "c".into(), None, key.clone())
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, abi::Error::IdempotencyKeyReused(_)),
+        "{:?}",
+        err
+    );
+    let err = rsvp
+        .change_status(tid.clone(), first.id.clone(), None, key)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, abi::Error::IdempotencyKeyReused(_)),
+        "{:?}",
+        err
+    );
+
+    // keys belong to their tenant
+    let mut other = input;
+    other.tenant_id = tenant("idempotency");
+    let other = rsvp
+        .reserve(other, Some("reserve-key".to_string()))
+        .await
+        .unwrap();
+    assert_ne!(other.id, first.id);
+
+    let key = Some("cancel-key".to_string());
+    let deleted = rsvp
+        .delete(tid.clone(), first.id.clone(), None, key.clone())
+        .await
+        .unwrap();
+    let retried = rsvp
+        .delete(tid.clone(), first.id.clone(), None, key)
+        .await
+        .unwrap();
+    assert_eq!(deleted, retried);
+
+    // a failed call doesn't use up its key
+    let key = Some("failed-key".to_string());
+    let err = rsvp
+        .change_status(tid.clone(), first.id.clone(), None, key.clone())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, abi::Error::NotFound), "{:?}", err);
+    let reserved = rsvp
+        .reserve(
+            new_rsvp(
+                &tid,
+                "bob",
+                "room",
+                "2022-12-10T10:00:00Z",
+                "2022-12-10T12:00:00Z",
+            ),
+            None,
+        )
+        .await
+        .unwrap();
+    let confirmed = rsvp
+        .change_status(tid.clone(), reserved.id, None, key.clone())
+        .await
+        .unwrap();
+    assert_eq!(confirmed.status, ReservationStatus::Confirmed as i32);
+
+    // nor is the same key on another reservation
+    let another = rsvp
+        .reserve(
+            new_rsvp(
+                &tid,
+                "carol",
+                "room",
+                "2022-12-11T10:00:00Z",
+                "2022-12-11T12:00:00Z",
+            ),
+            None,
+        )
+        .await
+        .unwrap();
+    let err = rsvp
+        .change_status(tid.clone(), another.id.clone(), None, key)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, abi::Error::IdempotencyKeyReused(_)),
+        "{:?}",
+        err
+    );
+    let pending = rsvp.get(tid.clone(), another.id).await.unwrap();
+    assert_eq!(pending.status, ReservationStatus::Pending as i32);
+
+    // keys longer than can be stored are rejected before anything is reserved
+    let long = new_rsvp(
+        &tid,
+        "dave",
+        "room",
+        "2022-12-12T10:00:00Z",
+        "2022-12-12T12:00:00Z",
+    );
+    let err = rsvp
+        .reserve(long.clone(), Some("k".repeat(65)))
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, abi::Error::InvalidIdempotencyKey(_)),
+        "{:?}",
+        err
+    );
+    let query = december(&tid, "dave", "", ReservationStatus::Unknown);
+    assert!(rsvp.query(query).await.unwrap().is_empty());
+    rsvp.reserve(long, Some("k".repeat(64))).await.unwrap();
+}
+
+pub async fn idempotency_keys_should_expire(rsvp: &(impl Rsvp + Sync)) {
+    let tid = tenant("idempotency-expiry");
+    let key = Some("expiring-key".to_string());
+    let first = rsvp
+        .reserve(
+            new_rsvp(
+                &tid,
+                "alice",
+                "room",
+                "2022-12-10T10:00:00Z",
+                "2022-12-10T12:00:00Z",
+            ),
+            key.clone(),
+        )
+        .await
+        .unwrap();
+    let other = new_rsvp(
+        &tid,
+        "alice",
+        "room",
+        "2022-12-11T10:00:00Z",
+        "2022-12-11T12:00:00Z",
+    );
+
+    // keys used since are kept
+    rsvp.expire_idempotency_keys(Utc::now() - Duration::hours(1))
+        .await
+        .unwrap();
+    let err = rsvp.reserve(other.clone(), key.clone()).await.unwrap_err();
+    assert!(
+        matches!(err, abi::Error::IdempotencyKeyReused(_)),
+        "{:?}",
+        err
+    );
+
+    // an expired key can be used for another request
+    let expired = rsvp
+        .expire_idempotency_keys(Utc::now() + Duration::minutes(1))
+        .await
+        .unwrap();
+    assert!(expired >= 1);
+    let second = rsvp.reserve(other, key).await.unwrap();
+    assert_ne!(second.id, first.id);
+}
+
+pub async fn missing_reservations_should_not_be_found(rsvp: &(impl Rsvp + Sync)) {
+    let tid = tenant("missing");
+    let reserved = rsvp
+        .reserve(
+            new_rsvp(
+                &tid,
+                "alice",
+                "room",
+                "2022-12-10T10:00:00Z",
+                "2022-12-10T12:00:00Z",
+            ),
+            None,
+        )
+        .await
+        .unwrap();
+
+    // unknown ids and ids of other tenants look the same
+    let other = tenant("missing");
+    for (tid, id) in [
+        (tid.clone(), MISSING_ID.to_string()),
+        (other, reserved.id.clone()),
+    ] {
+        let err = rsvp.get(tid.clone(), id.clone()).await.unwrap_err();
+        assert!(matches!(err, abi::Error::NotFound), "{:?}", err);
+        let err = rsvp
+            .change_status(tid.clone(), id.clone(), None, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, abi::Error::NotFound), "{:?}", err);
+        let err = rsvp
+            .update_note(tid.clone(), id.clone(), "note".into(), None, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, abi::Error::NotFound), "{:?}", err);
+        let err = rsvp
+            .update_note(tid.clone(), id.clone(), "note".into(), Some(1), None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, abi::Error::NotFound), "{:?}", err);
+        let err = rsvp.delete(tid, id, None, None).await.unwrap_err();
+        assert!(matches!(err, abi::Error::NotFound), "{:?}", err);
+    }
+    assert_eq!(rsvp.get(tid.clone(), reserved.id).await.unwrap().version, 1);
+
+    let err = rsvp
+        .get(tid.clone(), "not-a-uuid".into())
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(&err, abi::Error::InvalidReservationId(id) if id == "not-a-uuid"),
+        "{:?}",
+        err
+    );
+    let err = rsvp
+        .delete(tid, "not-a-uuid".into(), None, None)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, abi::Error::InvalidReservationId(_)),
+        "{:?}",
+        err
+    );
+}
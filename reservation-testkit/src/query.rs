@@ -0,0 +1,435 @@
+use crate::{december, new_rsvp, tenant};
+use abi::{QueryPageResponse, ReservationQuery, ReservationStatus, TimeMatch};
+use reservation::Rsvp;
+
+pub async fn query_should_filter(rsvp: &(impl Rsvp + Sync)) {
+    let tid = tenant("filter");
+    let mut rsvps = vec![];
+    for (uid, rid, start, end) in [
+        (
+            "alice",
+            "room-1",
+            "2022-12-10T10:00:00Z",
+            "2022-12-10T12:00:00Z",
+        ),
+        (
+            "alice",
+            "room-2",
+            "2022-12-11T10:00:00Z",
+            "2022-12-11T12:00:00Z",
+        ),
+        (
+            "bob",
+            "room-1",
+            "2022-12-12T10:00:00Z",
+            "2022-12-12T12:00:00Z",
+        ),
+        // only partly inside december
+        (
+            "alice",
+            "room-1",
+            "2022-12-31T22:00:00Z",
+            "2023-01-01T02:00:00Z",
+        ),
+    ] {
+        let reserved = rsvp
+            .reserve(new_rsvp(&tid, uid, rid, start, end), None)
+            .await
+            .unwrap();
+        rsvps.push(reserved);
+    }
+    let confirmed = rsvp
+        .change_status(tid.clone(), rsvps[1].id.clone(), None, None)
+        .await
+        .unwrap();
+
+    let query = december(&tid, "alice", "", ReservationStatus::Pending);
+    assert_eq!(rsvp.query(query).await.unwrap(), vec![rsvps[0].clone()]);
+    let query = december(&tid, "alice", "", ReservationStatus::Confirmed);
+    assert_eq!(rsvp.query(query).await.unwrap(), vec![confirmed.clone()]);
+    let query = december(&tid, "", "room-1", ReservationStatus::Pending);
+    assert_eq!(
+        rsvp.query(query).await.unwrap(),
+        vec![rsvps[0].clone(), rsvps[2].clone()]
+    );
+    let query = december(&tid, "bob", "room-1", ReservationStatus::Pending);
+    assert_eq!(rsvp.query(query).await.unwrap(), vec![rsvps[2].clone()]);
+    let query = december(&tid, "bob", "room-2", ReservationStatus::Pending);
+    assert!(rsvp.query(query).await.unwrap().is_empty());
+    let query = december(&tid, "", "", ReservationStatus::Blocked);
+    assert!(rsvp.query(query).await.unwrap().is_empty());
+
+    // several statuses at once, or every status
+    let query = december(&tid, "alice", "", ReservationStatus::Pending)
+        .with_statuses([ReservationStatus::Confirmed, ReservationStatus::Blocked]);
+    assert_eq!(
+        rsvp.query(query).await.unwrap(),
+        vec![rsvps[0].clone(), confirmed.clone()]
+    );
+    let query = december(&tid, "", "", ReservationStatus::Unknown)
+        .with_statuses([ReservationStatus::Confirmed]);
+    assert_eq!(rsvp.query(query).await.unwrap(), vec![confirmed.clone()]);
+    let query = december(&tid, "", "room-1", ReservationStatus::Unknown);
+    assert_eq!(
+        rsvp.query(query).await.unwrap(),
+        vec![rsvps[0].clone(), rsvps[2].clone()]
+    );
+    let query = december(&tid, "alice", "", ReservationStatus::Unknown);
+    assert_eq!(
+        rsvp.query(query).await.unwrap(),
+        vec![rsvps[0].clone(), confirmed.clone()]
+    );
+
+    // the window must contain the whole reservation, its bounds included
+    let mut query = december(&tid, "", "", ReservationStatus::Pending);
+    query.start = rsvps[0].start.clone();
+    query.end = rsvps[2].end.clone();
+    assert_eq!(
+        rsvp.query(query.clone()).await.unwrap(),
+        vec![rsvps[0].clone(), rsvps[2].clone()]
+    );
+    query.start = rsvps[2].start.clone();
+    query.end = rsvps[3].end.clone();
+    assert_eq!(
+        rsvp.query(query).await.unwrap(),
+        vec![rsvps[2].clone(), rsvps[3].clone()]
+    );
+
+    // other tenants see nothing
+    let query = december(&tenant("filter"), "alice", "", ReservationStatus::Pending);
+    assert!(rsvp.query(query).await.unwrap().is_empty());
+}
+
+pub async fn query_should_match_time(rsvp: &(impl Rsvp + Sync)) {
+    let tid = tenant("time");
+    let mut rsvps = vec![];
+    for (start, end) in [
+        // across the start of december
+        ("2022-11-30T22:00:00Z", "2022-12-01T02:00:00Z"),
+        ("2022-12-10T10:00:00Z", "2022-12-10T12:00:00Z"),
+        // across the end of december
+        ("2022-12-31T22:00:00Z", "2023-01-01T02:00:00Z"),
+    ] {
+        let reserved = rsvp
+            .reserve(new_rsvp(&tid, "alice", "room", start, end), None)
+            .await
+            .unwrap();
+        rsvps.push(reserved);
+    }
+    let ts = |s: &str| Some(abi::convert_to_timestamp(s.parse().unwrap()));
+    let matching = |start, end, time_match| {
+        let mut query =
+            december(&tid, "alice", "", ReservationStatus::Pending).with_time_match(time_match);
+        query.start = start;
+        query.end = end;
+        rsvp.query(query)
+    };
+    let whole_month = || (ts("2022-12-01T00:00:00Z"), ts("2023-01-01T00:00:00Z"));
+
+    let (start, end) = whole_month();
+    assert_eq!(
+        matching(start, end, TimeMatch::ContainedIn).await.unwrap(),
+        rsvps[1..2]
+    );
+    let (start, end) = whole_month();
+    assert_eq!(
+        matching(start, end, TimeMatch::Overlaps).await.unwrap(),
+        rsvps
+    );
+    let (start, end) = whole_month();
+    assert_eq!(
+        matching(start, end, TimeMatch::StartsWithin).await.unwrap(),
+        rsvps[1..]
+    );
+    let (start, end) = whole_month();
+    assert!(matching(start, end, TimeMatch::Contains)
+        .await
+        .unwrap()
+        .is_empty());
+    let (start, end) = (ts("2022-12-10T10:30:00Z"), ts("2022-12-10T11:00:00Z"));
+    assert_eq!(
+        matching(start, end, TimeMatch::Contains).await.unwrap(),
+        rsvps[1..2]
+    );
+
+    // windows are half open, touching isn't overlapping
+    let (start, end) = (ts("2022-12-01T02:00:00Z"), ts("2022-12-10T10:00:00Z"));
+    assert!(matching(start, end, TimeMatch::Overlaps)
+        .await
+        .unwrap()
+        .is_empty());
+
+    // open windows
+    let end = ts("2022-12-05T00:00:00Z");
+    assert_eq!(
+        matching(None, end.clone(), TimeMatch::ContainedIn)
+            .await
+            .unwrap(),
+        rsvps[..1]
+    );
+    assert_eq!(
+        matching(None, end, TimeMatch::StartsWithin).await.unwrap(),
+        rsvps[..1]
+    );
+    let start = ts("2022-12-05T00:00:00Z");
+    assert_eq!(
+        matching(start.clone(), None, TimeMatch::ContainedIn)
+            .await
+            .unwrap(),
+        rsvps[1..]
+    );
+    assert!(matching(start, None, TimeMatch::Contains)
+        .await
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        matching(None, None, TimeMatch::ContainedIn).await.unwrap(),
+        rsvps
+    );
+}
+
+pub async fn query_should_search_notes(rsvp: &(impl Rsvp + Sync)) {
+    let tid = tenant("search");
+    let mut rsvps = vec![];
+    for (uid, day, note) in [
+        ("alice", 10, "Team offsite: planning"),
+        ("bob", 11, "team sync"),
+        (
+            "alice",
+            12,
+            "planning, planning and more PLANNING with the team",
+        ),
+        ("alice", 13, "1:1"),
+    ] {
+        let start = format!("2022-12-{}T10:00:00Z", day);
+        let end = format!("2022-12-{}T12:00:00Z", day);
+        let mut input = new_rsvp(&tid, uid, "room", &start, &end);
+        input.note = note.into();
+        rsvps.push(rsvp.reserve(input, None).await.unwrap());
+    }
+    let search =
+        |uid, text: &str| december(&tid, uid, "", ReservationStatus::Pending).with_search(text);
+
+    let found = rsvp.query(search("", "team")).await.unwrap();
+    assert_eq!(found, rsvps[..3]);
+    // every word must appear, case and punctuation don't matter
+    let found = rsvp.query(search("", "PLANNING, team!")).await.unwrap();
+    assert_eq!(found, vec![rsvps[0].clone(), rsvps[2].clone()]);
+    let found = rsvp.query(search("alice", "team")).await.unwrap();
+    assert_eq!(found, vec![rsvps[0].clone(), rsvps[2].clone()]);
+    assert!(rsvp
+        .query(search("", "team retro"))
+        .await
+        .unwrap()
+        .is_empty());
+    // nothing to search for matches every note
+    assert_eq!(rsvp.query(search("", "!!")).await.unwrap(), rsvps);
+
+    let query = ReservationQuery {
+        by_relevance: true,
+        ..search("", "planning")
+    };
+    assert_eq!(
+        rsvp.query(query.clone()).await.unwrap(),
+        vec![rsvps[2].clone(), rsvps[0].clone()]
+    );
+    let cursor = abi::Cursor::after(&rsvps[0]).unwrap().encode();
+    let err = rsvp.query(query.with_cursor(cursor)).await.unwrap_err();
+    assert!(matches!(err, abi::Error::InvalidCursor(_)), "{:?}", err);
+
+    // changed notes are searched by their new words
+    let updated = rsvp
+        .update_note(
+            tid.clone(),
+            rsvps[3].id.clone(),
+            "team lunch".into(),
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    let found = rsvp.query(search("", "team lunch")).await.unwrap();
+    assert_eq!(found, vec![updated]);
+    assert!(rsvp.query(search("", "1")).await.unwrap().is_empty());
+}
+
+pub async fn query_should_order_and_paginate(rsvp: &(impl Rsvp + Sync)) {
+    let tid = tenant("page");
+    let mut rsvps = vec![];
+    // reserved out of order
+    for day in [14, 11, 13, 10, 12] {
+        let start = format!("2022-12-{}T10:00:00Z", day);
+        let end = format!("2022-12-{}T12:00:00Z", day);
+        let reserved = rsvp
+            .reserve(new_rsvp(&tid, "alice", "room", &start, &end), None)
+            .await
+            .unwrap();
+        rsvps.push(reserved);
+    }
+    rsvps.sort_by_key(|r| r.start.as_ref().unwrap().seconds);
+
+    let page = |page, page_size, desc| ReservationQuery {
+        page,
+        page_size,
+        desc,
+        ..december(&tid, "alice", "room", ReservationStatus::Pending)
+    };
+    assert_eq!(rsvp.query(page(1, 10, false)).await.unwrap(), rsvps);
+    assert_eq!(rsvp.query(page(1, 2, false)).await.unwrap(), rsvps[0..2]);
+    assert_eq!(rsvp.query(page(2, 2, false)).await.unwrap(), rsvps[2..4]);
+    assert_eq!(rsvp.query(page(3, 2, false)).await.unwrap(), rsvps[4..]);
+    assert!(rsvp.query(page(4, 2, false)).await.unwrap().is_empty());
+
+    let mut desc = rsvps.clone();
+    desc.reverse();
+    assert_eq!(rsvp.query(page(1, 10, true)).await.unwrap(), desc);
+    assert_eq!(rsvp.query(page(2, 3, true)).await.unwrap(), desc[3..]);
+
+    // a page past every row is empty instead of overflowing the offset
+    assert!(rsvp
+        .query(page(i32::MAX, 1000, false))
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(matches!(
+        rsvp.query(page(0, 10, false)).await.unwrap_err(),
+        abi::Error::InvalidPage(0)
+    ));
+    assert!(matches!(
+        rsvp.query(page(1, 0, false)).await.unwrap_err(),
+        abi::Error::InvalidPageSize(0)
+    ));
+}
+
+pub async fn query_should_continue_after_cursor(rsvp: &(impl Rsvp + Sync)) {
+    let tid = tenant("cursor");
+    let mut rsvps = vec![];
+    // two of them start at the same time, the id breaks the tie
+    for (rid, day) in [
+        ("room-1", 12),
+        ("room-1", 10),
+        ("room-2", 12),
+        ("room-1", 14),
+    ] {
+        let start = format!("2022-12-{}T10:00:00Z", day);
+        let end = format!("2022-12-{}T12:00:00Z", day);
+        let reserved = rsvp
+            .reserve(new_rsvp(&tid, "alice", rid, &start, &end), None)
+            .await
+            .unwrap();
+        rsvps.push(reserved);
+    }
+    let key = |r: &abi::Reservation| (r.start.as_ref().unwrap().seconds, r.id.clone());
+    rsvps.sort_by_key(key);
+
+    let page = |cursor: &str, desc| ReservationQuery {
+        page_size: 2,
+        desc,
+        // ignored once there is a cursor
+        page: 5,
+        ..december(&tid, "alice", "", ReservationStatus::Pending).with_cursor(cursor)
+    };
+    let first = rsvp.query(page("", false)).await.unwrap();
+    assert!(first.is_empty());
+    let first = rsvp
+        .query(ReservationQuery {
+            page: 1,
+            ..page("", false)
+        })
+        .await
+        .unwrap();
+    assert_eq!(first, rsvps[0..2]);
+    let first = QueryPageResponse::new(first, 2).unwrap();
+
+    // reservations added before the cursor don't shift the following pages
+    let earlier = rsvp
+        .reserve(
+            new_rsvp(
+                &tid,
+                "alice",
+                "room-1",
+                "2022-12-01T10:00:00Z",
+                "2022-12-01T12:00:00Z",
+            ),
+            None,
+        )
+        .await
+        .unwrap();
+    let later = rsvp
+        .reserve(
+            new_rsvp(
+                &tid,
+                "alice",
+                "room-1",
+                "2022-12-20T10:00:00Z",
+                "2022-12-20T12:00:00Z",
+            ),
+            None,
+        )
+        .await
+        .unwrap();
+    let second = rsvp.query(page(&first.next_cursor, false)).await.unwrap();
+    assert_eq!(second, rsvps[2..4]);
+    let second = QueryPageResponse::new(second, 2).unwrap();
+    let third = rsvp.query(page(&second.next_cursor, false)).await.unwrap();
+    assert_eq!(third, vec![later.clone()]);
+    let third = QueryPageResponse::new(third, 2).unwrap();
+    assert!(third.next_cursor.is_empty());
+
+    // descending pages walk back from the cursor
+    rsvps.insert(0, earlier);
+    rsvps.push(later);
+    rsvps.reverse();
+    let mut seen = vec![];
+    let mut cursor = String::new();
+    loop {
+        let query = ReservationQuery {
+            page: 1,
+            ..page(&cursor, true)
+        };
+        let res = QueryPageResponse::new(rsvp.query(query).await.unwrap(), 2).unwrap();
+        seen.extend(res.reservations);
+        if res.next_cursor.is_empty() {
+            break;
+        }
+        cursor = res.next_cursor;
+    }
+    assert_eq!(seen, rsvps);
+
+    let err = rsvp.query(page("garbage", false)).await.unwrap_err();
+    assert!(matches!(err, abi::Error::InvalidCursor(_)), "{:?}", err);
+}
+
+pub async fn invalid_query_should_reject(rsvp: &(impl Rsvp + Sync)) {
+    let query = december("", "alice", "", ReservationStatus::Pending);
+    let err = rsvp.query(query).await.unwrap_err();
+    assert!(matches!(err, abi::Error::InvalidTenantId(_)), "{:?}", err);
+
+    let mut query = december(&tenant("invalid-query"), "", "", ReservationStatus::Pending);
+    query.end = query.start.clone();
+    let err = rsvp.query(query.clone()).await.unwrap_err();
+    assert!(matches!(err, abi::Error::InvalidTime), "{:?}", err);
+
+    // an open start is fine, an unknown way of matching it isn't
+    query.start = None;
+    query.time_match = 42;
+    let err = rsvp.query(query).await.unwrap_err();
+    assert!(matches!(err, abi::Error::InvalidTimeMatch(42)), "{:?}", err);
+
+    let mut query = december(&tenant("invalid-query"), "", "", ReservationStatus::Pending);
+    query.statuses = vec![ReservationStatus::Confirmed as i32, 42];
+    let err = rsvp.query(query).await.unwrap_err();
+    assert!(matches!(err, abi::Error::InvalidStatus(42)), "{:?}", err);
+
+    let query = abi::ReservationQuery {
+        search_language: "klingon".into(),
+        ..december(&tenant("invalid-query"), "", "", ReservationStatus::Pending)
+            .with_search("plans")
+    };
+    let err = rsvp.query(query).await.unwrap_err();
+    assert!(
+        matches!(&err, abi::Error::InvalidSearchLanguage(language) if language == "klingon"),
+        "{:?}",
+        err
+    );
+}
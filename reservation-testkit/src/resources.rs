@@ -0,0 +1,123 @@
+use crate::{conflicts, december, new_rsvp, tenant};
+use abi::ReservationStatus;
+use reservation::Rsvp;
+
+pub async fn approval_should_gate_confirmation(rsvp: &(impl Rsvp + Sync)) {
+    let tid = tenant("approval");
+    let resource = rsvp
+        .update_resource(abi::Resource::new(&tid, "boardroom", true))
+        .await
+        .unwrap();
+    assert!(resource.requires_approval);
+
+    // one restricted resource is enough
+    let mut input = new_rsvp(
+        &tid,
+        "alice",
+        "room",
+        "2022-12-10T10:00:00Z",
+        "2022-12-10T12:00:00Z",
+    );
+    input.extra_resource_ids = vec!["boardroom".into()];
+    let awaiting = rsvp.reserve(input.clone(), None).await.unwrap();
+    assert_eq!(awaiting.status, ReservationStatus::AwaitingApproval as i32);
+
+    // awaiting approval still holds the resources
+    let err = rsvp.reserve(input.clone(), None).await.unwrap_err();
+    assert_eq!(conflicts(err).len(), 2);
+
+    let err = rsvp
+        .change_status(tid.clone(), awaiting.id.clone(), None, None)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, abi::Error::NotFound), "{:?}", err);
+    let err = rsvp
+        .approve(
+            tid.clone(),
+            awaiting.id.clone(),
+            "".into(),
+            "ok".into(),
+            None,
+            None,
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, abi::Error::InvalidUserId(_)), "{:?}", err);
+
+    let approved = rsvp
+        .approve(
+            tid.clone(),
+            awaiting.id.clone(),
+            "manager".into(),
+            "looks good".into(),
+            Some(1),
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(approved.status, ReservationStatus::Pending as i32);
+    assert_eq!(approved.approver_id, "manager");
+    assert_eq!(approved.approval_reason, "looks good");
+    assert_eq!(approved.version, 2);
+
+    // reviewed reservations can't be reviewed again
+    let err = rsvp
+        .reject(
+            tid.clone(),
+            awaiting.id.clone(),
+            "manager".into(),
+            "changed my mind".into(),
+            None,
+            None,
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, abi::Error::NotFound), "{:?}", err);
+    let confirmed = rsvp
+        .change_status(tid.clone(), awaiting.id.clone(), None, None)
+        .await
+        .unwrap();
+    assert_eq!(confirmed.status, ReservationStatus::Confirmed as i32);
+
+    // rejected reservations release their resources but are kept
+    input.start = Some(abi::convert_to_timestamp(
+        "2022-12-11T10:00:00Z".parse().unwrap(),
+    ));
+    input.end = Some(abi::convert_to_timestamp(
+        "2022-12-11T12:00:00Z".parse().unwrap(),
+    ));
+    let awaiting = rsvp.reserve(input.clone(), None).await.unwrap();
+    let rejected = rsvp
+        .reject(
+            tid.clone(),
+            awaiting.id.clone(),
+            "manager".into(),
+            "under maintenance".into(),
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(rejected.status, ReservationStatus::Rejected as i32);
+    let query = december(&tid, "alice", "", ReservationStatus::Rejected);
+    assert_eq!(rsvp.query(query).await.unwrap(), vec![rejected]);
+    let again = rsvp.reserve(input.clone(), None).await.unwrap();
+    assert_eq!(again.status, ReservationStatus::AwaitingApproval as i32);
+
+    // lifting the restriction only affects new reservations
+    rsvp.update_resource(abi::Resource::new(&tid, "boardroom", false))
+        .await
+        .unwrap();
+    input.start = Some(abi::convert_to_timestamp(
+        "2022-12-12T10:00:00Z".parse().unwrap(),
+    ));
+    input.end = Some(abi::convert_to_timestamp(
+        "2022-12-12T12:00:00Z".parse().unwrap(),
+    ));
+    let pending = rsvp.reserve(input, None).await.unwrap();
+    assert_eq!(pending.status, ReservationStatus::Pending as i32);
+    assert_eq!(
+        rsvp.get(tid, again.id).await.unwrap().status,
+        ReservationStatus::AwaitingApproval as i32
+    );
+}
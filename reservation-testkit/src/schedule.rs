@@ -0,0 +1,397 @@
+use crate::{conflicts, new_rsvp, now, tenant};
+use abi::{convert_to_utc_time, ReservationStatus};
+use chrono::{DateTime, Duration, Utc};
+use reservation::Rsvp;
+
+fn window(
+    tid: &str,
+    uid: &str,
+    rid: &str,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> abi::Reservation {
+    abi::Reservation::new_pending(tid, uid, rid, start.into(), end.into(), "note")
+}
+
+pub async fn check_in_should_only_work_around_start(rsvp: &(impl Rsvp + Sync)) {
+    let tid = tenant("check-in");
+    let now = now();
+    let soon = rsvp
+        .reserve(
+            window(
+                &tid,
+                "alice",
+                "room",
+                now + Duration::minutes(5),
+                now + Duration::hours(1),
+            ),
+            None,
+        )
+        .await
+        .unwrap();
+    let checked_in = rsvp
+        .check_in(tid.clone(), soon.id.clone(), Some(1), None)
+        .await
+        .unwrap();
+    let at = checked_in.checked_in_at.clone().unwrap();
+    assert_eq!(checked_in.version, 2);
+
+    // checking in again keeps the first time
+    let again = rsvp
+        .check_in(tid.clone(), soon.id.clone(), None, None)
+        .await
+        .unwrap();
+    assert_eq!(again.checked_in_at, Some(at));
+
+    // confirmed reservations that started a moment ago can be checked in
+    let started = rsvp
+        .reserve(
+            window(
+                &tid,
+                "bob",
+                "room-2",
+                now - Duration::minutes(5),
+                now + Duration::hours(1),
+            ),
+            None,
+        )
+        .await
+        .unwrap();
+    rsvp.change_status(tid.clone(), started.id.clone(), None, None)
+        .await
+        .unwrap();
+    rsvp.check_in(tid.clone(), started.id, None, None)
+        .await
+        .unwrap();
+
+    for (start, status) in [
+        (now + Duration::hours(2), ReservationStatus::Pending),
+        (now - Duration::hours(2), ReservationStatus::Pending),
+        (now, ReservationStatus::Blocked),
+    ] {
+        let mut input = window(
+            &tid,
+            "carol",
+            "room-3",
+            start,
+            start + Duration::minutes(30),
+        );
+        input.status = status as i32;
+        let reserved = rsvp.reserve(input, None).await.unwrap();
+        let err = rsvp
+            .check_in(tid.clone(), reserved.id.clone(), None, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, abi::Error::CheckInNotAllowed), "{:?}", err);
+        assert!(rsvp
+            .get(tid.clone(), reserved.id)
+            .await
+            .unwrap()
+            .checked_in_at
+            .is_none());
+    }
+
+    let err = rsvp
+        .check_in(tid, crate::MISSING_ID.into(), None, None)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, abi::Error::NotFound), "{:?}", err);
+}
+
+pub async fn release_no_shows_should_free_remaining_time(rsvp: &(impl Rsvp + Sync)) {
+    let tid = tenant("no-show");
+    let now = now();
+    let missed = rsvp
+        .reserve(
+            window(
+                &tid,
+                "alice",
+                "room",
+                now - Duration::hours(1),
+                now + Duration::hours(1),
+            ),
+            None,
+        )
+        .await
+        .unwrap();
+    let upcoming = rsvp
+        .reserve(
+            window(
+                &tid,
+                "alice",
+                "room-2",
+                now + Duration::minutes(5),
+                now + Duration::hours(1),
+            ),
+            None,
+        )
+        .await
+        .unwrap();
+    // its check-in closed long ago, while the job wasn't running
+    let past = rsvp
+        .reserve(
+            window(
+                &tid,
+                "alice",
+                "room",
+                now - Duration::days(3),
+                now - Duration::days(3) + Duration::hours(1),
+            ),
+            None,
+        )
+        .await
+        .unwrap();
+    let mut blocked = window(
+        &tid,
+        "admin",
+        "room-3",
+        now - Duration::hours(1),
+        now + Duration::hours(1),
+    );
+    blocked.status = ReservationStatus::Blocked as i32;
+    let blocked = rsvp.reserve(blocked, None).await.unwrap();
+
+    // other tenants may be released as well
+    let mut released: Vec<_> = rsvp
+        .release_no_shows()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|r| r.tenant_id == tid)
+        .collect();
+    released.sort_by_key(|r| r.start.as_ref().map(|t| t.seconds));
+    assert_eq!(released.len(), 2);
+    // nothing is left of its time to release
+    assert_eq!(released[0].id, past.id);
+    assert_eq!(released[0].status, ReservationStatus::NoShow as i32);
+    assert_eq!(released[0].end, past.end);
+    let released = &released[1..];
+    assert_eq!(released[0].id, missed.id);
+    assert_eq!(released[0].status, ReservationStatus::NoShow as i32);
+    assert_eq!(released[0].version, 2);
+    assert_eq!(released[0].start, missed.start);
+    let end = convert_to_utc_time(released[0].end.clone().unwrap());
+    assert!(end >= now && end < now + Duration::minutes(5));
+    assert_eq!(
+        rsvp.get(tid.clone(), missed.id.clone()).await.unwrap(),
+        released[0]
+    );
+    assert_eq!(
+        rsvp.no_show_count(tid.clone(), "alice".into())
+            .await
+            .unwrap(),
+        2
+    );
+    assert_eq!(
+        rsvp.no_show_count(tid.clone(), "bob".into()).await.unwrap(),
+        0
+    );
+    for id in [upcoming.id, blocked.id] {
+        assert_ne!(
+            rsvp.get(tid.clone(), id).await.unwrap().status,
+            ReservationStatus::NoShow as i32
+        );
+    }
+
+    // the rest of the window can be booked again, the missed part stays booked
+    rsvp.reserve(
+        window(
+            &tid,
+            "bob",
+            "room",
+            now + Duration::minutes(5),
+            now + Duration::hours(1),
+        ),
+        None,
+    )
+    .await
+    .unwrap();
+    let err = rsvp
+        .reserve(
+            window(
+                &tid,
+                "bob",
+                "room",
+                now - Duration::minutes(30),
+                now - Duration::minutes(10),
+            ),
+            None,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(
+        conflicts(err),
+        vec![("room".to_string(), "room".to_string())]
+    );
+
+    // released only once
+    assert!(rsvp
+        .release_no_shows()
+        .await
+        .unwrap()
+        .iter()
+        .all(|r| r.tenant_id != tid));
+}
+
+pub async fn reschedule_should_move_and_price(rsvp: &(impl Rsvp + Sync)) {
+    let tid = tenant("reschedule");
+    let card = abi::RateCard {
+        hourly_rate: 1000,
+        peak_hourly_rate: 3000,
+        peak_periods: vec![abi::WeeklyPeriod {
+            weekdays: vec![1, 2, 3, 4, 5],
+            start_minute: 9 * 60,
+            end_minute: 17 * 60,
+        }],
+        minimum_charge: 0,
+        utc_offset_minutes: 0,
+    };
+    rsvp.update_resource(abi::Resource::new(&tid, "studio", false).with_rate_card(card))
+        .await
+        .unwrap();
+
+    // 2022-12-26 is a monday, the projector has no rate card and is free
+    let mut input = new_rsvp(
+        &tid,
+        "alice",
+        "studio",
+        "2022-12-26T08:00:00Z",
+        "2022-12-26T10:00:00Z",
+    );
+    input.extra_resource_ids = vec!["projector".into()];
+    assert_eq!(rsvp.quote(input.clone()).await.unwrap(), 4000);
+    let reserved = rsvp.reserve(input, None).await.unwrap();
+    assert_eq!(reserved.price, 4000);
+
+    let other = rsvp
+        .reserve(
+            new_rsvp(
+                &tid,
+                "bob",
+                "projector",
+                "2022-12-27T08:00:00Z",
+                "2022-12-27T10:00:00Z",
+            ),
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(other.price, 0);
+
+    let moved = rsvp
+        .reschedule(
+            tid.clone(),
+            reserved.id.clone(),
+            "2022-12-26T18:00:00Z".parse().unwrap(),
+            "2022-12-26T20:00:00Z".parse().unwrap(),
+            Some(1),
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(moved.price, 2000);
+    assert_eq!(moved.version, 2);
+    assert_eq!(moved.extra_resource_ids, vec!["projector"]);
+    assert_eq!(
+        rsvp.get(tid.clone(), reserved.id.clone()).await.unwrap(),
+        moved
+    );
+
+    // the old window is free, the new one is checked like a new reservation
+    rsvp.reserve(
+        new_rsvp(
+            &tid,
+            "carol",
+            "studio",
+            "2022-12-26T08:00:00Z",
+            "2022-12-26T10:00:00Z",
+        ),
+        None,
+    )
+    .await
+    .unwrap();
+    let err = rsvp
+        .reschedule(
+            tid.clone(),
+            reserved.id.clone(),
+            "2022-12-27T09:00:00Z".parse().unwrap(),
+            "2022-12-27T11:00:00Z".parse().unwrap(),
+            None,
+            None,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(
+        conflicts(err),
+        vec![("projector".to_string(), "projector".to_string())]
+    );
+    // overlapping its own window is fine
+    rsvp.reschedule(
+        tid.clone(),
+        reserved.id.clone(),
+        "2022-12-26T19:00:00Z".parse().unwrap(),
+        "2022-12-26T21:00:00Z".parse().unwrap(),
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+
+    let err = rsvp
+        .reschedule(
+            tid.clone(),
+            reserved.id.clone(),
+            "2022-12-26T21:00:00Z".parse().unwrap(),
+            "2022-12-26T19:00:00Z".parse().unwrap(),
+            None,
+            None,
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, abi::Error::InvalidTime), "{:?}", err);
+    let err = rsvp
+        .reschedule(
+            tid.clone(),
+            reserved.id.clone(),
+            "2022-12-28T19:00:00Z".parse().unwrap(),
+            "2022-12-28T21:00:00Z".parse().unwrap(),
+            Some(1),
+            None,
+        )
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(
+            err,
+            abi::Error::PreconditionFailed {
+                expected: 1,
+                actual: 3
+            }
+        ),
+        "{:?}",
+        err
+    );
+
+    // blocked is no status that can be moved either
+    let mut blocked = new_rsvp(
+        &tid,
+        "admin",
+        "stage",
+        "2022-12-26T08:00:00Z",
+        "2022-12-26T10:00:00Z",
+    );
+    blocked.status = ReservationStatus::Blocked as i32;
+    let blocked = rsvp.reserve(blocked, None).await.unwrap();
+    let err = rsvp
+        .reschedule(
+            tid,
+            blocked.id,
+            "2022-12-28T19:00:00Z".parse().unwrap(),
+            "2022-12-28T21:00:00Z".parse().unwrap(),
+            None,
+            None,
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, abi::Error::NotFound), "{:?}", err);
+}
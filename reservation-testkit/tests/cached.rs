@@ -0,0 +1,6 @@
+use reservation::{CachedRsvp, InMemoryRsvp};
+
+#[tokio::test]
+async fn cached_rsvp_should_conform() {
+    reservation_testkit::run(&CachedRsvp::new(InMemoryRsvp::new())).await;
+}
@@ -0,0 +1,6 @@
+use reservation::InMemoryRsvp;
+
+#[tokio::test]
+async fn in_memory_rsvp_should_conform() {
+    reservation_testkit::run(&InMemoryRsvp::new()).await;
+}
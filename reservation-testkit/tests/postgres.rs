@@ -0,0 +1,6 @@
+use reservation::ReservationManager;
+
+#[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+async fn reservation_manager_should_conform() {
+    reservation_testkit::run(&ReservationManager::new(migrated_pool.clone())).await;
+}
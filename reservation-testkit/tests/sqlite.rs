@@ -0,0 +1,15 @@
+use reservation::SqliteRsvp;
+use sqlx::sqlite::SqlitePoolOptions;
+
+#[tokio::test]
+async fn sqlite_rsvp_should_conform() {
+    // every connection to :memory: opens a database of its own
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    let rsvp = SqliteRsvp::new(pool);
+    rsvp.migrate().await.unwrap();
+    reservation_testkit::run(&rsvp).await;
+}
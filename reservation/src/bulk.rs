@@ -0,0 +1,221 @@
+use crate::{BulkReserve, Rsvp};
+use abi::{convert_to_timestamp, Reservation, ReservationStatus};
+use chrono::{DateTime, FixedOffset, Utc};
+use serde::Deserialize;
+use std::io::Read;
+
+/// a row of a bulk import, times are RFC 3339
+#[derive(Debug, Deserialize)]
+struct Row {
+    user_id: String,
+    resource_id: String,
+    start: DateTime<FixedOffset>,
+    end: DateTime<FixedOffset>,
+    #[serde(default)]
+    note: String,
+    #[serde(default)]
+    extra_resource_ids: ResourceIds,
+    status: Option<Status>,
+}
+
+/// a list in JSON, separated by semicolons in CSV
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ResourceIds {
+    List(Vec<String>),
+    Joined(String),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Status {
+    Pending,
+    Confirmed,
+    Blocked,
+}
+
+impl Default for ResourceIds {
+    fn default() -> Self {
+        Self::List(vec![])
+    }
+}
+
+impl Row {
+    fn into_reservation(self, tid: &str) -> Reservation {
+        let extra_resource_ids = match self.extra_resource_ids {
+            ResourceIds::List(ids) => ids,
+            ResourceIds::Joined(ids) => ids
+                .split(';')
+                .map(str::trim)
+                .filter(|id| !id.is_empty())
+                .map(String::from)
+                .collect(),
+        };
+        let status = match self.status {
+            None | Some(Status::Pending) => ReservationStatus::Pending,
+            Some(Status::Confirmed) => ReservationStatus::Confirmed,
+            Some(Status::Blocked) => ReservationStatus::Blocked,
+        };
+        Reservation {
+            user_id: self.user_id,
+            status: status as i32,
+            resource_id: self.resource_id,
+            start: Some(convert_to_timestamp(self.start.with_timezone(&Utc))),
+            end: Some(convert_to_timestamp(self.end.with_timezone(&Utc))),
+            note: self.note,
+            tenant_id: tid.to_string(),
+            extra_resource_ids,
+            ..Default::default()
+        }
+    }
+}
+
+/// a parsed row by its number from 0, one that can't be read is an InvalidRow
+pub type ParsedRow = (usize, Result<Reservation, abi::Error>);
+
+/// read reservations of the tenant from CSV with a header row, the header isn't counted.
+/// columns are user_id, resource_id, start, end and optionally note, extra_resource_ids and status
+pub fn parse_csv(tid: &str, input: impl Read) -> Vec<ParsedRow> {
+    csv::Reader::from_reader(input)
+        .deserialize()
+        .enumerate()
+        .map(|(row, result)| {
+            let parsed = result
+                .map(|r: Row| r.into_reservation(tid))
+                .map_err(|e| invalid_row(row, e));
+            (row, parsed)
+        })
+        .collect()
+}
+
+/// read reservations of the tenant from JSON Lines with the same fields as the CSV columns.
+/// blank lines are skipped but still counted, rows are numbered by their line from 0
+pub fn parse_json_lines(tid: &str, input: &str) -> Vec<ParsedRow> {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(row, line)| {
+            let parsed = serde_json::from_str(line)
+                .map(|r: Row| r.into_reservation(tid))
+                .map_err(|e| invalid_row(row, e));
+            (row, parsed)
+        })
+        .collect()
+}
+
+/// bulk reserve the rows that could be read, rows that couldn't are rejected next to the invalid
+/// and conflicting ones. rejected rows keep the numbers the parser gave them, in order
+pub async fn bulk_reserve_rows(
+    rsvp: &(impl Rsvp + Sync),
+    rows: Vec<ParsedRow>,
+    dry_run: bool,
+) -> Result<BulkReserve, abi::Error> {
+    let mut numbers = vec![];
+    let mut rsvps = vec![];
+    let mut rejected = vec![];
+    for (row, parsed) in rows {
+        match parsed {
+            Ok(r) => {
+                numbers.push(row);
+                rsvps.push(r);
+            }
+            Err(e) => rejected.push((row, e)),
+        }
+    }
+
+    let mut result = rsvp.bulk_reserve(rsvps, dry_run).await?;
+    rejected.extend(
+        result
+            .rejected
+            .into_iter()
+            .map(|(index, e)| (numbers[index], e)),
+    );
+    rejected.sort_by_key(|(row, _)| *row);
+    result.rejected = rejected;
+    Ok(result)
+}
+
+fn invalid_row(row: usize, e: impl ToString) -> abi::Error {
+    abi::Error::InvalidRow {
+        row,
+        reason: e.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InMemoryRsvp;
+
+    #[test]
+    fn parse_csv_should_work() {
+        let input = "\
+user_id,resource_id,start,end,note,extra_resource_ids,status
+alice,room-1,2022-12-26T12:00:00-07:00,2022-12-26T14:00:00-07:00,\"sync, weekly\",projector;vc-kit,confirmed
+bob,room-2,2022-12-27T09:00:00Z,2022-12-27T10:00:00Z,,,
+";
+        let rsvps: Vec<_> = parse_csv("tenant_id", input.as_bytes())
+            .into_iter()
+            .map(|(_, parsed)| parsed.unwrap())
+            .collect();
+        assert_eq!(rsvps.len(), 2);
+        assert_eq!(rsvps[0].note, "sync, weekly");
+        assert_eq!(rsvps[0].extra_resource_ids, vec!["projector", "vc-kit"]);
+        assert_eq!(rsvps[0].status, ReservationStatus::Confirmed as i32);
+        assert_eq!(rsvps[0].start.as_ref().unwrap().seconds, 1672081200);
+        assert!(rsvps[1].extra_resource_ids.is_empty());
+        assert_eq!(rsvps[1].status, ReservationStatus::Pending as i32);
+        assert_eq!(rsvps[1].tenant_id, "tenant_id");
+    }
+
+    #[test]
+    fn parse_json_lines_should_report_bad_row() {
+        let input = r#"{"user_id":"alice","resource_id":"room-1","start":"2022-12-26T12:00:00Z","end":"2022-12-26T14:00:00Z","extra_resource_ids":["projector"]}
+
+{"user_id":"bob","resource_id":"room-1","start":"yesterday","end":"2022-12-26T14:00:00Z"}
+"#;
+        let rows = parse_json_lines("tenant_id", input);
+        assert_eq!(rows.len(), 2);
+        let (row, rsvp) = &rows[0];
+        assert_eq!(*row, 0);
+        assert_eq!(rsvp.as_ref().unwrap().extra_resource_ids, vec!["projector"]);
+        // the third line, the blank one is counted
+        let (row, err) = &rows[1];
+        assert_eq!(*row, 2);
+        assert!(matches!(err, Err(abi::Error::InvalidRow { row: 2, .. })));
+    }
+
+    #[tokio::test]
+    async fn bulk_reserve_rows_should_import_the_rows_that_parse() {
+        let input = "\
+user_id,resource_id,start,end
+alice,room-1,2022-12-26T09:00:00Z,2022-12-26T10:00:00Z
+bob,room-1,yesterday,2022-12-26T10:00:00Z
+carol,room-2
+dave,room-1,2022-12-26T09:30:00Z,2022-12-26T10:30:00Z
+erin,room-2,2022-12-26T09:00:00Z,2022-12-26T10:00:00Z
+";
+        let rsvp = InMemoryRsvp::new();
+        let rows = parse_csv("tenant_id", input.as_bytes());
+        let result = bulk_reserve_rows(&rsvp, rows, false).await.unwrap();
+
+        let users: Vec<_> = result.reserved.iter().map(|r| r.user_id.as_str()).collect();
+        assert_eq!(users, vec!["alice", "erin"]);
+        // a bad timestamp, a missing column and a conflict with alice, by their rows
+        let rejected: Vec<_> = result.rejected.iter().map(|(row, _)| *row).collect();
+        assert_eq!(rejected, vec![1, 2, 3]);
+        assert!(matches!(
+            result.rejected[0].1,
+            abi::Error::InvalidRow { row: 1, .. }
+        ));
+        assert!(matches!(
+            result.rejected[1].1,
+            abi::Error::InvalidRow { row: 2, .. }
+        ));
+        assert!(matches!(
+            result.rejected[2].1,
+            abi::Error::ConflictReservation(_)
+        ));
+    }
+}
@@ -0,0 +1,603 @@
+use crate::{BulkReserve, IdempotencyKey, ReservationId, ResourceId, Rsvp, TenantId, UserId};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use lru::LruCache;
+use prost::Message;
+use serde::Deserialize;
+use sqlx::{postgres::PgListener, types::Uuid, PgPool};
+use std::{
+    collections::HashMap,
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
+};
+
+/// reservations and resource timelines kept by default
+const DEFAULT_CAPACITY: usize = 10_000;
+/// distinct queries kept per resource timeline, older ones are dropped all at once
+const MAX_TIMELINE_QUERIES: usize = 64;
+
+/// `Rsvp` that keeps reservations read by `get` and the results of queries by resource in memory.
+/// writes through it invalidate what they touch right away, writes of other instances are
+/// picked up by `listen`. clones share the same cache
+#[derive(Debug, Clone)]
+pub struct CachedRsvp<T> {
+    inner: T,
+    cache: Arc<Mutex<Cache>>,
+}
+
+#[derive(Debug)]
+struct Cache {
+    reservations: LruCache<(TenantId, ReservationId), abi::Reservation>,
+    // results of the queries by resource, keyed by the encoded query
+    timelines: LruCache<(TenantId, ResourceId), HashMap<Vec<u8>, Vec<abi::Reservation>>>,
+    // bumped by every invalidation, results read before it are stale and not kept
+    epoch: u64,
+}
+
+/// payload of the reservation_update notification, the resources are left out when there are
+/// too many of them to fit
+#[derive(Debug, Deserialize)]
+struct Change {
+    tenant_id: TenantId,
+    id: ReservationId,
+    resource_ids: Option<Vec<ResourceId>>,
+}
+
+#[async_trait]
+impl<T: Rsvp + Send + Sync> Rsvp for CachedRsvp<T> {
+    async fn reserve(
+        &self,
+        rsvp: abi::Reservation,
+        key: Option<IdempotencyKey>,
+    ) -> Result<abi::Reservation, abi::Error> {
+        let rsvp = self.inner.reserve(rsvp, key).await?;
+        self.forget(&rsvp);
+        Ok(rsvp)
+    }
+
+    async fn bulk_reserve(
+        &self,
+        rsvps: Vec<abi::Reservation>,
+        dry_run: bool,
+    ) -> Result<BulkReserve, abi::Error> {
+        let result = self.inner.bulk_reserve(rsvps, dry_run).await?;
+        if !dry_run {
+            result.reserved.iter().for_each(|rsvp| self.forget(rsvp));
+        }
+        Ok(result)
+    }
+
+    async fn change_status(
+        &self,
+        tenant_id: TenantId,
+        id: ReservationId,
+        expected_version: Option<i64>,
+        key: Option<IdempotencyKey>,
+    ) -> Result<abi::Reservation, abi::Error> {
+        let rsvp = self
+            .inner
+            .change_status(tenant_id, id, expected_version, key)
+            .await?;
+        self.forget(&rsvp);
+        Ok(rsvp)
+    }
+
+    async fn update_note(
+        &self,
+        tenant_id: TenantId,
+        id: ReservationId,
+        note: String,
+        expected_version: Option<i64>,
+        key: Option<IdempotencyKey>,
+    ) -> Result<abi::Reservation, abi::Error> {
+        let rsvp = self
+            .inner
+            .update_note(tenant_id, id, note, expected_version, key)
+            .await?;
+        self.forget(&rsvp);
+        Ok(rsvp)
+    }
+
+    async fn delete(
+        &self,
+        tenant_id: TenantId,
+        id: ReservationId,
+        expected_version: Option<i64>,
+        key: Option<IdempotencyKey>,
+    ) -> Result<abi::Reservation, abi::Error> {
+        let rsvp = self
+            .inner
+            .delete(tenant_id, id, expected_version, key)
+            .await?;
+        self.forget(&rsvp);
+        Ok(rsvp)
+    }
+
+    async fn get(
+        &self,
+        tenant_id: TenantId,
+        id: ReservationId,
+    ) -> Result<abi::Reservation, abi::Error> {
+        // ids are kept in the form changes are announced in
+        let id = match Uuid::parse_str(&id) {
+            Ok(uuid) => uuid.to_string(),
+            Err(_) => return self.inner.get(tenant_id, id).await,
+        };
+        let key = (tenant_id, id);
+        let epoch = {
+            let mut cache = self.cache.lock().unwrap();
+            if let Some(rsvp) = cache.reservations.get(&key) {
+                return Ok(rsvp.clone());
+            }
+            cache.epoch
+        };
+
+        let rsvp = self.inner.get(key.0.clone(), key.1.clone()).await?;
+        let mut cache = self.cache.lock().unwrap();
+        if cache.epoch == epoch {
+            cache.reservations.put(key, rsvp.clone());
+        }
+        Ok(rsvp)
+    }
+
+    async fn query(
+        &self,
+        query: abi::ReservationQuery,
+    ) -> Result<Vec<abi::Reservation>, abi::Error> {
+        // only queries by resource are kept, they are the ones a change can be traced back to
+        if query.resource_id.is_empty() {
+            return self.inner.query(query).await;
+        }
+        let timeline = (query.tenant_id.clone(), query.resource_id.clone());
+        let key = query.encode_to_vec();
+        let epoch = {
+            let mut cache = self.cache.lock().unwrap();
+            if let Some(rsvps) = cache
+                .timelines
+                .get(&timeline)
+                .and_then(|queries| queries.get(&key))
+            {
+                return Ok(rsvps.clone());
+            }
+            cache.epoch
+        };
+
+        let rsvps = self.inner.query(query).await?;
+        let mut cache = self.cache.lock().unwrap();
+        if cache.epoch == epoch {
+            let queries = cache.timelines.get_or_insert_mut(timeline, HashMap::new);
+            if queries.len() >= MAX_TIMELINE_QUERIES {
+                queries.clear();
+            }
+            queries.insert(key, rsvps.clone());
+        }
+        Ok(rsvps)
+    }
+
+    async fn aggregate(
+        &self,
+        query: abi::ReservationQuery,
+        group_by: abi::AggregateGroup,
+        utc_offset_minutes: i32,
+    ) -> Result<Vec<abi::Aggregate>, abi::Error> {
+        self.inner
+            .aggregate(query, group_by, utc_offset_minutes)
+            .await
+    }
+
+    async fn link_resources(
+        &self,
+        tenant_id: TenantId,
+        parent_id: ResourceId,
+        child_id: ResourceId,
+    ) -> Result<(), abi::Error> {
+        self.inner
+            .link_resources(tenant_id, parent_id, child_id)
+            .await
+    }
+
+    async fn unlink_resources(
+        &self,
+        tenant_id: TenantId,
+        parent_id: ResourceId,
+        child_id: ResourceId,
+    ) -> Result<(), abi::Error> {
+        self.inner
+            .unlink_resources(tenant_id, parent_id, child_id)
+            .await
+    }
+
+    async fn update_resource(&self, resource: abi::Resource) -> Result<abi::Resource, abi::Error> {
+        self.inner.update_resource(resource).await
+    }
+
+    async fn approve(
+        &self,
+        tenant_id: TenantId,
+        id: ReservationId,
+        approver_id: UserId,
+        reason: String,
+        expected_version: Option<i64>,
+        key: Option<IdempotencyKey>,
+    ) -> Result<abi::Reservation, abi::Error> {
+        let rsvp = self
+            .inner
+            .approve(tenant_id, id, approver_id, reason, expected_version, key)
+            .await?;
+        self.forget(&rsvp);
+        Ok(rsvp)
+    }
+
+    async fn reject(
+        &self,
+        tenant_id: TenantId,
+        id: ReservationId,
+        approver_id: UserId,
+        reason: String,
+        expected_version: Option<i64>,
+        key: Option<IdempotencyKey>,
+    ) -> Result<abi::Reservation, abi::Error> {
+        let rsvp = self
+            .inner
+            .reject(tenant_id, id, approver_id, reason, expected_version, key)
+            .await?;
+        self.forget(&rsvp);
+        Ok(rsvp)
+    }
+
+    async fn check_in(
+        &self,
+        tenant_id: TenantId,
+        id: ReservationId,
+        expected_version: Option<i64>,
+        key: Option<IdempotencyKey>,
+    ) -> Result<abi::Reservation, abi::Error> {
+        let rsvp = self
+            .inner
+            .check_in(tenant_id, id, expected_version, key)
+            .await?;
+        self.forget(&rsvp);
+        Ok(rsvp)
+    }
+
+    async fn release_no_shows(&self) -> Result<Vec<abi::Reservation>, abi::Error> {
+        let rsvps = self.inner.release_no_shows().await?;
+        rsvps.iter().for_each(|rsvp| self.forget(rsvp));
+        Ok(rsvps)
+    }
+
+    async fn no_show_count(&self, tenant_id: TenantId, user_id: UserId) -> Result<i64, abi::Error> {
+        self.inner.no_show_count(tenant_id, user_id).await
+    }
+
+    async fn expire_idempotency_keys(&self, before: DateTime<Utc>) -> Result<u64, abi::Error> {
+        self.inner.expire_idempotency_keys(before).await
+    }
+
+    async fn quote(&self, rsvp: abi::Reservation) -> Result<i64, abi::Error> {
+        self.inner.quote(rsvp).await
+    }
+
+    async fn feed_token(&self, tenant_id: TenantId, user_id: UserId) -> Result<String, abi::Error> {
+        self.inner.feed_token(tenant_id, user_id).await
+    }
+
+    async fn reset_feed_token(
+        &self,
+        tenant_id: TenantId,
+        user_id: UserId,
+    ) -> Result<String, abi::Error> {
+        self.inner.reset_feed_token(tenant_id, user_id).await
+    }
+
+    async fn feed_owner(&self, token: String) -> Result<(TenantId, UserId), abi::Error> {
+        self.inner.feed_owner(token).await
+    }
+
+    async fn reschedule(
+        &self,
+        tenant_id: TenantId,
+        id: ReservationId,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        expected_version: Option<i64>,
+        key: Option<IdempotencyKey>,
+    ) -> Result<abi::Reservation, abi::Error> {
+        let rsvp = self
+            .inner
+            .reschedule(tenant_id, id, start, end, expected_version, key)
+            .await?;
+        self.forget(&rsvp);
+        Ok(rsvp)
+    }
+}
+
+impl<T> CachedRsvp<T> {
+    pub fn new(inner: T) -> Self {
+        Self::with_capacity(inner, DEFAULT_CAPACITY)
+    }
+
+    /// keep at most capacity reservations and as many resource timelines
+    pub fn with_capacity(inner: T, capacity: usize) -> Self {
+        let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+        Self {
+            inner,
+            cache: Arc::new(Mutex::new(Cache {
+                reservations: LruCache::new(capacity),
+                timelines: LruCache::new(capacity),
+                epoch: 0,
+            })),
+        }
+    }
+
+    /// drop the reservation and the timelines of the given resources
+    pub fn invalidate(&self, tenant_id: &str, id: &str, resource_ids: &[ResourceId]) {
+        let mut cache = self.cache.lock().unwrap();
+        cache.epoch += 1;
+        cache
+            .reservations
+            .pop(&(tenant_id.to_string(), id.to_string()));
+        for rid in resource_ids {
+            cache
+                .timelines
+                .pop(&(tenant_id.to_string(), rid.to_string()));
+        }
+    }
+
+    /// drop the reservation and the timelines of every resource of the tenant
+    pub fn invalidate_tenant(&self, tenant_id: &str, id: &str) {
+        let mut cache = self.cache.lock().unwrap();
+        cache.epoch += 1;
+        cache
+            .reservations
+            .pop(&(tenant_id.to_string(), id.to_string()));
+        let keys: Vec<_> = cache
+            .timelines
+            .iter()
+            .map(|(key, _)| key)
+            .filter(|(tid, _)| tid == tenant_id)
+            .cloned()
+            .collect();
+        for key in keys {
+            cache.timelines.pop(&key);
+        }
+    }
+
+    /// drop everything
+    pub fn clear(&self) {
+        let mut cache = self.cache.lock().unwrap();
+        cache.epoch += 1;
+        cache.reservations.clear();
+        cache.timelines.clear();
+    }
+
+    /// apply the reservation_update notifications sent for changes of every instance until the
+    /// connection fails. the cache is cleared whenever notifications may have been missed,
+    /// reads may be stale from a failure until this is called again
+    pub async fn listen(&self, pool: &PgPool) -> Result<(), abi::Error> {
+        let mut listener = PgListener::connect_with(pool).await?;
+        listener.listen("reservation_update").await?;
+        // changes made before listening weren't seen
+        self.clear();
+        loop {
+            let result = listener.try_recv().await;
+            match result {
+                Ok(Some(notification)) => {
+                    match serde_json::from_str::<Change>(notification.payload()) {
+                        Ok(Change {
+                            tenant_id,
+                            id,
+                            resource_ids: Some(resource_ids),
+                        }) => self.invalidate(&tenant_id, &id, &resource_ids),
+                        Ok(Change { tenant_id, id, .. }) => self.invalidate_tenant(&tenant_id, &id),
+                        // a notification without details, e.g. from an older trigger
+                        Err(_) => self.clear(),
+                    }
+                }
+                // the connection was lost and reestablished
+                Ok(None) => self.clear(),
+                Err(e) => {
+                    self.clear();
+                    return Err(e.into());
+                }
+            }
+        }
+    }
+
+    fn forget(&self, rsvp: &abi::Reservation) {
+        let mut resource_ids = vec![rsvp.resource_id.clone()];
+        resource_ids.extend(rsvp.extra_resource_ids.iter().cloned());
+        self.invalidate(&rsvp.tenant_id, &rsvp.id, &resource_ids);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{InMemoryRsvp, ReservationManager};
+    use abi::{ReservationQuery, ReservationStatus};
+    use std::time::Duration;
+
+    fn rsvp(uid: &str, rid: &str, start: &str, end: &str) -> abi::Reservation {
+        abi::Reservation::new_pending(
+            "tenant_id",
+            uid,
+            rid,
+            start.parse().unwrap(),
+            end.parse().unwrap(),
+            "note",
+        )
+    }
+
+    fn december(rid: &str) -> ReservationQuery {
+        ReservationQuery::new(
+            "tenant_id",
+            "",
+            rid,
+            "2022-12-01T00:00:00Z".parse().unwrap(),
+            "2023-01-01T00:00:00Z".parse().unwrap(),
+            ReservationStatus::Pending,
+            1,
+            10,
+            false,
+        )
+    }
+
+    #[tokio::test]
+    async fn reads_should_be_kept_until_invalidated() {
+        // clones of the in-memory store share their reservations, like two instances sharing a database
+        let other = InMemoryRsvp::new();
+        let cached = CachedRsvp::new(other.clone());
+        let reserved = cached
+            .reserve(
+                rsvp(
+                    "alice",
+                    "room",
+                    "2022-12-25T12:00:00Z",
+                    "2022-12-25T13:00:00Z",
+                ),
+                None,
+            )
+            .await
+            .unwrap();
+        let id = reserved.id.clone();
+        assert_eq!(
+            cached.get("tenant_id".into(), id.clone()).await.unwrap(),
+            reserved
+        );
+        assert_eq!(
+            cached.query(december("room")).await.unwrap(),
+            vec![reserved]
+        );
+
+        // writes of others aren't seen until they're announced
+        other
+            .update_note("tenant_id".into(), id.clone(), "moved".into(), None, None)
+            .await
+            .unwrap();
+        let second = other
+            .reserve(
+                rsvp(
+                    "bob",
+                    "room",
+                    "2022-12-26T12:00:00Z",
+                    "2022-12-26T13:00:00Z",
+                ),
+                None,
+            )
+            .await
+            .unwrap();
+        let upper = id.to_uppercase();
+        assert_eq!(
+            cached
+                .get("tenant_id".into(), upper.clone())
+                .await
+                .unwrap()
+                .note,
+            "note"
+        );
+        assert_eq!(cached.query(december("room")).await.unwrap().len(), 1);
+
+        cached.invalidate("tenant_id", &id, &["room".to_string()]);
+        assert_eq!(
+            cached.get("tenant_id".into(), upper).await.unwrap().note,
+            "moved"
+        );
+        assert_eq!(cached.query(december("room")).await.unwrap().len(), 2);
+
+        // writes through the cache are seen right away
+        let confirmed = cached
+            .change_status("tenant_id".into(), second.id.clone(), None, None)
+            .await
+            .unwrap();
+        assert_eq!(
+            cached.get("tenant_id".into(), second.id).await.unwrap(),
+            confirmed
+        );
+        assert_eq!(cached.query(december("room")).await.unwrap().len(), 1);
+
+        // other tenants don't share entries
+        let err = cached.get("other".into(), id).await.unwrap_err();
+        assert!(matches!(err, abi::Error::NotFound));
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn changes_of_other_instances_should_invalidate() {
+        let cached = CachedRsvp::new(ReservationManager::new(migrated_pool.clone()));
+        let other = ReservationManager::new(migrated_pool.clone());
+        let listener = {
+            let cached = cached.clone();
+            let pool = migrated_pool.clone();
+            tokio::spawn(async move { cached.listen(&pool).await })
+        };
+
+        let reserved = other
+            .reserve(
+                rsvp(
+                    "alice",
+                    "room",
+                    "2022-12-25T12:00:00Z",
+                    "2022-12-25T13:00:00Z",
+                ),
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            cached
+                .get("tenant_id".into(), reserved.id.clone())
+                .await
+                .unwrap(),
+            reserved
+        );
+        assert_eq!(cached.query(december("room")).await.unwrap().len(), 1);
+
+        // a note changes no status, it's announced all the same
+        other
+            .update_note(
+                "tenant_id".into(),
+                reserved.id.clone(),
+                "moved".into(),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        other
+            .reserve(
+                rsvp(
+                    "bob",
+                    "room",
+                    "2022-12-26T12:00:00Z",
+                    "2022-12-26T13:00:00Z",
+                ),
+                None,
+            )
+            .await
+            .unwrap();
+        // too many resources to list in the notification, it still goes through
+        let mut crowded = rsvp(
+            "carol",
+            "room",
+            "2022-12-27T12:00:00Z",
+            "2022-12-27T13:00:00Z",
+        );
+        crowded.extra_resource_ids = (0..150).map(|i| format!("{:064}", i)).collect();
+        other.reserve(crowded, None).await.unwrap();
+        let mut seen = false;
+        for _ in 0..50 {
+            let rsvp = cached
+                .get("tenant_id".into(), reserved.id.clone())
+                .await
+                .unwrap();
+            let found = cached.query(december("room")).await.unwrap();
+            seen = rsvp.note == "moved" && found.len() == 3;
+            if seen {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        listener.abort();
+        assert!(seen, "changes of the other instance were never seen");
+    }
+}
@@ -0,0 +1,492 @@
+use crate::{ResourceId, TenantId, UserId};
+use abi::{AggregateGroup, Cursor, ReservationStatus, TimeMatch};
+use chrono::{DateTime, FixedOffset, Utc};
+use sqlx::{postgres::types::PgRange, Postgres, QueryBuilder};
+use std::ops::Bound;
+
+/// how the timespan of a reservation is matched against a window
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimePredicate {
+    /// shares at least one instant with the window
+    Overlaps(PgRange<DateTime<Utc>>),
+    /// lies completely inside the window
+    ContainedIn(PgRange<DateTime<Utc>>),
+    /// covers the whole window
+    Contains(PgRange<DateTime<Utc>>),
+    /// starts inside the window, wherever it ends
+    StartsWithin(PgRange<DateTime<Utc>>),
+}
+
+impl TimePredicate {
+    pub fn from_query(query: &abi::ReservationQuery) -> Self {
+        let window = query.get_timespan();
+        match TimeMatch::from_i32(query.time_match).unwrap_or(TimeMatch::ContainedIn) {
+            TimeMatch::ContainedIn => Self::ContainedIn(window),
+            TimeMatch::Overlaps => Self::Overlaps(window),
+            TimeMatch::Contains => Self::Contains(window),
+            TimeMatch::StartsWithin => Self::StartsWithin(window),
+        }
+    }
+
+    pub fn window(&self) -> &PgRange<DateTime<Utc>> {
+        match self {
+            Self::Overlaps(window)
+            | Self::ContainedIn(window)
+            | Self::Contains(window)
+            | Self::StartsWithin(window) => window,
+        }
+    }
+}
+
+/// conditions on the reservations of a tenant, unset ones match everything.
+/// values are always bound as parameters, so the statement can be prepared and cached
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReservationFilter {
+    tenant_id: TenantId,
+    user_id: Option<UserId>,
+    resource_id: Option<ResourceId>,
+    statuses: Vec<ReservationStatus>,
+    time: Option<TimePredicate>,
+    note: Option<String>,
+    search: Option<String>,
+    language: Option<String>,
+    by_relevance: bool,
+    cursor: Option<Cursor>,
+    desc: bool,
+    limit: Option<i64>,
+    offset: i64,
+}
+
+impl ReservationFilter {
+    pub fn new(tenant_id: impl Into<TenantId>) -> Self {
+        Self {
+            tenant_id: tenant_id.into(),
+            user_id: None,
+            resource_id: None,
+            statuses: vec![],
+            time: None,
+            note: None,
+            search: None,
+            language: None,
+            by_relevance: false,
+            cursor: None,
+            desc: false,
+            limit: None,
+            offset: 0,
+        }
+    }
+
+    /// the filter of a validated query
+    pub fn from_query(query: &abi::ReservationQuery) -> Result<Self, abi::Error> {
+        let mut filter = Self::new(&query.tenant_id)
+            .with_statuses(query.get_statuses())
+            .with_time(TimePredicate::from_query(query))
+            .with_desc(query.desc)
+            .with_limit(query.page_size.into())
+            .with_offset(query.offset());
+        if !query.user_id.is_empty() {
+            filter = filter.with_user(&query.user_id);
+        }
+        if !query.resource_id.is_empty() {
+            filter = filter.with_resource(&query.resource_id);
+        }
+        let terms = query.get_search_terms();
+        if !terms.is_empty() {
+            filter = filter
+                .with_search(terms.join(" "))
+                .with_relevance(query.by_relevance);
+        }
+        if !query.search_language.is_empty() {
+            filter = filter.with_search_language(&query.search_language);
+        }
+        if let Some(cursor) = query.get_cursor()? {
+            filter = filter.with_cursor(cursor);
+        }
+        Ok(filter)
+    }
+
+    pub fn with_user(mut self, user_id: impl Into<UserId>) -> Self {
+        self.user_id = Some(user_id.into());
+        self
+    }
+
+    /// reservations holding the resource, whether as their main one or an extra one
+    pub fn with_resource(mut self, resource_id: impl Into<ResourceId>) -> Self {
+        self.resource_id = Some(resource_id.into());
+        self
+    }
+
+    /// reservations in any of the statuses, an empty set means any status
+    pub fn with_statuses(mut self, statuses: impl IntoIterator<Item = ReservationStatus>) -> Self {
+        self.statuses = statuses.into_iter().collect();
+        self
+    }
+
+    pub fn with_time(mut self, time: TimePredicate) -> Self {
+        self.time = Some(time);
+        self
+    }
+
+    /// notes containing the text, ignoring case
+    pub fn with_note(mut self, text: impl Into<String>) -> Self {
+        self.note = Some(text.into());
+        self
+    }
+
+    /// full text search over notes, every word must appear
+    pub fn with_search(mut self, text: impl Into<String>) -> Self {
+        self.search = Some(text.into());
+        self
+    }
+
+    /// text search configuration like english, words are matched as they are without one
+    pub fn with_search_language(mut self, language: impl Into<String>) -> Self {
+        self.language = Some(language.into());
+        self
+    }
+
+    /// order by how well the note matches the search before the start
+    pub fn with_relevance(mut self, by_relevance: bool) -> Self {
+        self.by_relevance = by_relevance;
+        self
+    }
+
+    /// reservations after the cursor in the order of the filter
+    pub fn with_cursor(mut self, cursor: Cursor) -> Self {
+        self.cursor = Some(cursor);
+        self
+    }
+
+    pub fn with_desc(mut self, desc: bool) -> Self {
+        self.desc = desc;
+        self
+    }
+
+    pub fn with_limit(mut self, limit: i64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn with_offset(mut self, offset: i64) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// the select statement of the matching reservations, ordered by (start, id)
+    pub fn build(&self) -> QueryBuilder<'_, Postgres> {
+        let mut qb = QueryBuilder::new("SELECT * FROM rsvp.reservations WHERE ");
+        self.push_conditions(&mut qb);
+
+        if let Some(cursor) = &self.cursor {
+            qb.push(if self.desc {
+                " AND (lower(timespan), id) < ("
+            } else {
+                " AND (lower(timespan), id) > ("
+            });
+            qb.push_bind(cursor.start);
+            qb.push(", ");
+            qb.push_bind(cursor.id);
+            qb.push(")");
+        }
+
+        qb.push(" ORDER BY ");
+        if let (Some(search), true) = (&self.search, self.by_relevance) {
+            qb.push("ts_rank(to_tsvector(");
+            self.push_search_config(&mut qb);
+            qb.push(", note), plainto_tsquery(");
+            self.push_search_config(&mut qb);
+            qb.push(", ");
+            qb.push_bind(search);
+            qb.push(")) DESC, ");
+        }
+        let order = if self.desc { "DESC" } else { "ASC" };
+        qb.push(format!("lower(timespan) {order}, id {order}"));
+        if let Some(limit) = self.limit {
+            qb.push(" LIMIT ");
+            qb.push_bind(limit);
+        }
+        if self.offset > 0 {
+            qb.push(" OFFSET ");
+            qb.push_bind(self.offset);
+        }
+        qb
+    }
+
+    /// counts and booked time inside the window of the matching reservations, ordered by group key.
+    /// cursor, order and paging are ignored
+    pub fn build_aggregate(
+        &self,
+        group_by: AggregateGroup,
+        utc_offset: FixedOffset,
+    ) -> QueryBuilder<'_, Postgres> {
+        // keys are ordered by their bytes, like the other backends do
+        let mut qb = QueryBuilder::new("SELECT (");
+        match group_by {
+            AggregateGroup::None => qb.push("''"),
+            AggregateGroup::Resource => qb.push("held.rid"),
+            AggregateGroup::User => qb.push("user_id"),
+            AggregateGroup::Status => qb.push("status::text"),
+            AggregateGroup::Day => {
+                qb.push("to_char(lower(timespan) AT TIME ZONE 'UTC' + ");
+                qb.push_bind(utc_offset.local_minus_utc());
+                qb.push(" * interval '1 second', 'YYYY-MM-DD')")
+            }
+        };
+
+        let window = match &self.time {
+            Some(time) => time.window().clone(),
+            None => PgRange {
+                start: Bound::Unbounded,
+                end: Bound::Unbounded,
+            },
+        };
+        qb.push(
+            ") COLLATE \"C\" AS key, count(*) AS count, COALESCE(floor(sum(EXTRACT(EPOCH FROM upper(timespan * ",
+        );
+        qb.push_bind(window.clone());
+        qb.push(") - lower(timespan * ");
+        qb.push_bind(window);
+        qb.push(")))), 0)::bigint AS seconds FROM rsvp.reservations");
+        if group_by == AggregateGroup::Resource {
+            qb.push(", unnest(array_prepend(resource_id, extra_resource_ids)) AS held(rid)");
+        }
+        qb.push(" WHERE ");
+        self.push_conditions(&mut qb);
+        // a query for one resource only counts that one, not the others its reservations hold
+        if let (AggregateGroup::Resource, Some(resource_id)) = (group_by, &self.resource_id) {
+            qb.push(" AND held.rid = ");
+            qb.push_bind(resource_id);
+        }
+        if group_by != AggregateGroup::None {
+            qb.push(" GROUP BY 1 ORDER BY 1");
+        }
+        qb
+    }
+
+    /// the conditions on rsvp.reservations, without ordering or paging
+    pub fn push_conditions<'a>(&'a self, qb: &mut QueryBuilder<'a, Postgres>) {
+        qb.push("tenant_id = ");
+        qb.push_bind(&self.tenant_id);
+
+        if let Some(user_id) = &self.user_id {
+            qb.push(" AND user_id = ");
+            qb.push_bind(user_id);
+        }
+
+        // rejected reservations hold no resources, so they aren't found by resource
+        if let Some(resource_id) = &self.resource_id {
+            qb.push(" AND id IN (SELECT reservation_id FROM rsvp.reservation_resources WHERE tenant_id = ");
+            qb.push_bind(&self.tenant_id);
+            qb.push(" AND resource_id = ");
+            qb.push_bind(resource_id);
+            qb.push(")");
+        }
+
+        if !self.statuses.is_empty() {
+            let statuses: Vec<String> = self.statuses.iter().map(|s| s.to_string()).collect();
+            qb.push(" AND status = ANY(");
+            qb.push_bind(statuses);
+            qb.push("::rsvp.reservation_status[])");
+        }
+
+        if let Some(time) = &self.time {
+            let (op, window) = match time {
+                TimePredicate::Overlaps(window) => (" AND timespan && ", window),
+                TimePredicate::ContainedIn(window) => (" AND timespan <@ ", window),
+                TimePredicate::Contains(window) => (" AND timespan @> ", window),
+                TimePredicate::StartsWithin(window) => (" AND lower(timespan) <@ ", window),
+            };
+            qb.push(op);
+            qb.push_bind(window.clone());
+        }
+
+        if let Some(text) = &self.note {
+            qb.push(" AND note ILIKE ");
+            qb.push_bind(format!("%{}%", escape_like(text)));
+        }
+
+        if let Some(search) = &self.search {
+            qb.push(" AND to_tsvector(");
+            self.push_search_config(qb);
+            qb.push(", note) @@ plainto_tsquery(");
+            self.push_search_config(qb);
+            qb.push(", ");
+            qb.push_bind(search);
+            qb.push(")");
+        }
+    }
+
+    /// the default configuration is inlined, so the note index can serve it
+    fn push_search_config<'a>(&'a self, qb: &mut QueryBuilder<'a, Postgres>) {
+        match &self.language {
+            Some(language) => {
+                qb.push_bind(language);
+                qb.push("::regconfig");
+            }
+            None => {
+                qb.push("'simple'");
+            }
+        }
+    }
+}
+
+/// match the text literally in a LIKE pattern
+fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ReservationManager, Rsvp};
+    use abi::Reservation;
+
+    fn december() -> PgRange<DateTime<Utc>> {
+        let start: DateTime<Utc> = "2022-12-01T00:00:00Z".parse().unwrap();
+        let end: DateTime<Utc> = "2023-01-01T00:00:00Z".parse().unwrap();
+        (start..end).into()
+    }
+
+    #[test]
+    fn values_should_be_bound_not_inlined() {
+        let filter = ReservationFilter::new("tenant'; DROP TABLE rsvp.reservations; --")
+            .with_user("alice")
+            .with_resource("room")
+            .with_statuses([ReservationStatus::Pending, ReservationStatus::Confirmed])
+            .with_time(TimePredicate::Overlaps(december()))
+            .with_note("50%")
+            .with_limit(10);
+        let qb = filter.build();
+        assert_eq!(
+            qb.sql(),
+            "SELECT * FROM rsvp.reservations WHERE tenant_id = $1 AND user_id = $2 \
+            AND id IN (SELECT reservation_id FROM rsvp.reservation_resources WHERE tenant_id = $3 AND resource_id = $4) \
+            AND status = ANY($5::rsvp.reservation_status[]) AND timespan && $6 AND note ILIKE $7 \
+            ORDER BY lower(timespan) ASC, id ASC LIMIT $8"
+        );
+        assert_eq!(escape_like("50%_\\"), "50\\%\\_\\\\");
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn filters_should_combine() {
+        let manager = ReservationManager::new(migrated_pool.clone());
+        let mut rsvps = vec![];
+        for (uid, start, end, note) in [
+            (
+                "alice",
+                "2022-11-30T22:00:00Z",
+                "2022-12-01T02:00:00Z",
+                "Team offsite",
+            ),
+            (
+                "alice",
+                "2022-12-10T10:00:00Z",
+                "2022-12-10T12:00:00Z",
+                "team sync",
+            ),
+            (
+                "bob",
+                "2022-12-11T10:00:00Z",
+                "2022-12-11T12:00:00Z",
+                "Team 50% review",
+            ),
+            (
+                "alice",
+                "2022-12-12T10:00:00Z",
+                "2022-12-12T12:00:00Z",
+                "1:1",
+            ),
+        ] {
+            let rsvp = Reservation::new_pending(
+                "tenant_id",
+                uid,
+                "room",
+                start.parse().unwrap(),
+                end.parse().unwrap(),
+                note,
+            );
+            rsvps.push(manager.reserve(rsvp, None).await.unwrap());
+        }
+        let confirmed = manager
+            .change_status("tenant_id".into(), rsvps[3].id.clone(), None, None)
+            .await
+            .unwrap();
+
+        let filter = ReservationFilter::new("tenant_id")
+            .with_user("alice")
+            .with_note("TEAM")
+            .with_time(TimePredicate::Overlaps(december()));
+        assert_eq!(
+            manager.filter(&filter).await.unwrap(),
+            vec![rsvps[0].clone(), rsvps[1].clone()]
+        );
+        let filter = filter.with_time(TimePredicate::ContainedIn(december()));
+        assert_eq!(
+            manager.filter(&filter).await.unwrap(),
+            vec![rsvps[1].clone()]
+        );
+
+        let filter = ReservationFilter::new("tenant_id")
+            .with_resource("room")
+            .with_statuses([ReservationStatus::Confirmed, ReservationStatus::Pending])
+            .with_time(TimePredicate::StartsWithin(december()))
+            .with_desc(true);
+        assert_eq!(
+            manager.filter(&filter).await.unwrap(),
+            vec![confirmed.clone(), rsvps[2].clone(), rsvps[1].clone()]
+        );
+        let filter = filter.with_statuses([ReservationStatus::Confirmed]);
+        assert_eq!(manager.filter(&filter).await.unwrap(), vec![confirmed]);
+
+        // like patterns in the text are matched literally
+        let filter = ReservationFilter::new("tenant_id").with_note("%");
+        assert_eq!(
+            manager.filter(&filter).await.unwrap(),
+            vec![rsvps[2].clone()]
+        );
+        let filter = ReservationFilter::new("other_tenant_id");
+        assert!(manager.filter(&filter).await.unwrap().is_empty());
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn search_language_should_stem_words() {
+        let manager = ReservationManager::new(migrated_pool.clone());
+        let rsvp = Reservation::new_pending(
+            "tenant_id",
+            "alice",
+            "room",
+            "2022-12-10T10:00:00Z".parse().unwrap(),
+            "2022-12-10T12:00:00Z".parse().unwrap(),
+            "Planning the budgets",
+        );
+        let rsvp = manager.reserve(rsvp, None).await.unwrap();
+
+        let filter = ReservationFilter::new("tenant_id").with_search("plans budget");
+        assert!(manager.filter(&filter).await.unwrap().is_empty());
+        let filter = filter.with_search_language("english");
+        assert_eq!(manager.filter(&filter).await.unwrap(), vec![rsvp]);
+
+        // languages postgres doesn't come with are rejected before reaching it
+        let query = abi::ReservationQuery {
+            search_language: "klingon".into(),
+            ..abi::ReservationQuery::new(
+                "tenant_id",
+                "",
+                "",
+                "2022-12-01T00:00:00Z".parse().unwrap(),
+                "2023-01-01T00:00:00Z".parse().unwrap(),
+                ReservationStatus::Pending,
+                1,
+                10,
+                false,
+            )
+            .with_search("plans budget")
+        };
+        assert!(matches!(
+            manager.query(query).await.unwrap_err(),
+            abi::Error::InvalidSearchLanguage(_)
+        ));
+    }
+}
@@ -0,0 +1,117 @@
+use crate::{fingerprint, Rsvp, TenantId, UserId};
+
+/// outcome of importing a calendar, events are identified by their UID
+#[derive(Debug, Default)]
+pub struct IcsImport {
+    pub reserved: Vec<abi::Reservation>,
+    pub conflicted: Vec<(String, abi::Error)>,
+    /// events that can't be read or booked as they are, like those in a time zone
+    pub unsupported: Vec<(String, abi::Error)>,
+    /// events imported before whose details have changed since, they aren't moved
+    pub changed: Vec<(String, abi::Error)>,
+}
+
+/// query reservations and export them as an iCalendar
+pub async fn export_ics(
+    rsvp: &(impl Rsvp + Sync),
+    query: abi::ReservationQuery,
+) -> Result<String, abi::Error> {
+    let rsvps = rsvp.query(query).await?;
+    Ok(abi::to_ics(&rsvps))
+}
+
+/// reserve every event of an iCalendar for the user, events that conflict or can't be booked
+/// are reported and skipped. the user and UID make the idempotency key, importing the same
+/// calendar again doesn't book twice and another user importing the same invite books their own
+pub async fn import_ics(
+    rsvp: &(impl Rsvp + Sync),
+    tenant_id: TenantId,
+    user_id: UserId,
+    ics: &str,
+) -> Result<IcsImport, abi::Error> {
+    if tenant_id.is_empty() {
+        return Err(abi::Error::InvalidTenantId(tenant_id));
+    }
+    if user_id.is_empty() {
+        return Err(abi::Error::InvalidUserId(user_id));
+    }
+
+    let mut result = IcsImport::default();
+    for (uid, event) in abi::parse_ics(&tenant_id, &user_id, ics)? {
+        let event = match event.and_then(|event| event.validate().map(|_| event)) {
+            Ok(event) => event,
+            Err(e) => {
+                result.unsupported.push((uid, e));
+                continue;
+            }
+        };
+        match rsvp.reserve(event, Some(import_key(&user_id, &uid))).await {
+            Ok(reserved) => result.reserved.push(reserved),
+            Err(e @ (abi::Error::ConflictReservation(_) | abi::Error::ConflictReservations(_))) => {
+                result.conflicted.push((uid, e))
+            }
+            Err(e @ abi::Error::IdempotencyKeyReused(_)) => result.changed.push((uid, e)),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(result)
+}
+
+/// idempotency key of the user's import of an event, hashed so long UIDs fit
+fn import_key(user_id: &str, uid: &str) -> String {
+    let digest = fingerprint(&[user_id.as_bytes(), uid.as_bytes()]);
+    let hex: String = digest[..16].iter().map(|b| format!("{:02x}", b)).collect();
+    format!("ics:{}", hex)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ReservationManager;
+
+    fn calendar(uid: &str, start: &str, end: &str) -> String {
+        format!(
+            "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:{}\r\nDTSTART:{}\r\nDTEND:{}\r\nLOCATION:room-1\r\nEND:VEVENT\r\n\
+            BEGIN:VEVENT\r\nUID:tz\r\nDTSTART;TZID=Europe/Paris:20221226T100000\r\nDTEND;TZID=Europe/Paris:20221226T110000\r\nLOCATION:room-2\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n",
+            uid, start, end
+        )
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn import_ics_should_book_per_user_and_report_the_rest() {
+        let rsvp = ReservationManager::new(migrated_pool.clone());
+        let ics = calendar("invite@example.com", "20221226T090000Z", "20221226T100000Z");
+        let imported = import_ics(&rsvp, "tenant".into(), "alice".into(), &ics)
+            .await
+            .unwrap();
+        assert_eq!(imported.reserved.len(), 1);
+        assert_eq!(imported.unsupported.len(), 1);
+        assert_eq!(imported.unsupported[0].0, "tz");
+
+        // a retry replays the booking
+        let again = import_ics(&rsvp, "tenant".into(), "alice".into(), &ics)
+            .await
+            .unwrap();
+        assert_eq!(again.reserved, imported.reserved);
+
+        // another user isn't handed alice's booking, the invite is booked for them or conflicts
+        let other = import_ics(&rsvp, "tenant".into(), "bob".into(), &ics)
+            .await
+            .unwrap();
+        assert!(other.reserved.is_empty());
+        assert_eq!(other.conflicted.len(), 1);
+        assert_eq!(other.conflicted[0].0, "invite@example.com");
+
+        // the event moved since alice imported it
+        let moved = calendar("invite@example.com", "20221227T090000Z", "20221227T100000Z");
+        let moved = import_ics(&rsvp, "tenant".into(), "alice".into(), &moved)
+            .await
+            .unwrap();
+        assert!(moved.reserved.is_empty());
+        assert_eq!(moved.changed.len(), 1);
+        assert!(matches!(
+            moved.changed[0].1,
+            abi::Error::IdempotencyKeyReused(_)
+        ));
+    }
+}
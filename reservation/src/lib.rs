@@ -1,33 +1,280 @@
+mod bulk;
+mod cache;
+mod filter;
+mod ical;
 mod manager;
+mod memory;
+mod outbox;
+mod reminder;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+mod utilization;
+mod webhook;
 use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use sha2::{Digest, Sha256};
+
+pub use bulk::{bulk_reserve_rows, parse_csv, parse_json_lines, ParsedRow};
+pub use cache::CachedRsvp;
+pub use filter::{ReservationFilter, TimePredicate};
+pub use ical::{export_ics, import_ics, IcsImport};
+pub use manager::ReservationManager;
+pub use memory::InMemoryRsvp;
+pub use outbox::{Outbox, OutboxEvent};
+pub use reminder::{Reminder, Reminders};
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteRsvp;
+pub use utilization::{utilization, utilization_csv, utilization_json};
+pub use webhook::{Delivery, RetryPolicy, Webhooks};
+
 pub type ReservationId = String;
+pub type TenantId = String;
 pub type UserId = String;
 pub type ResourceId = String;
+pub type IdempotencyKey = String;
+pub type WebhookId = String;
+
+/// reservations can be checked in from `before` the start until `after` it,
+/// those not checked in by then are no-shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CheckInPolicy {
+    pub before: Duration,
+    pub after: Duration,
+}
+
+impl Default for CheckInPolicy {
+    fn default() -> Self {
+        Self {
+            before: Duration::minutes(15),
+            after: Duration::minutes(15),
+        }
+    }
+}
+
+/// how long before the start of a reservation its user is reminded, once for each lead time
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReminderPolicy {
+    pub before: Vec<Duration>,
+}
+
+impl Default for ReminderPolicy {
+    fn default() -> Self {
+        Self {
+            before: vec![Duration::hours(24), Duration::minutes(15)],
+        }
+    }
+}
+
+/// longest idempotency key the backends store, in characters
+const MAX_IDEMPOTENCY_KEY_LEN: usize = 64;
+
+/// reject a key that couldn't be stored before anything is claimed with it
+pub(crate) fn validate_key(key: &str) -> Result<(), abi::Error> {
+    if key.chars().count() > MAX_IDEMPOTENCY_KEY_LEN {
+        return Err(abi::Error::InvalidIdempotencyKey(key.to_string()));
+    }
+    Ok(())
+}
+
+/// digest of the arguments of a mutating call, stored with its idempotency key
+/// so a key sent again with other arguments is rejected instead of replayed
+pub(crate) fn fingerprint(parts: &[&[u8]]) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        // length prefixed, ("ab", "c") and ("a", "bc") don't collide
+        hasher.update((part.len() as u64).to_be_bytes());
+        hasher.update(part);
+    }
+    hasher.finalize().to_vec()
+}
+
+/// the expected version of a call as a part of its fingerprint, none differs from every version
+pub(crate) fn version_part(expected_version: Option<i64>) -> Vec<u8> {
+    expected_version
+        .map(|version| version.to_be_bytes().to_vec())
+        .unwrap_or_default()
+}
 
+/// outcome of a bulk reservation, rows that failed are identified by their index
+#[derive(Debug, Default)]
+pub struct BulkReserve {
+    pub reserved: Vec<abi::Reservation>,
+    pub rejected: Vec<(usize, abi::Error)>,
+}
+
+impl From<BulkReserve> for abi::BulkReserveResponse {
+    fn from(result: BulkReserve) -> Self {
+        Self {
+            reservations: result.reserved,
+            failures: result
+                .rejected
+                .into_iter()
+                .map(|(row, e)| abi::BulkReserveFailure {
+                    row: row as i32,
+                    reason: e.to_string(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// mutating methods accept an optional idempotency key, a retry with the same key and arguments returns
+/// the first result until the key expires, the key sent with other arguments fails with IdempotencyKeyReused.
+/// methods taking an expected version fail with PreconditionFailed if the reservation has changed.
+/// reservations are always looked up within the given tenant, others are reported as not found
 #[async_trait]
 pub trait Rsvp {
     /// generate a reservation
-    async fn reserve(&self, rsvp: abi::Reservation) -> Result<abi::Reservation, abi::Error>;
+    async fn reserve(
+        &self,
+        rsvp: abi::Reservation,
+        key: Option<IdempotencyKey>,
+    ) -> Result<abi::Reservation, abi::Error>;
+
+    /// reserve many reservations in one transaction, invalid and conflicting rows are reported and skipped.
+    /// rows conflict with the database and with earlier rows, a dry run reports the same but writes nothing.
+    /// any other error fails the whole batch
+    async fn bulk_reserve(
+        &self,
+        rsvps: Vec<abi::Reservation>,
+        dry_run: bool,
+    ) -> Result<BulkReserve, abi::Error>;
 
     /// if current status is pending, change it to confirmed
-    async fn change_status(&self, id: ReservationId) -> Result<abi::Reservation, abi::Error>;
+    async fn change_status(
+        &self,
+        tenant_id: TenantId,
+        id: ReservationId,
+        expected_version: Option<i64>,
+        key: Option<IdempotencyKey>,
+    ) -> Result<abi::Reservation, abi::Error>;
 
     /// update note
     async fn update_note(
         &self,
+        tenant_id: TenantId,
         id: ReservationId,
         note: String,
+        expected_version: Option<i64>,
+        key: Option<IdempotencyKey>,
     ) -> Result<abi::Reservation, abi::Error>;
 
-    /// delete reservation
-    async fn delete(&self, id: ReservationId) -> Result<(), abi::Error>;
+    /// delete reservation, return the deleted one
+    async fn delete(
+        &self,
+        tenant_id: TenantId,
+        id: ReservationId,
+        expected_version: Option<i64>,
+        key: Option<IdempotencyKey>,
+    ) -> Result<abi::Reservation, abi::Error>;
 
     /// get reservation by id
-    async fn get(&self, id: ReservationId) -> Result<abi::Reservation, abi::Error>;
+    async fn get(
+        &self,
+        tenant_id: TenantId,
+        id: ReservationId,
+    ) -> Result<abi::Reservation, abi::Error>;
 
     /// query reservations
     async fn query(
         &self,
         query: abi::ReservationQuery,
     ) -> Result<Vec<abi::Reservation>, abi::Error>;
+
+    /// counts and booked time of the reservations the query finds, ordered by group key.
+    /// by day, a reservation's time all counts on the local day it starts
+    async fn aggregate(
+        &self,
+        query: abi::ReservationQuery,
+        group_by: abi::AggregateGroup,
+        utc_offset_minutes: i32,
+    ) -> Result<Vec<abi::Aggregate>, abi::Error>;
+
+    /// forget the idempotency keys first used before the given time and return how many there were,
+    /// a key stays valid until then. the service expires keys a day after their first use
+    async fn expire_idempotency_keys(&self, before: DateTime<Utc>) -> Result<u64, abi::Error>;
+
+    /// make child a sub-resource of parent, booking either one blocks the other from then on
+    async fn link_resources(
+        &self,
+        tenant_id: TenantId,
+        parent_id: ResourceId,
+        child_id: ResourceId,
+    ) -> Result<(), abi::Error>;
+
+    /// remove the relation between parent and child
+    async fn unlink_resources(
+        &self,
+        tenant_id: TenantId,
+        parent_id: ResourceId,
+        child_id: ResourceId,
+    ) -> Result<(), abi::Error>;
+
+    /// create or update the settings of a resource
+    async fn update_resource(&self, resource: abi::Resource) -> Result<abi::Resource, abi::Error>;
+
+    /// if current status is awaiting approval, change it to pending so that it can be confirmed
+    async fn approve(
+        &self,
+        tenant_id: TenantId,
+        id: ReservationId,
+        approver_id: UserId,
+        reason: String,
+        expected_version: Option<i64>,
+        key: Option<IdempotencyKey>,
+    ) -> Result<abi::Reservation, abi::Error>;
+
+    /// if current status is awaiting approval, change it to rejected and release its resources
+    async fn reject(
+        &self,
+        tenant_id: TenantId,
+        id: ReservationId,
+        approver_id: UserId,
+        reason: String,
+        expected_version: Option<i64>,
+        key: Option<IdempotencyKey>,
+    ) -> Result<abi::Reservation, abi::Error>;
+
+    /// check in a pending or confirmed reservation within the check-in window
+    async fn check_in(
+        &self,
+        tenant_id: TenantId,
+        id: ReservationId,
+        expected_version: Option<i64>,
+        key: Option<IdempotencyKey>,
+    ) -> Result<abi::Reservation, abi::Error>;
+
+    /// mark reservations not checked in whose check-in window closed as no-shows, however long ago,
+    /// release their remaining time and return them
+    async fn release_no_shows(&self) -> Result<Vec<abi::Reservation>, abi::Error>;
+
+    /// number of no-shows recorded for the user
+    async fn no_show_count(&self, tenant_id: TenantId, user_id: UserId) -> Result<i64, abi::Error>;
+
+    /// price the reservation would have if reserved now, from the rate cards of its resources
+    async fn quote(&self, rsvp: abi::Reservation) -> Result<i64, abi::Error>;
+
+    /// secret token of the user's calendar feed, created on first use and stable afterwards
+    async fn feed_token(&self, tenant_id: TenantId, user_id: UserId) -> Result<String, abi::Error>;
+
+    /// replace the token of the user's calendar feed, the old one stops working
+    async fn reset_feed_token(
+        &self,
+        tenant_id: TenantId,
+        user_id: UserId,
+    ) -> Result<String, abi::Error>;
+
+    /// tenant and user the calendar feed token belongs to
+    async fn feed_owner(&self, token: String) -> Result<(TenantId, UserId), abi::Error>;
+
+    /// move a reservation to a new window and quote its price again
+    async fn reschedule(
+        &self,
+        tenant_id: TenantId,
+        id: ReservationId,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        expected_version: Option<i64>,
+        key: Option<IdempotencyKey>,
+    ) -> Result<abi::Reservation, abi::Error>;
 }
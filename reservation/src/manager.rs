@@ -1,121 +1,833 @@
-use crate::{ReservationId, Rsvp};
+use crate::{
+    fingerprint, validate_key, version_part, BulkReserve, CheckInPolicy, IdempotencyKey,
+    ReminderPolicy, ReservationFilter, ReservationId, ResourceId, Rsvp, TenantId, UserId,
+};
 use async_trait::async_trait;
-use sqlx::{types::Uuid, PgPool, Row};
+use chrono::{DateTime, Utc};
+use prost::Message;
+use sqlx::{types::Uuid, Acquire, Executor, PgPool, Postgres, Row, Transaction};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ReservationManager {
     pool: PgPool,
+    check_in: CheckInPolicy,
+    reminders: ReminderPolicy,
 }
 
 #[async_trait]
 impl Rsvp for ReservationManager {
-    async fn reserve(&self, mut rsvp: abi::Reservation) -> Result<abi::Reservation, abi::Error> {
+    async fn reserve(
+        &self,
+        rsvp: abi::Reservation,
+        key: Option<IdempotencyKey>,
+    ) -> Result<abi::Reservation, abi::Error> {
         rsvp.validate()?;
 
-        let status = abi::ReservationStatus::from_i32(rsvp.status) // 数字转枚举值
-            .unwrap_or(abi::ReservationStatus::Pending);
-        let timespan = rsvp.get_timespan();
-        let id:Uuid = sqlx::query(
-            "INSERT INTO rsvp.reservations (user_id, resource_id, timespan, note, status) VALUES ($1, $2, $3, $4, $5::rsvp.reservation_status) RETURNING id"
+        let request = fingerprint(&[&rsvp.encode_to_vec()]);
+        let mut tx = self.pool.begin().await?;
+        if let Some(rsvp) = replay(
+            &mut tx,
+            &rsvp.tenant_id,
+            key.as_deref(),
+            "reserve",
+            &request,
         )
-        .bind(rsvp.user_id.clone())
-        .bind(rsvp.resource_id.clone())
-        .bind(timespan)
-        .bind(rsvp.note.clone())
-        .bind(status.to_string())
-        .fetch_one(&self.pool)
-        .await?.get(0);
-
-        rsvp.id = id.to_string();
+        .await?
+        {
+            return Ok(rsvp);
+        }
 
+        let rsvp = insert(&mut tx, rsvp, &self.reminders).await?;
+        save_response(&mut tx, &rsvp.tenant_id, key.as_deref(), &rsvp).await?;
+        tx.commit().await?;
         Ok(rsvp)
     }
 
-    async fn change_status(&self, id: ReservationId) -> Result<abi::Reservation, abi::Error> {
+    async fn bulk_reserve(
+        &self,
+        rsvps: Vec<abi::Reservation>,
+        dry_run: bool,
+    ) -> Result<BulkReserve, abi::Error> {
+        let mut result = BulkReserve::default();
+        let mut tx = self.pool.begin().await?;
+        for (row, rsvp) in rsvps.into_iter().enumerate() {
+            if let Err(e) = rsvp.validate().and_then(|_| rsvp.requested_status()) {
+                result.rejected.push((row, e));
+                continue;
+            }
+
+            // earlier rows are visible to later ones, a conflicting row only rolls back its own savepoint
+            let mut savepoint = tx.begin().await?;
+            match insert(&mut savepoint, rsvp, &self.reminders).await {
+                Ok(rsvp) => {
+                    savepoint.commit().await?;
+                    result.reserved.push(rsvp);
+                }
+                Err(
+                    e @ (abi::Error::ConflictReservation(_) | abi::Error::ConflictReservations(_)),
+                ) => {
+                    savepoint.rollback().await?;
+                    result.rejected.push((row, e));
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        if dry_run {
+            tx.rollback().await?;
+            for rsvp in &mut result.reserved {
+                rsvp.id.clear();
+                rsvp.version = 0;
+            }
+        } else {
+            tx.commit().await?;
+        }
+        Ok(result)
+    }
+
+    async fn change_status(
+        &self,
+        tenant_id: TenantId,
+        id: ReservationId,
+        expected_version: Option<i64>,
+        key: Option<IdempotencyKey>,
+    ) -> Result<abi::Reservation, abi::Error> {
         let id = Uuid::parse_str(&id).map_err(|_| abi::Error::InvalidReservationId(id.clone()))?;
+
+        let request = fingerprint(&[id.to_string().as_bytes(), &version_part(expected_version)]);
+        let mut tx = self.pool.begin().await?;
+        if let Some(rsvp) = replay(&mut tx, &tenant_id, key.as_deref(), "confirm", &request).await?
+        {
+            return Ok(rsvp);
+        }
+        check_version(&mut tx, &tenant_id, id, expected_version).await?;
+
         let rsvp = sqlx::query_as(
-            "UPDATE rsvp.reservations SET status = 'confirmed' WHERE id = $1 AND status = 'pending' RETURNING *"
-        ).bind(id).fetch_one(&self.pool).await?;
+            "UPDATE rsvp.reservations SET status = 'confirmed' WHERE id = $1 AND tenant_id = $2 AND status = 'pending' RETURNING *"
+        ).bind(id).bind(&tenant_id).fetch_one(&mut tx).await?;
+
+        save_response(&mut tx, &tenant_id, key.as_deref(), &rsvp).await?;
+        tx.commit().await?;
         Ok(rsvp)
     }
 
     async fn update_note(
         &self,
+        tenant_id: TenantId,
         id: ReservationId,
         note: String,
+        expected_version: Option<i64>,
+        key: Option<IdempotencyKey>,
+    ) -> Result<abi::Reservation, abi::Error> {
+        let id = Uuid::parse_str(&id).map_err(|_| abi::Error::InvalidReservationId(id.clone()))?;
+
+        let request = fingerprint(&[
+            id.to_string().as_bytes(),
+            note.as_bytes(),
+            &version_part(expected_version),
+        ]);
+        let mut tx = self.pool.begin().await?;
+        if let Some(rsvp) = replay(&mut tx, &tenant_id, key.as_deref(), "update", &request).await? {
+            return Ok(rsvp);
+        }
+        check_version(&mut tx, &tenant_id, id, expected_version).await?;
+
+        let rsvp = sqlx::query_as(
+            "UPDATE rsvp.reservations SET note = $1 WHERE id = $2 AND tenant_id = $3 RETURNING *",
+        )
+        .bind(note)
+        .bind(id)
+        .bind(&tenant_id)
+        .fetch_one(&mut tx)
+        .await?;
+
+        save_response(&mut tx, &tenant_id, key.as_deref(), &rsvp).await?;
+        tx.commit().await?;
+        Ok(rsvp)
+    }
+
+    async fn delete(
+        &self,
+        tenant_id: TenantId,
+        id: ReservationId,
+        expected_version: Option<i64>,
+        key: Option<IdempotencyKey>,
+    ) -> Result<abi::Reservation, abi::Error> {
+        let id = Uuid::parse_str(&id).map_err(|_| abi::Error::InvalidReservationId(id.clone()))?;
+
+        let request = fingerprint(&[id.to_string().as_bytes(), &version_part(expected_version)]);
+        let mut tx = self.pool.begin().await?;
+        if let Some(rsvp) = replay(&mut tx, &tenant_id, key.as_deref(), "cancel", &request).await? {
+            return Ok(rsvp);
+        }
+        check_version(&mut tx, &tenant_id, id, expected_version).await?;
+
+        let rsvp = sqlx::query_as(
+            "DELETE FROM rsvp.reservations WHERE id = $1 AND tenant_id = $2 RETURNING *",
+        )
+        .bind(id)
+        .bind(&tenant_id)
+        .fetch_one(&mut tx)
+        .await?;
+
+        save_response(&mut tx, &tenant_id, key.as_deref(), &rsvp).await?;
+        tx.commit().await?;
+        Ok(rsvp)
+    }
+
+    async fn get(
+        &self,
+        tenant_id: TenantId,
+        id: ReservationId,
     ) -> Result<abi::Reservation, abi::Error> {
         let id = Uuid::parse_str(&id).map_err(|_| abi::Error::InvalidReservationId(id.clone()))?;
         let rsvp =
-            sqlx::query_as("UPDATE rsvp.reservations SET note = $1 WHERE id = $2 RETURNING *")
-                .bind(note)
+            sqlx::query_as("SELECT * FROM rsvp.reservations WHERE id = $1 AND tenant_id = $2")
                 .bind(id)
+                .bind(tenant_id)
                 .fetch_one(&self.pool)
                 .await?;
         Ok(rsvp)
     }
 
-    async fn delete(&self, id: ReservationId) -> Result<(), abi::Error> {
-        let id = Uuid::parse_str(&id).map_err(|_| abi::Error::InvalidReservationId(id.clone()))?;
-        sqlx::query("DELETE FROM rsvp.reservations WHERE id = $1")
-            .bind(id)
+    async fn query(
+        &self,
+        query: abi::ReservationQuery,
+    ) -> Result<Vec<abi::Reservation>, abi::Error> {
+        query.validate()?;
+        self.filter(&ReservationFilter::from_query(&query)?).await
+    }
+
+    async fn aggregate(
+        &self,
+        query: abi::ReservationQuery,
+        group_by: abi::AggregateGroup,
+        utc_offset_minutes: i32,
+    ) -> Result<Vec<abi::Aggregate>, abi::Error> {
+        query.validate()?;
+        let utc_offset = abi::utc_offset(utc_offset_minutes)?;
+        let filter = ReservationFilter::from_query(&query)?;
+        let mut qb = filter.build_aggregate(group_by, utc_offset);
+        let rows = qb.build().fetch_all(&self.pool).await?;
+        Ok(rows
+            .iter()
+            .map(|row| {
+                abi::Aggregate::new(
+                    row.get::<String, _>("key"),
+                    row.get("count"),
+                    row.get("seconds"),
+                )
+            })
+            .collect())
+    }
+
+    async fn expire_idempotency_keys(&self, before: DateTime<Utc>) -> Result<u64, abi::Error> {
+        let expired = sqlx::query("DELETE FROM rsvp.idempotency_keys WHERE created_at < $1")
+            .bind(before)
             .execute(&self.pool)
-            .await?;
+            .await?
+            .rows_affected();
+        Ok(expired)
+    }
+
+    async fn link_resources(
+        &self,
+        tenant_id: TenantId,
+        parent_id: ResourceId,
+        child_id: ResourceId,
+    ) -> Result<(), abi::Error> {
+        if parent_id.is_empty() {
+            return Err(abi::Error::InvalidResourceId(parent_id));
+        }
+        if child_id.is_empty() {
+            return Err(abi::Error::InvalidResourceId(child_id));
+        }
+
+        let mut tx = self.pool.begin().await?;
+        // parent must not be child itself or one of its descendants
+        let cycle: bool = sqlx::query(
+            "WITH RECURSIVE descendants(id) AS (
+                SELECT $2::varchar
+                UNION
+                SELECT r.child_id FROM rsvp.resource_relations r JOIN descendants d ON r.parent_id = d.id WHERE r.tenant_id = $1
+            ) SELECT EXISTS (SELECT 1 FROM descendants WHERE id = $3)",
+        )
+        .bind(&tenant_id)
+        .bind(&child_id)
+        .bind(&parent_id)
+        .fetch_one(&mut tx)
+        .await?
+        .get(0);
+        if cycle {
+            return Err(abi::Error::ResourceCycle {
+                parent: parent_id,
+                child: child_id,
+            });
+        }
+
+        sqlx::query(
+            "INSERT INTO rsvp.resource_relations (tenant_id, parent_id, child_id) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING",
+        )
+        .bind(&tenant_id)
+        .bind(&parent_id)
+        .bind(&child_id)
+        .execute(&mut tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn unlink_resources(
+        &self,
+        tenant_id: TenantId,
+        parent_id: ResourceId,
+        child_id: ResourceId,
+    ) -> Result<(), abi::Error> {
+        let deleted = sqlx::query(
+            "DELETE FROM rsvp.resource_relations WHERE tenant_id = $1 AND parent_id = $2 AND child_id = $3",
+        )
+        .bind(tenant_id)
+        .bind(parent_id)
+        .bind(child_id)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        if deleted == 0 {
+            return Err(abi::Error::NotFound);
+        }
         Ok(())
     }
+    async fn update_resource(&self, resource: abi::Resource) -> Result<abi::Resource, abi::Error> {
+        resource.validate()?;
+        let resource = sqlx::query_as(
+            "INSERT INTO rsvp.resources (tenant_id, id, requires_approval, rate_card) VALUES ($1, $2, $3, $4) ON CONFLICT (tenant_id, id) DO UPDATE SET requires_approval = EXCLUDED.requires_approval, rate_card = EXCLUDED.rate_card RETURNING *",
+        )
+        .bind(resource.tenant_id)
+        .bind(resource.id)
+        .bind(resource.requires_approval)
+        .bind(resource.rate_card.map(|card| card.encode_to_vec()))
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(resource)
+    }
+
+    async fn approve(
+        &self,
+        tenant_id: TenantId,
+        id: ReservationId,
+        approver_id: UserId,
+        reason: String,
+        expected_version: Option<i64>,
+        key: Option<IdempotencyKey>,
+    ) -> Result<abi::Reservation, abi::Error> {
+        self.review(
+            tenant_id,
+            id,
+            abi::ReservationStatus::Pending,
+            approver_id,
+            reason,
+            expected_version,
+            key,
+        )
+        .await
+    }
 
-    async fn get(&self, id: ReservationId) -> Result<abi::Reservation, abi::Error> {
+    async fn reject(
+        &self,
+        tenant_id: TenantId,
+        id: ReservationId,
+        approver_id: UserId,
+        reason: String,
+        expected_version: Option<i64>,
+        key: Option<IdempotencyKey>,
+    ) -> Result<abi::Reservation, abi::Error> {
+        self.review(
+            tenant_id,
+            id,
+            abi::ReservationStatus::Rejected,
+            approver_id,
+            reason,
+            expected_version,
+            key,
+        )
+        .await
+    }
+
+    async fn check_in(
+        &self,
+        tenant_id: TenantId,
+        id: ReservationId,
+        expected_version: Option<i64>,
+        key: Option<IdempotencyKey>,
+    ) -> Result<abi::Reservation, abi::Error> {
         let id = Uuid::parse_str(&id).map_err(|_| abi::Error::InvalidReservationId(id.clone()))?;
-        let rsvp = sqlx::query_as("SELECT * FROM rsvp.reservations WHERE id = $1")
-            .bind(id)
-            .fetch_one(&self.pool)
-            .await?;
+
+        let request = fingerprint(&[id.to_string().as_bytes(), &version_part(expected_version)]);
+        let mut tx = self.pool.begin().await?;
+        if let Some(rsvp) =
+            replay(&mut tx, &tenant_id, key.as_deref(), "check_in", &request).await?
+        {
+            return Ok(rsvp);
+        }
+        check_version(&mut tx, &tenant_id, id, expected_version).await?;
+
+        let rsvp = sqlx::query_as(
+            "UPDATE rsvp.reservations SET checked_in_at = COALESCE(checked_in_at, $1) WHERE id = $2 AND tenant_id = $3 AND status IN ('pending', 'confirmed') AND $1 BETWEEN lower(timespan) - $4 AND lower(timespan) + $5 RETURNING *",
+        )
+        .bind(Utc::now())
+        .bind(id)
+        .bind(&tenant_id)
+        .bind(self.check_in.before)
+        .bind(self.check_in.after)
+        .fetch_optional(&mut tx)
+        .await?;
+        let rsvp = match rsvp {
+            Some(rsvp) => rsvp,
+            None => {
+                // tell a closed check-in window from a missing reservation
+                sqlx::query("SELECT 1 FROM rsvp.reservations WHERE id = $1 AND tenant_id = $2")
+                    .bind(id)
+                    .bind(&tenant_id)
+                    .fetch_one(&mut tx)
+                    .await?;
+                return Err(abi::Error::CheckInNotAllowed);
+            }
+        };
+
+        save_response(&mut tx, &tenant_id, key.as_deref(), &rsvp).await?;
+        tx.commit().await?;
         Ok(rsvp)
     }
 
-    async fn query(
-        &self,
-        query: abi::ReservationQuery,
-    ) -> Result<Vec<abi::Reservation>, abi::Error> {
-        let user_id = str_to_option(&query.user_id);
-        let resource_id = str_to_option(&query.resource_id);
-        let range = query.get_timespan();
-        let status = abi::ReservationStatus::from_i32(query.status)
-            .unwrap_or(abi::ReservationStatus::Pending);
+    async fn release_no_shows(&self) -> Result<Vec<abi::Reservation>, abi::Error> {
         let rsvps = sqlx::query_as(
-            "SELECT * FROM rsvp.query($1, $2, $3, $4::rsvp.reservation_status, $5, $6, $7)",
+            "WITH released AS (
+                UPDATE rsvp.reservations SET status = 'no_show', timespan = tstzrange(lower(timespan), LEAST(upper(timespan), $1))
+                WHERE checked_in_at IS NULL AND status IN ('pending', 'confirmed')
+                    AND lower(timespan) < $1 - $2
+                RETURNING *
+            ), counted AS (
+                INSERT INTO rsvp.user_no_shows (tenant_id, user_id, count)
+                SELECT tenant_id, user_id, count(*) FROM released GROUP BY tenant_id, user_id
+                ON CONFLICT (tenant_id, user_id) DO UPDATE SET count = rsvp.user_no_shows.count + EXCLUDED.count
+            ) SELECT * FROM released",
         )
-        .bind(user_id)
-        .bind(resource_id)
-        .bind(range)
-        .bind(status.to_string())
-        .bind(query.page)
-        .bind(query.page_size)
-        .bind(query.desc)
+        .bind(Utc::now())
+        .bind(self.check_in.after)
         .fetch_all(&self.pool)
         .await?;
-
         Ok(rsvps)
     }
+
+    async fn no_show_count(&self, tenant_id: TenantId, user_id: UserId) -> Result<i64, abi::Error> {
+        let count = sqlx::query(
+            "SELECT count FROM rsvp.user_no_shows WHERE tenant_id = $1 AND user_id = $2",
+        )
+        .bind(tenant_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .map(|row| row.get(0))
+        .unwrap_or(0);
+        Ok(count)
+    }
+
+    async fn quote(&self, rsvp: abi::Reservation) -> Result<i64, abi::Error> {
+        rsvp.validate()?;
+        price(&self.pool, &rsvp).await
+    }
+
+    async fn feed_token(&self, tenant_id: TenantId, user_id: UserId) -> Result<String, abi::Error> {
+        if user_id.is_empty() {
+            return Err(abi::Error::InvalidUserId(user_id));
+        }
+        // the no-op update makes RETURNING yield the existing token
+        let token = sqlx::query(
+            "INSERT INTO rsvp.calendar_feeds (tenant_id, user_id) VALUES ($1, $2) ON CONFLICT (tenant_id, user_id) DO UPDATE SET token = rsvp.calendar_feeds.token RETURNING token",
+        )
+        .bind(tenant_id)
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?
+        .get(0);
+        Ok(token)
+    }
+
+    async fn reset_feed_token(
+        &self,
+        tenant_id: TenantId,
+        user_id: UserId,
+    ) -> Result<String, abi::Error> {
+        if user_id.is_empty() {
+            return Err(abi::Error::InvalidUserId(user_id));
+        }
+        let token = sqlx::query(
+            "INSERT INTO rsvp.calendar_feeds (tenant_id, user_id) VALUES ($1, $2) ON CONFLICT (tenant_id, user_id) DO UPDATE SET token = DEFAULT, created_at = DEFAULT RETURNING token",
+        )
+        .bind(tenant_id)
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?
+        .get(0);
+        Ok(token)
+    }
+
+    async fn feed_owner(&self, token: String) -> Result<(TenantId, UserId), abi::Error> {
+        let row =
+            sqlx::query("SELECT tenant_id, user_id FROM rsvp.calendar_feeds WHERE token = $1")
+                .bind(token)
+                .fetch_one(&self.pool)
+                .await?;
+        Ok((row.get("tenant_id"), row.get("user_id")))
+    }
+
+    async fn reschedule(
+        &self,
+        tenant_id: TenantId,
+        id: ReservationId,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        expected_version: Option<i64>,
+        key: Option<IdempotencyKey>,
+    ) -> Result<abi::Reservation, abi::Error> {
+        let id = Uuid::parse_str(&id).map_err(|_| abi::Error::InvalidReservationId(id.clone()))?;
+        if start >= end {
+            return Err(abi::Error::InvalidTime);
+        }
+
+        let request = fingerprint(&[
+            id.to_string().as_bytes(),
+            start.to_rfc3339().as_bytes(),
+            end.to_rfc3339().as_bytes(),
+            &version_part(expected_version),
+        ]);
+        let mut tx = self.pool.begin().await?;
+        if let Some(rsvp) =
+            replay(&mut tx, &tenant_id, key.as_deref(), "reschedule", &request).await?
+        {
+            return Ok(rsvp);
+        }
+        check_version(&mut tx, &tenant_id, id, expected_version).await?;
+
+        // rejected and no-show reservations are over, they can't be moved
+        let mut rsvp: abi::Reservation = sqlx::query_as(
+            "SELECT * FROM rsvp.reservations WHERE id = $1 AND tenant_id = $2 AND status IN ('pending', 'confirmed', 'awaiting_approval') FOR UPDATE",
+        )
+        .bind(id)
+        .bind(&tenant_id)
+        .fetch_one(&mut tx)
+        .await?;
+        rsvp.start = Some(abi::convert_to_timestamp(start));
+        rsvp.end = Some(abi::convert_to_timestamp(end));
+        let price = price(&mut tx, &rsvp).await?;
+
+        let rsvp = sqlx::query_as(
+            "UPDATE rsvp.reservations SET timespan = $1, price = $2 WHERE id = $3 AND tenant_id = $4 RETURNING *",
+        )
+        .bind(rsvp.get_timespan())
+        .bind(price)
+        .bind(id)
+        .bind(&tenant_id)
+        .fetch_one(&mut tx)
+        .await?;
+        schedule_reminders(&mut tx, id, start, &self.reminders).await?;
+
+        save_response(&mut tx, &tenant_id, key.as_deref(), &rsvp).await?;
+        tx.commit().await?;
+        Ok(rsvp)
+    }
 }
 
 impl ReservationManager {
-    #[allow(dead_code)]
     pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+        Self {
+            pool,
+            check_in: CheckInPolicy::default(),
+            reminders: ReminderPolicy::default(),
+        }
+    }
+
+    pub fn with_check_in_policy(mut self, policy: CheckInPolicy) -> Self {
+        self.check_in = policy;
+        self
+    }
+
+    pub fn with_reminder_policy(mut self, policy: ReminderPolicy) -> Self {
+        self.reminders = policy;
+        self
+    }
+
+    /// reservations matching any combination of conditions, not only those a query can express
+    pub async fn filter(
+        &self,
+        filter: &ReservationFilter,
+    ) -> Result<Vec<abi::Reservation>, abi::Error> {
+        let mut qb = filter.build();
+        let rsvps = qb.build_query_as().fetch_all(&self.pool).await?;
+        Ok(rsvps)
+    }
+
+    /// move a reservation awaiting approval to the given status, recording the approver
+    #[allow(clippy::too_many_arguments)]
+    async fn review(
+        &self,
+        tenant_id: TenantId,
+        id: ReservationId,
+        status: abi::ReservationStatus,
+        approver_id: UserId,
+        reason: String,
+        expected_version: Option<i64>,
+        key: Option<IdempotencyKey>,
+    ) -> Result<abi::Reservation, abi::Error> {
+        let id = Uuid::parse_str(&id).map_err(|_| abi::Error::InvalidReservationId(id.clone()))?;
+        if approver_id.is_empty() {
+            return Err(abi::Error::InvalidUserId(approver_id));
+        }
+
+        let op = match status {
+            abi::ReservationStatus::Rejected => "reject",
+            _ => "approve",
+        };
+        let request = fingerprint(&[
+            id.to_string().as_bytes(),
+            approver_id.as_bytes(),
+            reason.as_bytes(),
+            &version_part(expected_version),
+        ]);
+        let mut tx = self.pool.begin().await?;
+        if let Some(rsvp) = replay(&mut tx, &tenant_id, key.as_deref(), op, &request).await? {
+            return Ok(rsvp);
+        }
+        check_version(&mut tx, &tenant_id, id, expected_version).await?;
+
+        let rsvp = sqlx::query_as(
+            "UPDATE rsvp.reservations SET status = $1::rsvp.reservation_status, approver_id = $2, approval_reason = $3 WHERE id = $4 AND tenant_id = $5 AND status = 'awaiting_approval' RETURNING *",
+        )
+        .bind(status.to_string())
+        .bind(approver_id)
+        .bind(reason)
+        .bind(id)
+        .bind(&tenant_id)
+        .fetch_one(&mut tx)
+        .await?;
+
+        save_response(&mut tx, &tenant_id, key.as_deref(), &rsvp).await?;
+        tx.commit().await?;
+        Ok(rsvp)
+    }
+}
+
+/// insert a validated reservation, it awaits approval if any of its resources requires it
+async fn insert(
+    tx: &mut Transaction<'_, Postgres>,
+    mut rsvp: abi::Reservation,
+    reminders: &ReminderPolicy,
+) -> Result<abi::Reservation, abi::Error> {
+    let mut status = rsvp.requested_status()?;
+    if requires_approval(tx, &rsvp).await? {
+        status = abi::ReservationStatus::AwaitingApproval;
+    }
+    rsvp.price = price(&mut *tx, &rsvp).await?;
+    let timespan = rsvp.get_timespan();
+    let row = sqlx::query(
+        "INSERT INTO rsvp.reservations (tenant_id, user_id, resource_id, extra_resource_ids, timespan, note, status, price) VALUES ($1, $2, $3, $4, $5, $6, $7::rsvp.reservation_status, $8) RETURNING id, version"
+    )
+    .bind(rsvp.tenant_id.clone())
+    .bind(rsvp.user_id.clone())
+    .bind(rsvp.resource_id.clone())
+    .bind(rsvp.extra_resource_ids.clone())
+    .bind(timespan)
+    .bind(rsvp.note.clone())
+    .bind(status.to_string())
+    .bind(rsvp.price)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let id: Uuid = row.get("id");
+    rsvp.id = id.to_string();
+    rsvp.version = row.get("version");
+    rsvp.status = status as i32;
+    let start = abi::convert_to_utc_time(rsvp.start.clone().unwrap());
+    schedule_reminders(tx, id, start, reminders).await?;
+    Ok(rsvp)
+}
+
+/// replace the reminders of the reservation, those that would already be due are skipped
+async fn schedule_reminders(
+    tx: &mut Transaction<'_, Postgres>,
+    id: Uuid,
+    start: DateTime<Utc>,
+    policy: &ReminderPolicy,
+) -> Result<(), abi::Error> {
+    sqlx::query("DELETE FROM rsvp.reminders WHERE reservation_id = $1")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+    let now = Utc::now();
+    for before in &policy.before {
+        let remind_at = start - *before;
+        if remind_at <= now {
+            continue;
+        }
+        sqlx::query(
+            "INSERT INTO rsvp.reminders (reservation_id, lead_time, remind_at) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING",
+        )
+        .bind(id)
+        .bind(*before)
+        .bind(remind_at)
+        .execute(&mut *tx)
+        .await?;
+    }
+    Ok(())
+}
+
+/// whether any resource of the reservation needs an approver
+async fn requires_approval(
+    tx: &mut Transaction<'_, Postgres>,
+    rsvp: &abi::Reservation,
+) -> Result<bool, abi::Error> {
+    let mut resource_ids = vec![rsvp.resource_id.clone()];
+    resource_ids.extend(rsvp.extra_resource_ids.iter().cloned());
+    let required = sqlx::query(
+        "SELECT EXISTS (SELECT 1 FROM rsvp.resources WHERE tenant_id = $1 AND id = ANY($2) AND requires_approval)",
+    )
+    .bind(&rsvp.tenant_id)
+    .bind(resource_ids)
+    .fetch_one(&mut *tx)
+    .await?
+    .get(0);
+    Ok(required)
+}
+
+/// sum of the quotes of every resource of the reservation, resources without a rate card are free
+async fn price<'c>(
+    executor: impl Executor<'c, Database = Postgres>,
+    rsvp: &abi::Reservation,
+) -> Result<i64, abi::Error> {
+    let mut resource_ids = vec![rsvp.resource_id.clone()];
+    resource_ids.extend(rsvp.extra_resource_ids.iter().cloned());
+    let rows = sqlx::query(
+        "SELECT id, rate_card FROM rsvp.resources WHERE tenant_id = $1 AND id = ANY($2) AND rate_card IS NOT NULL",
+    )
+    .bind(&rsvp.tenant_id)
+    .bind(resource_ids)
+    .fetch_all(executor)
+    .await?;
+
+    let start = abi::convert_to_utc_time(rsvp.start.clone().unwrap());
+    let end = abi::convert_to_utc_time(rsvp.end.clone().unwrap());
+    let mut cards = vec![];
+    for row in rows {
+        let id: String = row.try_get("id")?;
+        let bytes: Vec<u8> = row.try_get("rate_card")?;
+        let card = abi::RateCard::decode(bytes.as_slice())
+            .map_err(|e| abi::Error::InvalidRateCard(format!("{}: {}", id, e)))?;
+        // quoting assumes a valid card, like the ones update_resource accepts
+        card.validate()?;
+        cards.push(card);
+    }
+    abi::RateCard::quote_all(&cards, start, end)
+}
+
+/// claim the idempotency key for the request, or return the result stored by an earlier call
+/// with the same key. the key can't be reused for another op or another request
+async fn replay(
+    tx: &mut Transaction<'_, Postgres>,
+    tenant_id: &str,
+    key: Option<&str>,
+    op: &str,
+    request: &[u8],
+) -> Result<Option<abi::Reservation>, abi::Error> {
+    let key = match key {
+        Some(key) => key,
+        None => return Ok(None),
+    };
+    validate_key(key)?;
+
+    // a concurrent call holding the same key blocks here until it commits or rolls back
+    let claimed = sqlx::query(
+        "INSERT INTO rsvp.idempotency_keys (tenant_id, key, op, fingerprint) VALUES ($1, $2, $3, $4) ON CONFLICT DO NOTHING",
+    )
+    .bind(tenant_id)
+    .bind(key)
+    .bind(op)
+    .bind(request)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+    if claimed == 1 {
+        return Ok(None);
+    }
+
+    let row = sqlx::query(
+        "SELECT op, fingerprint, response FROM rsvp.idempotency_keys WHERE tenant_id = $1 AND key = $2",
+    )
+    .bind(tenant_id)
+    .bind(key)
+    .fetch_one(&mut *tx)
+    .await?;
+    let stored_op: String = row.get("op");
+    let stored_request: Vec<u8> = row.get("fingerprint");
+    let response: Option<Vec<u8>> = row.get("response");
+    match response {
+        Some(response) if stored_op == op && stored_request == request => Ok(Some(
+            abi::Reservation::decode(response.as_slice()).map_err(|_| abi::Error::Unknown)?,
+        )),
+        _ => Err(abi::Error::IdempotencyKeyReused(key.to_string())),
+    }
+}
+
+/// lock the reservation and make sure it's still at the expected version
+async fn check_version(
+    tx: &mut Transaction<'_, Postgres>,
+    tenant_id: &str,
+    id: Uuid,
+    expected: Option<i64>,
+) -> Result<(), abi::Error> {
+    let expected = match expected {
+        Some(expected) => expected,
+        None => return Ok(()),
+    };
+
+    let actual: i64 = sqlx::query(
+        "SELECT version FROM rsvp.reservations WHERE id = $1 AND tenant_id = $2 FOR UPDATE",
+    )
+    .bind(id)
+    .bind(tenant_id)
+    .fetch_one(&mut *tx)
+    .await?
+    .get(0);
+    if actual != expected {
+        return Err(abi::Error::PreconditionFailed { expected, actual });
     }
+    Ok(())
 }
 
-fn str_to_option(s: &str) -> Option<&str> {
-    if s.is_empty() {
-        None
-    } else {
-        Some(s)
+/// store the result of the op for the claimed idempotency key
+async fn save_response(
+    tx: &mut Transaction<'_, Postgres>,
+    tenant_id: &str,
+    key: Option<&str>,
+    rsvp: &abi::Reservation,
+) -> Result<(), abi::Error> {
+    if let Some(key) = key {
+        sqlx::query(
+            "UPDATE rsvp.idempotency_keys SET response = $1 WHERE tenant_id = $2 AND key = $3",
+        )
+        .bind(rsvp.encode_to_vec())
+        .bind(tenant_id)
+        .bind(key)
+        .execute(&mut *tx)
+        .await?;
     }
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use abi::{Reservation, ReservationConflictInfo, ReservationQuery, ReservationStatus};
+    use chrono::Duration;
 
     use super::*;
 
@@ -129,13 +841,14 @@ mod tests {
     async fn reserve_conflict_reservation_should_reject() {
         let (_, manager) = make_reservation(migrated_pool.clone()).await;
         let rsvp2 = abi::Reservation::new_pending(
+            "tenant_id",
             "user_id2",
             "resource_id",
             "2022-12-26T12:00:00-0700".parse().unwrap(),
             "2022-12-30T12:00:00-0700".parse().unwrap(),
             "Test note2",
         );
-        let err = manager.reserve(rsvp2).await.unwrap_err();
+        let err = manager.reserve(rsvp2, None).await.unwrap_err();
         if let abi::Error::ConflictReservation(ReservationConflictInfo::Parsed(info)) = err {
             assert_eq!(info.exist.rid, "resource_id");
             assert_eq!(info.exist.start.to_rfc3339(), "2022-12-25T19:00:00+00:00");
@@ -148,7 +861,10 @@ mod tests {
     #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
     async fn reserve_change_status_should_work() {
         let (rsvp, manager) = make_reservation(migrated_pool.clone()).await;
-        let rsvp = manager.change_status(rsvp.id).await.unwrap();
+        let rsvp = manager
+            .change_status("tenant_id".into(), rsvp.id, None, None)
+            .await
+            .unwrap();
 
         assert_eq!(rsvp.status, abi::ReservationStatus::Confirmed as i32)
     }
@@ -157,60 +873,227 @@ mod tests {
     async fn update_note_should_work() {
         let (rsvp, manager) = make_reservation(migrated_pool.clone()).await;
         let rsvp = manager
-            .update_note(rsvp.id, "Updated Note!!!".to_owned())
+            .update_note(
+                "tenant_id".into(),
+                rsvp.id,
+                "Updated Note!!!".to_owned(),
+                None,
+                None,
+            )
             .await
             .unwrap();
 
-        assert_eq!(rsvp.note, "Updated Note!!!")
+        assert_eq!(rsvp.note, "Updated Note!!!");
+        assert_eq!(rsvp.version, 2);
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn update_with_stale_version_should_reject() {
+        let (rsvp, manager) = make_reservation(migrated_pool.clone()).await;
+        manager
+            .update_note(
+                "tenant_id".into(),
+                rsvp.id.clone(),
+                "first".to_owned(),
+                Some(rsvp.version),
+                None,
+            )
+            .await
+            .unwrap();
+        let err = manager
+            .update_note(
+                "tenant_id".into(),
+                rsvp.id.clone(),
+                "second".to_owned(),
+                Some(rsvp.version),
+                None,
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            abi::Error::PreconditionFailed {
+                expected: 1,
+                actual: 2
+            }
+        ));
     }
     #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
     async fn get_and_delete_reservation_should_work() {
         let (rsvp, manager) = make_reservation(migrated_pool.clone()).await;
-        let data = manager.get(rsvp.id.clone()).await.unwrap();
+        let data = manager
+            .get("tenant_id".into(), rsvp.id.clone())
+            .await
+            .unwrap();
         assert_eq!(rsvp, data);
-        let result = manager.delete(rsvp.id.clone()).await;
+        let result = manager
+            .delete("tenant_id".into(), rsvp.id.clone(), None, None)
+            .await;
         assert!(result.is_ok());
     }
 
-    async fn make_reservation(pool: PgPool) -> (Reservation, ReservationManager) {
-        make_basic_reservation(
-            pool,
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn reserve_with_same_idempotency_key_should_return_original() {
+        let manager = ReservationManager::new(migrated_pool.clone());
+        let rsvp = abi::Reservation::new_pending(
+            "tenant_id",
             "user_id1",
             "resource_id",
-            "2022-12-25T12:00:00-0700",
-            "2022-12-31T12:00:00-0700",
+            "2022-12-25T12:00:00-0700".parse().unwrap(),
+            "2022-12-31T12:00:00-0700".parse().unwrap(),
             "Test note1",
-        )
-        .await
-    }
-    async fn make_basic_reservation(
-        pool: PgPool,
-        uid: &str,
-        rid: &str,
-        start: &str,
-        end: &str,
-        note: &str,
-    ) -> (Reservation, ReservationManager) {
-        let manager = ReservationManager::new(pool.clone());
-        let rsvp = abi::Reservation::new_pending(
-            uid,
-            rid,
-            start.parse().unwrap(),
-            end.parse().unwrap(),
-            note,
         );
-        (manager.reserve(rsvp).await.unwrap(), manager)
+        let key = Some("retry-key".to_string());
+        let rsvp1 = manager.reserve(rsvp.clone(), key.clone()).await.unwrap();
+        let rsvp2 = manager.reserve(rsvp, key).await.unwrap();
+        assert_eq!(rsvp1, rsvp2);
     }
 
     #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
-    async fn query_reservations_should_work() {
+    async fn cancel_with_same_idempotency_key_should_not_act_again() {
         let (rsvp, manager) = make_reservation(migrated_pool.clone()).await;
-        let query = ReservationQuery::new(
-            "user_id1",
-            "",
-            "2022-12-01T12:00:00-0700".parse().unwrap(),
-            "2022-12-31T12:00:00-0700".parse().unwrap(),
-            ReservationStatus::Pending,
+        let key = Some("cancel-key".to_string());
+        let deleted = manager
+            .delete("tenant_id".into(), rsvp.id.clone(), None, key.clone())
+            .await
+            .unwrap();
+        let retried = manager
+            .delete("tenant_id".into(), rsvp.id.clone(), None, key)
+            .await
+            .unwrap();
+        assert_eq!(rsvp, deleted);
+        assert_eq!(deleted, retried);
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn idempotency_key_reused_for_another_op_should_reject() {
+        let (rsvp, manager) = make_reservation(migrated_pool.clone()).await;
+        let key = Some("shared-key".to_string());
+        manager
+            .update_note(
+                "tenant_id".into(),
+                rsvp.id.clone(),
+                "new note".to_owned(),
+                None,
+                key.clone(),
+            )
+            .await
+            .unwrap();
+        let err = manager
+            .change_status("tenant_id".into(), rsvp.id, None, key)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, abi::Error::IdempotencyKeyReused(_)));
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn idempotency_key_reused_for_another_reservation_should_reject() {
+        let (rsvp, manager) = make_reservation(migrated_pool.clone()).await;
+        let other = manager
+            .reserve(
+                abi::Reservation::new_pending(
+                    "tenant_id",
+                    "user_id2",
+                    "resource_id",
+                    "2023-01-05T12:00:00-0700".parse().unwrap(),
+                    "2023-01-06T12:00:00-0700".parse().unwrap(),
+                    "Test note2",
+                ),
+                None,
+            )
+            .await
+            .unwrap();
+        let key = Some("confirm-key".to_string());
+        manager
+            .change_status("tenant_id".into(), rsvp.id, None, key.clone())
+            .await
+            .unwrap();
+        let err = manager
+            .change_status("tenant_id".into(), other.id.clone(), None, key)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, abi::Error::IdempotencyKeyReused(_)));
+        let other = manager.get("tenant_id".into(), other.id).await.unwrap();
+        assert_eq!(other.status, ReservationStatus::Pending as i32);
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn expired_idempotency_key_should_be_usable_again() {
+        let (rsvp, manager) = make_reservation(migrated_pool.clone()).await;
+        let key = Some("expiring-key".to_string());
+        manager
+            .update_note(
+                "tenant_id".into(),
+                rsvp.id.clone(),
+                "first".to_owned(),
+                None,
+                key.clone(),
+            )
+            .await
+            .unwrap();
+
+        // keys used since are kept
+        let expired = manager
+            .expire_idempotency_keys(Utc::now() - chrono::Duration::hours(1))
+            .await
+            .unwrap();
+        assert_eq!(expired, 0);
+
+        let expired = manager
+            .expire_idempotency_keys(Utc::now() + chrono::Duration::minutes(1))
+            .await
+            .unwrap();
+        assert_eq!(expired, 1);
+        let rsvp = manager
+            .update_note("tenant_id".into(), rsvp.id, "second".to_owned(), None, key)
+            .await
+            .unwrap();
+        assert_eq!(rsvp.note, "second");
+    }
+
+    async fn make_reservation(pool: PgPool) -> (Reservation, ReservationManager) {
+        make_basic_reservation(
+            pool,
+            "tenant_id",
+            "user_id1",
+            "resource_id",
+            "2022-12-25T12:00:00-0700",
+            "2022-12-31T12:00:00-0700",
+            "Test note1",
+        )
+        .await
+    }
+    async fn make_basic_reservation(
+        pool: PgPool,
+        tid: &str,
+        uid: &str,
+        rid: &str,
+        start: &str,
+        end: &str,
+        note: &str,
+    ) -> (Reservation, ReservationManager) {
+        let manager = ReservationManager::new(pool.clone());
+        let rsvp = abi::Reservation::new_pending(
+            tid,
+            uid,
+            rid,
+            start.parse().unwrap(),
+            end.parse().unwrap(),
+            note,
+        );
+        (manager.reserve(rsvp, None).await.unwrap(), manager)
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn query_reservations_should_work() {
+        let (rsvp, manager) = make_reservation(migrated_pool.clone()).await;
+        let query = ReservationQuery::new(
+            "tenant_id",
+            "user_id1",
+            "",
+            "2022-12-01T12:00:00-0700".parse().unwrap(),
+            "2022-12-31T12:00:00-0700".parse().unwrap(),
+            ReservationStatus::Pending,
             1,
             10,
             false,
@@ -220,4 +1103,614 @@ mod tests {
         assert_eq!(rsvps.len(), 1);
         assert_eq!(rsvp, rsvps[0]);
     }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn same_resource_in_different_tenants_should_not_conflict() {
+        let (rsvp1, _) = make_reservation(migrated_pool.clone()).await;
+        let (rsvp2, _) = make_basic_reservation(
+            migrated_pool.clone(),
+            "other_tenant_id",
+            "user_id1",
+            "resource_id",
+            "2022-12-25T12:00:00-0700",
+            "2022-12-31T12:00:00-0700",
+            "Test note1",
+        )
+        .await;
+        assert_ne!(rsvp1.id, rsvp2.id);
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn reservation_of_other_tenant_should_be_invisible() {
+        let (rsvp, manager) = make_reservation(migrated_pool.clone()).await;
+        let err = manager
+            .get("other_tenant_id".into(), rsvp.id.clone())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, abi::Error::NotFound));
+        let err = manager
+            .delete("other_tenant_id".into(), rsvp.id.clone(), None, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, abi::Error::NotFound));
+
+        let query = ReservationQuery::new(
+            "other_tenant_id",
+            "user_id1",
+            "",
+            "2022-12-01T12:00:00-0700".parse().unwrap(),
+            "2022-12-31T12:00:00-0700".parse().unwrap(),
+            ReservationStatus::Pending,
+            1,
+            10,
+            false,
+        );
+        assert!(manager.query(query).await.unwrap().is_empty());
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn reserve_child_of_booked_parent_should_reject() {
+        let manager = ReservationManager::new(migrated_pool.clone());
+        manager
+            .link_resources("tenant_id".into(), "hall-ab".into(), "hall-a".into())
+            .await
+            .unwrap();
+        manager
+            .link_resources("tenant_id".into(), "hall-ab".into(), "hall-b".into())
+            .await
+            .unwrap();
+        make_basic_reservation(
+            migrated_pool.clone(),
+            "tenant_id",
+            "user_id1",
+            "hall-ab",
+            "2022-12-25T12:00:00-0700",
+            "2022-12-31T12:00:00-0700",
+            "Test note1",
+        )
+        .await;
+
+        let rsvp = abi::Reservation::new_pending(
+            "tenant_id",
+            "user_id2",
+            "hall-a",
+            "2022-12-26T12:00:00-0700".parse().unwrap(),
+            "2022-12-30T12:00:00-0700".parse().unwrap(),
+            "Test note2",
+        );
+        let err = manager.reserve(rsvp, None).await.unwrap_err();
+        if let abi::Error::ConflictReservation(ReservationConflictInfo::Parsed(info)) = err {
+            assert_eq!(info.new.rid, "hall-a");
+            assert_eq!(info.exist.rid, "hall-ab");
+        } else {
+            panic!("expect conflict reservation error");
+        }
+
+        // sibling resources don't block each other
+        let rsvp = abi::Reservation::new_pending(
+            "tenant_id",
+            "user_id2",
+            "hall-b",
+            "2023-01-01T12:00:00-0700".parse().unwrap(),
+            "2023-01-02T12:00:00-0700".parse().unwrap(),
+            "Test note2",
+        );
+        manager.reserve(rsvp, None).await.unwrap();
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn reserve_parent_of_booked_child_should_reject() {
+        let manager = ReservationManager::new(migrated_pool.clone());
+        manager
+            .link_resources("tenant_id".into(), "floor-1".into(), "hall-ab".into())
+            .await
+            .unwrap();
+        manager
+            .link_resources("tenant_id".into(), "hall-ab".into(), "hall-a".into())
+            .await
+            .unwrap();
+        make_basic_reservation(
+            migrated_pool.clone(),
+            "tenant_id",
+            "user_id1",
+            "hall-a",
+            "2022-12-25T12:00:00-0700",
+            "2022-12-31T12:00:00-0700",
+            "Test note1",
+        )
+        .await;
+
+        let rsvp = abi::Reservation::new_pending(
+            "tenant_id",
+            "user_id2",
+            "floor-1",
+            "2022-12-26T12:00:00-0700".parse().unwrap(),
+            "2022-12-30T12:00:00-0700".parse().unwrap(),
+            "Test note2",
+        );
+        let err = manager.reserve(rsvp, None).await.unwrap_err();
+        assert!(matches!(err, abi::Error::ConflictReservation(_)));
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn link_resources_cycle_should_reject() {
+        let manager = ReservationManager::new(migrated_pool.clone());
+        manager
+            .link_resources("tenant_id".into(), "hall-ab".into(), "hall-a".into())
+            .await
+            .unwrap();
+        let err = manager
+            .link_resources("tenant_id".into(), "hall-a".into(), "hall-ab".into())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, abi::Error::ResourceCycle { .. }));
+
+        manager
+            .unlink_resources("tenant_id".into(), "hall-ab".into(), "hall-a".into())
+            .await
+            .unwrap();
+        manager
+            .link_resources("tenant_id".into(), "hall-a".into(), "hall-ab".into())
+            .await
+            .unwrap();
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn reserve_multiple_resources_should_report_each_conflict() {
+        let manager = ReservationManager::new(migrated_pool.clone());
+        let mut rsvp = abi::Reservation::new_pending(
+            "tenant_id",
+            "user_id1",
+            "room-1",
+            "2022-12-25T12:00:00-0700".parse().unwrap(),
+            "2022-12-31T12:00:00-0700".parse().unwrap(),
+            "Test note1",
+        );
+        rsvp.extra_resource_ids = vec!["projector".into(), "vc-kit".into()];
+        let rsvp = manager.reserve(rsvp, None).await.unwrap();
+
+        // booked by any of its resources
+        let mut rsvp2 = abi::Reservation::new_pending(
+            "tenant_id",
+            "user_id2",
+            "room-2",
+            "2022-12-26T12:00:00-0700".parse().unwrap(),
+            "2022-12-30T12:00:00-0700".parse().unwrap(),
+            "Test note2",
+        );
+        rsvp2.extra_resource_ids = vec!["projector".into(), "vc-kit".into()];
+        let err = manager.reserve(rsvp2.clone(), None).await.unwrap_err();
+        if let abi::Error::ConflictReservations(infos) = err {
+            let rids: Vec<_> = infos
+                .into_iter()
+                .map(|info| match info {
+                    ReservationConflictInfo::Parsed(conflict) => conflict.exist.rid,
+                    ReservationConflictInfo::Unparsed(_) => panic!("should be parsed!"),
+                })
+                .collect();
+            assert_eq!(rids, vec!["projector", "vc-kit"]);
+        } else {
+            panic!("expect conflict reservations error");
+        }
+
+        let query = ReservationQuery::new(
+            "tenant_id",
+            "",
+            "vc-kit",
+            "2022-12-01T12:00:00-0700".parse().unwrap(),
+            "2022-12-31T12:00:00-0700".parse().unwrap(),
+            ReservationStatus::Pending,
+            1,
+            10,
+            false,
+        );
+        let rsvps = manager.query(query).await.unwrap();
+        assert_eq!(rsvps, vec![rsvp.clone()]);
+
+        // cancelling releases all of its resources at once
+        manager
+            .delete("tenant_id".into(), rsvp.id, None, None)
+            .await
+            .unwrap();
+        manager.reserve(rsvp2, None).await.unwrap();
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn reserve_duplicated_resources_should_reject() {
+        let manager = ReservationManager::new(migrated_pool.clone());
+        let mut rsvp = abi::Reservation::new_pending(
+            "tenant_id",
+            "user_id1",
+            "room-1",
+            "2022-12-25T12:00:00-0700".parse().unwrap(),
+            "2022-12-31T12:00:00-0700".parse().unwrap(),
+            "Test note1",
+        );
+        rsvp.extra_resource_ids = vec!["room-1".into()];
+        let err = manager.reserve(rsvp, None).await.unwrap_err();
+        assert!(matches!(err, abi::Error::InvalidResourceId(rid) if rid == "room-1"));
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn reserve_restricted_resource_should_await_approval() {
+        let manager = ReservationManager::new(migrated_pool.clone());
+        manager
+            .update_resource(abi::Resource::new("tenant_id", "boardroom", true))
+            .await
+            .unwrap();
+        let (rsvp, _) = make_basic_reservation(
+            migrated_pool.clone(),
+            "tenant_id",
+            "user_id1",
+            "boardroom",
+            "2022-12-25T12:00:00-0700",
+            "2022-12-31T12:00:00-0700",
+            "Test note1",
+        )
+        .await;
+        assert_eq!(rsvp.status, ReservationStatus::AwaitingApproval as i32);
+
+        // only approved reservations can be confirmed
+        let err = manager
+            .change_status("tenant_id".into(), rsvp.id.clone(), None, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, abi::Error::NotFound));
+
+        let rsvp = manager
+            .approve(
+                "tenant_id".into(),
+                rsvp.id,
+                "manager_id".into(),
+                "looks good".into(),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(rsvp.status, ReservationStatus::Pending as i32);
+        assert_eq!(rsvp.approver_id, "manager_id");
+        assert_eq!(rsvp.approval_reason, "looks good");
+
+        let rsvp = manager
+            .change_status("tenant_id".into(), rsvp.id, None, None)
+            .await
+            .unwrap();
+        assert_eq!(rsvp.status, ReservationStatus::Confirmed as i32);
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn reserve_with_lifecycle_status_should_reject() {
+        let manager = ReservationManager::new(migrated_pool.clone());
+        let mut rsvp = abi::Reservation::new_pending(
+            "tenant_id",
+            "user_id1",
+            "resource_id",
+            "2022-12-25T12:00:00-0700".parse().unwrap(),
+            "2022-12-31T12:00:00-0700".parse().unwrap(),
+            "Test note1",
+        );
+        // approval can't be skipped by asking for the status directly
+        rsvp.status = ReservationStatus::AwaitingApproval as i32;
+        let err = manager.reserve(rsvp, None).await.unwrap_err();
+        assert!(
+            matches!(err, abi::Error::InvalidStatus(s) if s == ReservationStatus::AwaitingApproval as i32)
+        );
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn reject_reservation_should_release_resource() {
+        let manager = ReservationManager::new(migrated_pool.clone());
+        manager
+            .update_resource(abi::Resource::new("tenant_id", "lab", true))
+            .await
+            .unwrap();
+        let (rsvp, _) = make_basic_reservation(
+            migrated_pool.clone(),
+            "tenant_id",
+            "user_id1",
+            "lab",
+            "2022-12-25T12:00:00-0700",
+            "2022-12-31T12:00:00-0700",
+            "Test note1",
+        )
+        .await;
+        let rsvp = manager
+            .reject(
+                "tenant_id".into(),
+                rsvp.id,
+                "manager_id".into(),
+                "lab is under maintenance".into(),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(rsvp.status, ReservationStatus::Rejected as i32);
+
+        let (rsvp2, _) = make_basic_reservation(
+            migrated_pool.clone(),
+            "tenant_id",
+            "user_id2",
+            "lab",
+            "2022-12-25T12:00:00-0700",
+            "2022-12-31T12:00:00-0700",
+            "Test note2",
+        )
+        .await;
+        assert_eq!(rsvp2.status, ReservationStatus::AwaitingApproval as i32);
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn check_in_should_only_work_around_start() {
+        let manager = ReservationManager::new(migrated_pool.clone());
+        let now = Utc::now();
+        let rsvp = abi::Reservation::new_pending(
+            "tenant_id",
+            "user_id1",
+            "room-1",
+            (now + Duration::minutes(5)).into(),
+            (now + Duration::hours(1)).into(),
+            "Test note1",
+        );
+        let rsvp = manager.reserve(rsvp, None).await.unwrap();
+        let rsvp = manager
+            .check_in("tenant_id".into(), rsvp.id, None, None)
+            .await
+            .unwrap();
+        assert!(rsvp.checked_in_at.is_some());
+
+        let (rsvp, _) = make_reservation(migrated_pool.clone()).await;
+        let err = manager
+            .check_in("tenant_id".into(), rsvp.id, None, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, abi::Error::CheckInNotAllowed));
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn release_no_shows_should_free_remaining_time() {
+        let manager = ReservationManager::new(migrated_pool.clone());
+        let now = Utc::now();
+        let rsvp = abi::Reservation::new_pending(
+            "tenant_id",
+            "user_id1",
+            "room-1",
+            (now - Duration::hours(1)).into(),
+            (now + Duration::hours(1)).into(),
+            "Test note1",
+        );
+        let rsvp = manager.reserve(rsvp, None).await.unwrap();
+
+        let released = manager.release_no_shows().await.unwrap();
+        assert_eq!(released.len(), 1);
+        assert_eq!(released[0].id, rsvp.id);
+        assert_eq!(released[0].status, ReservationStatus::NoShow as i32);
+        assert!(released[0].end.as_ref().unwrap().seconds < rsvp.end.as_ref().unwrap().seconds);
+        assert_eq!(
+            manager
+                .no_show_count("tenant_id".into(), "user_id1".into())
+                .await
+                .unwrap(),
+            1
+        );
+
+        // the rest of the window can be booked again
+        let rsvp = abi::Reservation::new_pending(
+            "tenant_id",
+            "user_id2",
+            "room-1",
+            (now + Duration::minutes(5)).into(),
+            (now + Duration::hours(1)).into(),
+            "Test note2",
+        );
+        manager.reserve(rsvp, None).await.unwrap();
+        assert!(manager.release_no_shows().await.unwrap().is_empty());
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn reserve_and_reschedule_should_store_price() {
+        let manager = ReservationManager::new(migrated_pool.clone());
+        let card = abi::RateCard {
+            hourly_rate: 1000,
+            peak_hourly_rate: 3000,
+            peak_periods: vec![abi::WeeklyPeriod {
+                weekdays: vec![1, 2, 3, 4, 5],
+                start_minute: 9 * 60,
+                end_minute: 17 * 60,
+            }],
+            minimum_charge: 0,
+            utc_offset_minutes: 0,
+        };
+        manager
+            .update_resource(abi::Resource::new("tenant_id", "studio", false).with_rate_card(card))
+            .await
+            .unwrap();
+
+        // 2022-12-26 is a monday, the projector has no rate card and is free
+        let mut rsvp = abi::Reservation::new_pending(
+            "tenant_id",
+            "user_id1",
+            "studio",
+            "2022-12-26T08:00:00Z".parse().unwrap(),
+            "2022-12-26T10:00:00Z".parse().unwrap(),
+            "Test note1",
+        );
+        rsvp.extra_resource_ids = vec!["projector".into()];
+        assert_eq!(manager.quote(rsvp.clone()).await.unwrap(), 4000);
+        let rsvp = manager.reserve(rsvp, None).await.unwrap();
+        assert_eq!(rsvp.price, 4000);
+
+        let rsvp = manager
+            .reschedule(
+                "tenant_id".into(),
+                rsvp.id,
+                "2022-12-26T18:00:00Z".parse().unwrap(),
+                "2022-12-26T20:00:00Z".parse().unwrap(),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(rsvp.price, 2000);
+        assert_eq!(
+            manager
+                .get("tenant_id".into(), rsvp.id.clone())
+                .await
+                .unwrap(),
+            rsvp
+        );
+
+        // a card that can't be read is reported as such
+        sqlx::query("UPDATE rsvp.resources SET rate_card = '\\xff' WHERE id = 'studio'")
+            .execute(&migrated_pool)
+            .await
+            .unwrap();
+        let err = manager.quote(rsvp).await.unwrap_err();
+        assert!(matches!(err, abi::Error::InvalidRateCard(_)), "{:?}", err);
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn export_and_import_ics_should_work() {
+        let (rsvp, manager) = make_reservation(migrated_pool.clone()).await;
+        let query = ReservationQuery::new(
+            "tenant_id",
+            "user_id1",
+            "",
+            "2022-12-01T12:00:00-0700".parse().unwrap(),
+            "2022-12-31T12:00:00-0700".parse().unwrap(),
+            ReservationStatus::Pending,
+            1,
+            10,
+            false,
+        );
+        let ics = crate::export_ics(&manager, query).await.unwrap();
+        assert!(ics.contains(&format!("UID:{}", rsvp.id)));
+
+        // the exported event is still booked in its own tenant
+        let imported = crate::import_ics(&manager, "tenant_id".into(), "user_id2".into(), &ics)
+            .await
+            .unwrap();
+        assert!(imported.reserved.is_empty());
+        assert_eq!(imported.conflicted.len(), 1);
+        assert_eq!(imported.conflicted[0].0, rsvp.id);
+
+        let imported =
+            crate::import_ics(&manager, "other_tenant_id".into(), "user_id2".into(), &ics)
+                .await
+                .unwrap();
+        assert_eq!(imported.reserved.len(), 1);
+        assert_eq!(imported.reserved[0].start, rsvp.start);
+        assert_eq!(imported.reserved[0].note, rsvp.note);
+
+        // importing again replays the first import
+        let again = crate::import_ics(&manager, "other_tenant_id".into(), "user_id2".into(), &ics)
+            .await
+            .unwrap();
+        assert_eq!(again.reserved, imported.reserved);
+
+        // another user gets a booking of their own, which conflicts
+        let other = crate::import_ics(&manager, "other_tenant_id".into(), "user_id3".into(), &ics)
+            .await
+            .unwrap();
+        assert!(other.reserved.is_empty());
+        assert_eq!(other.conflicted.len(), 1);
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn feed_token_should_be_stable_until_reset() {
+        let manager = ReservationManager::new(migrated_pool.clone());
+        let token = manager
+            .feed_token("tenant_id".into(), "user_id1".into())
+            .await
+            .unwrap();
+        assert_eq!(token.len(), 64);
+        assert_eq!(
+            manager
+                .feed_token("tenant_id".into(), "user_id1".into())
+                .await
+                .unwrap(),
+            token
+        );
+        assert_eq!(
+            manager.feed_owner(token.clone()).await.unwrap(),
+            ("tenant_id".to_string(), "user_id1".to_string())
+        );
+
+        let new_token = manager
+            .reset_feed_token("tenant_id".into(), "user_id1".into())
+            .await
+            .unwrap();
+        assert_ne!(new_token, token);
+        assert!(matches!(
+            manager.feed_owner(token).await,
+            Err(abi::Error::NotFound)
+        ));
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn bulk_reserve_dry_run_should_report_conflicts_without_writing() {
+        let (_, manager) = make_reservation(migrated_pool.clone()).await;
+        let make = |rid: &str, start: &str, end: &str| {
+            abi::Reservation::new_pending(
+                "tenant_id",
+                "user_id2",
+                rid,
+                start.parse().unwrap(),
+                end.parse().unwrap(),
+                "imported",
+            )
+        };
+        let rsvps = vec![
+            // conflicts with the existing reservation
+            make(
+                "resource_id",
+                "2022-12-26T12:00:00-0700",
+                "2022-12-27T12:00:00-0700",
+            ),
+            make(
+                "room-1",
+                "2023-01-02T09:00:00-0700",
+                "2023-01-02T10:00:00-0700",
+            ),
+            // conflicts with the row above
+            make(
+                "room-1",
+                "2023-01-02T09:30:00-0700",
+                "2023-01-02T11:00:00-0700",
+            ),
+            make("", "2023-01-02T09:00:00-0700", "2023-01-02T10:00:00-0700"),
+        ];
+
+        let result = manager.bulk_reserve(rsvps.clone(), true).await.unwrap();
+        assert_eq!(result.reserved.len(), 1);
+        assert!(result.reserved[0].id.is_empty());
+        let rows: Vec<_> = result.rejected.iter().map(|(row, _)| *row).collect();
+        assert_eq!(rows, vec![0, 2, 3]);
+        assert!(matches!(
+            result.rejected[1].1,
+            abi::Error::ConflictReservation(_)
+        ));
+        assert!(matches!(
+            result.rejected[2].1,
+            abi::Error::InvalidResourceId(_)
+        ));
+        let count: i64 = sqlx::query("SELECT count(*) FROM rsvp.reservations")
+            .fetch_one(&migrated_pool)
+            .await
+            .unwrap()
+            .get(0);
+        assert_eq!(count, 1);
+
+        let result = manager.bulk_reserve(rsvps, false).await.unwrap();
+        assert_eq!(result.rejected.len(), 3);
+        let rsvp = &result.reserved[0];
+        assert_eq!(
+            &manager
+                .get("tenant_id".into(), rsvp.id.clone())
+                .await
+                .unwrap(),
+            rsvp
+        );
+    }
 }
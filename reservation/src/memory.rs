@@ -0,0 +1,1001 @@
+use crate::{
+    fingerprint, validate_key, version_part, BulkReserve, CheckInPolicy, IdempotencyKey,
+    ReservationId, ResourceId, Rsvp, TenantId, UserId,
+};
+use abi::{
+    convert_to_timestamp, convert_to_utc_time, split_words, AggregateGroup, ReservationConflict,
+    ReservationConflictInfo, ReservationStatus, ReservationWindow, TimeMatch,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use prost::Message;
+use sqlx::types::Uuid;
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
+
+/// `Rsvp` kept in process memory with the same rules as `ReservationManager`,
+/// for tests and embedded use. clones share the same reservations
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryRsvp {
+    state: Arc<Mutex<State>>,
+    check_in: CheckInPolicy,
+}
+
+#[derive(Debug, Clone, Default)]
+struct State {
+    reservations: HashMap<Uuid, abi::Reservation>,
+    // (tenant_id, parent_id, child_id)
+    relations: HashSet<(TenantId, ResourceId, ResourceId)>,
+    resources: HashMap<(TenantId, ResourceId), abi::Resource>,
+    idempotency_keys: HashMap<(TenantId, IdempotencyKey), StoredResponse>,
+    no_shows: HashMap<(TenantId, UserId), i64>,
+    feed_tokens: HashMap<(TenantId, UserId), String>,
+}
+
+/// the op, the request fingerprint and the result stored for an idempotency key
+#[derive(Debug, Clone)]
+struct StoredResponse {
+    op: &'static str,
+    request: Vec<u8>,
+    rsvp: abi::Reservation,
+    created_at: DateTime<Utc>,
+}
+
+#[async_trait]
+impl Rsvp for InMemoryRsvp {
+    async fn reserve(
+        &self,
+        rsvp: abi::Reservation,
+        key: Option<IdempotencyKey>,
+    ) -> Result<abi::Reservation, abi::Error> {
+        rsvp.validate()?;
+
+        let request = fingerprint(&[&rsvp.encode_to_vec()]);
+        let mut state = self.state.lock().unwrap();
+        let tenant_id = rsvp.tenant_id.clone();
+        if let Some(rsvp) = state.replay(&tenant_id, key.as_deref(), "reserve", &request)? {
+            return Ok(rsvp);
+        }
+
+        let rsvp = state.insert(rsvp)?;
+        state.save_response(&tenant_id, key, "reserve", request, &rsvp);
+        Ok(rsvp)
+    }
+
+    async fn bulk_reserve(
+        &self,
+        rsvps: Vec<abi::Reservation>,
+        dry_run: bool,
+    ) -> Result<BulkReserve, abi::Error> {
+        let mut result = BulkReserve::default();
+        let mut state = self.state.lock().unwrap();
+        // earlier rows are visible to later ones, a dry run works on a copy that's thrown away
+        let mut scratch = state.clone();
+        for (row, rsvp) in rsvps.into_iter().enumerate() {
+            if let Err(e) = rsvp.validate().and_then(|_| rsvp.requested_status()) {
+                result.rejected.push((row, e));
+                continue;
+            }
+            // only conflicts skip a row, anything else fails the whole batch
+            match scratch.insert(rsvp) {
+                Ok(rsvp) => result.reserved.push(rsvp),
+                Err(
+                    e @ (abi::Error::ConflictReservation(_) | abi::Error::ConflictReservations(_)),
+                ) => result.rejected.push((row, e)),
+                Err(e) => return Err(e),
+            }
+        }
+
+        if dry_run {
+            for rsvp in &mut result.reserved {
+                rsvp.id.clear();
+                rsvp.version = 0;
+            }
+        } else {
+            *state = scratch;
+        }
+        Ok(result)
+    }
+
+    async fn change_status(
+        &self,
+        tenant_id: TenantId,
+        id: ReservationId,
+        expected_version: Option<i64>,
+        key: Option<IdempotencyKey>,
+    ) -> Result<abi::Reservation, abi::Error> {
+        let request = fingerprint(&[id.as_bytes(), &version_part(expected_version)]);
+        self.update(
+            tenant_id,
+            id,
+            expected_version,
+            key,
+            "confirm",
+            request,
+            |rsvp| {
+                if rsvp.status != ReservationStatus::Pending as i32 {
+                    return Err(abi::Error::NotFound);
+                }
+                rsvp.status = ReservationStatus::Confirmed as i32;
+                Ok(())
+            },
+        )
+    }
+
+    async fn update_note(
+        &self,
+        tenant_id: TenantId,
+        id: ReservationId,
+        note: String,
+        expected_version: Option<i64>,
+        key: Option<IdempotencyKey>,
+    ) -> Result<abi::Reservation, abi::Error> {
+        let request = fingerprint(&[
+            id.as_bytes(),
+            note.as_bytes(),
+            &version_part(expected_version),
+        ]);
+        self.update(
+            tenant_id,
+            id,
+            expected_version,
+            key,
+            "update",
+            request,
+            |rsvp| {
+                rsvp.note = note;
+                Ok(())
+            },
+        )
+    }
+
+    async fn delete(
+        &self,
+        tenant_id: TenantId,
+        id: ReservationId,
+        expected_version: Option<i64>,
+        key: Option<IdempotencyKey>,
+    ) -> Result<abi::Reservation, abi::Error> {
+        let request = fingerprint(&[id.as_bytes(), &version_part(expected_version)]);
+        let id = parse_id(id)?;
+
+        let mut state = self.state.lock().unwrap();
+        if let Some(rsvp) = state.replay(&tenant_id, key.as_deref(), "cancel", &request)? {
+            return Ok(rsvp);
+        }
+        state.check_version(&tenant_id, id, expected_version)?;
+        state.find(&tenant_id, id)?;
+
+        let rsvp = state.reservations.remove(&id).ok_or(abi::Error::NotFound)?;
+        state.save_response(&tenant_id, key, "cancel", request, &rsvp);
+        Ok(rsvp)
+    }
+
+    async fn get(
+        &self,
+        tenant_id: TenantId,
+        id: ReservationId,
+    ) -> Result<abi::Reservation, abi::Error> {
+        let id = parse_id(id)?;
+        let state = self.state.lock().unwrap();
+        state.find(&tenant_id, id).cloned()
+    }
+
+    async fn query(
+        &self,
+        query: abi::ReservationQuery,
+    ) -> Result<Vec<abi::Reservation>, abi::Error> {
+        query.validate()?;
+        let terms = query.get_search_terms();
+        let cursor = query.get_cursor()?.map(|c| (c.start, c.id.to_string()));
+
+        let state = self.state.lock().unwrap();
+        let mut rsvps: Vec<&abi::Reservation> = state
+            .matching(&query)
+            .into_iter()
+            .filter(|rsvp| {
+                let key = (window(rsvp).0, rsvp.id.clone());
+                match &cursor {
+                    Some(cursor) if query.desc => key < *cursor,
+                    Some(cursor) => key > *cursor,
+                    None => true,
+                }
+            })
+            .collect();
+        rsvps.sort_by_key(|rsvp| (window(rsvp).0, rsvp.id.clone()));
+        if query.desc {
+            rsvps.reverse();
+        }
+        if query.by_relevance && !terms.is_empty() {
+            rsvps.sort_by_key(|rsvp| Reverse(relevance(&terms, rsvp)));
+        }
+
+        Ok(rsvps
+            .into_iter()
+            .skip(query.offset() as usize)
+            .take(query.page_size.max(0) as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn aggregate(
+        &self,
+        query: abi::ReservationQuery,
+        group_by: AggregateGroup,
+        utc_offset_minutes: i32,
+    ) -> Result<Vec<abi::Aggregate>, abi::Error> {
+        query.validate()?;
+        let utc_offset = abi::utc_offset(utc_offset_minutes)?;
+        let (start, end) = query.get_window();
+
+        let state = self.state.lock().unwrap();
+        // (count, microseconds) of each key
+        let mut groups: BTreeMap<String, (i64, i64)> = BTreeMap::new();
+        if group_by == AggregateGroup::None {
+            groups.insert(String::new(), (0, 0));
+        }
+        for rsvp in state.matching(&query) {
+            let (rsvp_start, rsvp_end) = window(rsvp);
+            let from = start.map_or(rsvp_start, |start| start.max(rsvp_start));
+            let to = end.map_or(rsvp_end, |end| end.min(rsvp_end));
+            let micros = (to - from).num_microseconds().unwrap_or(0).max(0);
+            let keys = match group_by {
+                AggregateGroup::None => vec![String::new()],
+                // a query for one resource only counts that one
+                AggregateGroup::Resource => occupies(rsvp)
+                    .filter(|rid| query.resource_id.is_empty() || *rid == query.resource_id)
+                    .map(String::from)
+                    .collect(),
+                AggregateGroup::User => vec![rsvp.user_id.clone()],
+                AggregateGroup::Status => vec![ReservationStatus::from_i32(rsvp.status)
+                    .unwrap_or(ReservationStatus::Unknown)
+                    .to_string()],
+                AggregateGroup::Day => vec![rsvp_start
+                    .with_timezone(&utc_offset)
+                    .format("%Y-%m-%d")
+                    .to_string()],
+            };
+            for key in keys {
+                let group = groups.entry(key).or_default();
+                group.0 += 1;
+                group.1 += micros;
+            }
+        }
+        Ok(groups
+            .into_iter()
+            .map(|(key, (count, micros))| abi::Aggregate::new(key, count, micros / 1_000_000))
+            .collect())
+    }
+
+    async fn link_resources(
+        &self,
+        tenant_id: TenantId,
+        parent_id: ResourceId,
+        child_id: ResourceId,
+    ) -> Result<(), abi::Error> {
+        if parent_id.is_empty() {
+            return Err(abi::Error::InvalidResourceId(parent_id));
+        }
+        if child_id.is_empty() {
+            return Err(abi::Error::InvalidResourceId(child_id));
+        }
+
+        let mut state = self.state.lock().unwrap();
+        // parent must not be child itself or one of its descendants
+        if child_id == parent_id
+            || state
+                .descendants(&tenant_id, &child_id)
+                .contains(&parent_id)
+        {
+            return Err(abi::Error::ResourceCycle {
+                parent: parent_id,
+                child: child_id,
+            });
+        }
+        state.relations.insert((tenant_id, parent_id, child_id));
+        Ok(())
+    }
+
+    async fn unlink_resources(
+        &self,
+        tenant_id: TenantId,
+        parent_id: ResourceId,
+        child_id: ResourceId,
+    ) -> Result<(), abi::Error> {
+        let mut state = self.state.lock().unwrap();
+        if !state.relations.remove(&(tenant_id, parent_id, child_id)) {
+            return Err(abi::Error::NotFound);
+        }
+        Ok(())
+    }
+
+    async fn update_resource(&self, resource: abi::Resource) -> Result<abi::Resource, abi::Error> {
+        resource.validate()?;
+        let mut state = self.state.lock().unwrap();
+        state.resources.insert(
+            (resource.tenant_id.clone(), resource.id.clone()),
+            resource.clone(),
+        );
+        Ok(resource)
+    }
+
+    async fn approve(
+        &self,
+        tenant_id: TenantId,
+        id: ReservationId,
+        approver_id: UserId,
+        reason: String,
+        expected_version: Option<i64>,
+        key: Option<IdempotencyKey>,
+    ) -> Result<abi::Reservation, abi::Error> {
+        self.review(
+            tenant_id,
+            id,
+            ReservationStatus::Pending,
+            approver_id,
+            reason,
+            expected_version,
+            key,
+        )
+    }
+
+    async fn reject(
+        &self,
+        tenant_id: TenantId,
+        id: ReservationId,
+        approver_id: UserId,
+        reason: String,
+        expected_version: Option<i64>,
+        key: Option<IdempotencyKey>,
+    ) -> Result<abi::Reservation, abi::Error> {
+        self.review(
+            tenant_id,
+            id,
+            ReservationStatus::Rejected,
+            approver_id,
+            reason,
+            expected_version,
+            key,
+        )
+    }
+
+    async fn check_in(
+        &self,
+        tenant_id: TenantId,
+        id: ReservationId,
+        expected_version: Option<i64>,
+        key: Option<IdempotencyKey>,
+    ) -> Result<abi::Reservation, abi::Error> {
+        let policy = self.check_in;
+        let request = fingerprint(&[id.as_bytes(), &version_part(expected_version)]);
+        self.update(
+            tenant_id,
+            id,
+            expected_version,
+            key,
+            "check_in",
+            request,
+            |rsvp| {
+                let now = Utc::now();
+                let start = window(rsvp).0;
+                if !is_active(rsvp) || now < start - policy.before || now > start + policy.after {
+                    return Err(abi::Error::CheckInNotAllowed);
+                }
+                if rsvp.checked_in_at.is_none() {
+                    rsvp.checked_in_at = Some(convert_to_timestamp(now));
+                }
+                Ok(())
+            },
+        )
+    }
+
+    async fn release_no_shows(&self) -> Result<Vec<abi::Reservation>, abi::Error> {
+        let now = Utc::now();
+        let mut state = self.state.lock().unwrap();
+        let mut released = vec![];
+        for rsvp in state.reservations.values_mut() {
+            let (start, end) = window(rsvp);
+            let closed = start + self.check_in.after;
+            if rsvp.checked_in_at.is_some() || !is_active(rsvp) || closed >= now {
+                continue;
+            }
+            rsvp.status = ReservationStatus::NoShow as i32;
+            rsvp.end = Some(convert_to_timestamp(end.min(now)));
+            rsvp.version += 1;
+            released.push(rsvp.clone());
+        }
+        for rsvp in &released {
+            *state
+                .no_shows
+                .entry((rsvp.tenant_id.clone(), rsvp.user_id.clone()))
+                .or_default() += 1;
+        }
+        Ok(released)
+    }
+
+    async fn no_show_count(&self, tenant_id: TenantId, user_id: UserId) -> Result<i64, abi::Error> {
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .no_shows
+            .get(&(tenant_id, user_id))
+            .copied()
+            .unwrap_or(0))
+    }
+
+    async fn expire_idempotency_keys(&self, before: DateTime<Utc>) -> Result<u64, abi::Error> {
+        let mut state = self.state.lock().unwrap();
+        let count = state.idempotency_keys.len();
+        state
+            .idempotency_keys
+            .retain(|_, stored| stored.created_at >= before);
+        Ok((count - state.idempotency_keys.len()) as u64)
+    }
+
+    async fn quote(&self, rsvp: abi::Reservation) -> Result<i64, abi::Error> {
+        rsvp.validate()?;
+        let state = self.state.lock().unwrap();
+        state.price(&rsvp)
+    }
+
+    async fn feed_token(&self, tenant_id: TenantId, user_id: UserId) -> Result<String, abi::Error> {
+        if user_id.is_empty() {
+            return Err(abi::Error::InvalidUserId(user_id));
+        }
+        let mut state = self.state.lock().unwrap();
+        Ok(state
+            .feed_tokens
+            .entry((tenant_id, user_id))
+            .or_insert_with(new_token)
+            .clone())
+    }
+
+    async fn reset_feed_token(
+        &self,
+        tenant_id: TenantId,
+        user_id: UserId,
+    ) -> Result<String, abi::Error> {
+        if user_id.is_empty() {
+            return Err(abi::Error::InvalidUserId(user_id));
+        }
+        let mut state = self.state.lock().unwrap();
+        let token = new_token();
+        state
+            .feed_tokens
+            .insert((tenant_id, user_id), token.clone());
+        Ok(token)
+    }
+
+    async fn feed_owner(&self, token: String) -> Result<(TenantId, UserId), abi::Error> {
+        let state = self.state.lock().unwrap();
+        state
+            .feed_tokens
+            .iter()
+            .find(|(_, t)| **t == token)
+            .map(|(owner, _)| owner.clone())
+            .ok_or(abi::Error::NotFound)
+    }
+
+    async fn reschedule(
+        &self,
+        tenant_id: TenantId,
+        id: ReservationId,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        expected_version: Option<i64>,
+        key: Option<IdempotencyKey>,
+    ) -> Result<abi::Reservation, abi::Error> {
+        let request = fingerprint(&[
+            id.as_bytes(),
+            start.to_rfc3339().as_bytes(),
+            end.to_rfc3339().as_bytes(),
+            &version_part(expected_version),
+        ]);
+        let id = parse_id(id)?;
+        if start >= end {
+            return Err(abi::Error::InvalidTime);
+        }
+
+        let mut state = self.state.lock().unwrap();
+        if let Some(rsvp) = state.replay(&tenant_id, key.as_deref(), "reschedule", &request)? {
+            return Ok(rsvp);
+        }
+        state.check_version(&tenant_id, id, expected_version)?;
+
+        // rejected and no-show reservations are over, they can't be moved
+        let mut rsvp = state.find(&tenant_id, id)?.clone();
+        if !is_active(&rsvp) && rsvp.status != ReservationStatus::AwaitingApproval as i32 {
+            return Err(abi::Error::NotFound);
+        }
+        rsvp.start = Some(convert_to_timestamp(start));
+        rsvp.end = Some(convert_to_timestamp(end));
+        rsvp.price = state.price(&rsvp)?;
+        state.check_conflicts(id, &rsvp)?;
+        rsvp.version += 1;
+        state.reservations.insert(id, rsvp.clone());
+
+        state.save_response(&tenant_id, key, "reschedule", request, &rsvp);
+        Ok(rsvp)
+    }
+}
+
+impl InMemoryRsvp {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_check_in_policy(mut self, policy: CheckInPolicy) -> Self {
+        self.check_in = policy;
+        self
+    }
+
+    /// apply f to the reservation as one op, the reservation is left untouched if f fails
+    #[allow(clippy::too_many_arguments)]
+    fn update(
+        &self,
+        tenant_id: TenantId,
+        id: ReservationId,
+        expected_version: Option<i64>,
+        key: Option<IdempotencyKey>,
+        op: &'static str,
+        request: Vec<u8>,
+        f: impl FnOnce(&mut abi::Reservation) -> Result<(), abi::Error>,
+    ) -> Result<abi::Reservation, abi::Error> {
+        let id = parse_id(id)?;
+
+        let mut state = self.state.lock().unwrap();
+        if let Some(rsvp) = state.replay(&tenant_id, key.as_deref(), op, &request)? {
+            return Ok(rsvp);
+        }
+        state.check_version(&tenant_id, id, expected_version)?;
+
+        let mut rsvp = state.find(&tenant_id, id)?.clone();
+        f(&mut rsvp)?;
+        rsvp.version += 1;
+        state.reservations.insert(id, rsvp.clone());
+
+        state.save_response(&tenant_id, key, op, request, &rsvp);
+        Ok(rsvp)
+    }
+
+    /// move a reservation awaiting approval to the given status, recording the approver
+    #[allow(clippy::too_many_arguments)]
+    fn review(
+        &self,
+        tenant_id: TenantId,
+        id: ReservationId,
+        status: ReservationStatus,
+        approver_id: UserId,
+        reason: String,
+        expected_version: Option<i64>,
+        key: Option<IdempotencyKey>,
+    ) -> Result<abi::Reservation, abi::Error> {
+        // validated before the id is looked up, like the manager does
+        parse_id(id.clone())?;
+        if approver_id.is_empty() {
+            return Err(abi::Error::InvalidUserId(approver_id));
+        }
+
+        let op = match status {
+            ReservationStatus::Rejected => "reject",
+            _ => "approve",
+        };
+        let request = fingerprint(&[
+            id.as_bytes(),
+            approver_id.as_bytes(),
+            reason.as_bytes(),
+            &version_part(expected_version),
+        ]);
+        self.update(tenant_id, id, expected_version, key, op, request, |rsvp| {
+            if rsvp.status != ReservationStatus::AwaitingApproval as i32 {
+                return Err(abi::Error::NotFound);
+            }
+            rsvp.status = status as i32;
+            rsvp.approver_id = approver_id;
+            rsvp.approval_reason = reason;
+            Ok(())
+        })
+    }
+}
+
+impl State {
+    /// insert a validated reservation, it awaits approval if any of its resources requires it
+    fn insert(&mut self, mut rsvp: abi::Reservation) -> Result<abi::Reservation, abi::Error> {
+        let mut status = rsvp.requested_status()?;
+        let requires_approval = occupies(&rsvp).any(|rid| {
+            self.resources
+                .get(&(rsvp.tenant_id.clone(), rid.to_string()))
+                .is_some_and(|resource| resource.requires_approval)
+        });
+        if requires_approval {
+            status = ReservationStatus::AwaitingApproval;
+        }
+        rsvp.status = status as i32;
+        rsvp.price = self.price(&rsvp)?;
+
+        let id = Uuid::new_v4();
+        self.check_conflicts(id, &rsvp)?;
+        rsvp.id = id.to_string();
+        rsvp.version = 1;
+        self.reservations.insert(id, rsvp.clone());
+        Ok(rsvp)
+    }
+
+    /// every resource of rsvp conflicts with an overlapping reservation of the same resource
+    /// or one of its ancestors and descendants, each conflicting resource is reported once
+    fn check_conflicts(&self, id: Uuid, rsvp: &abi::Reservation) -> Result<(), abi::Error> {
+        // rejected reservations don't hold their resources
+        if rsvp.status == ReservationStatus::Rejected as i32 {
+            return Ok(());
+        }
+        let (start, end) = window(rsvp);
+        let mut infos: Vec<ReservationConflictInfo> = occupies(rsvp)
+            .filter_map(|rid| {
+                let mut related = self.related(&rsvp.tenant_id, rid);
+                related.insert(rid.to_string());
+                self.reservations
+                    .iter()
+                    .filter(|(other_id, other)| {
+                        **other_id != id
+                            && other.tenant_id == rsvp.tenant_id
+                            && other.status != ReservationStatus::Rejected as i32
+                    })
+                    .flat_map(|(_, other)| occupies(other).map(move |other_rid| (other, other_rid)))
+                    .find(|(other, other_rid)| {
+                        let (other_start, other_end) = window(other);
+                        related.contains(*other_rid) && other_start < end && start < other_end
+                    })
+                    .map(|(other, other_rid)| {
+                        let (other_start, other_end) = window(other);
+                        ReservationConflictInfo::Parsed(ReservationConflict {
+                            new: ReservationWindow {
+                                rid: rid.to_string(),
+                                start,
+                                end,
+                            },
+                            exist: ReservationWindow {
+                                rid: other_rid.to_string(),
+                                start: other_start,
+                                end: other_end,
+                            },
+                        })
+                    })
+            })
+            .collect();
+
+        match infos.len() {
+            0 => Ok(()),
+            1 => Err(abi::Error::ConflictReservation(infos.pop().unwrap())),
+            _ => Err(abi::Error::ConflictReservations(infos)),
+        }
+    }
+
+    /// all ancestors and descendants of the resource
+    fn related(&self, tenant_id: &str, rid: &str) -> HashSet<String> {
+        let mut related = self.descendants(tenant_id, rid);
+        let mut stack = vec![rid.to_string()];
+        while let Some(id) = stack.pop() {
+            for (tid, parent, child) in &self.relations {
+                if tid == tenant_id && *child == id && related.insert(parent.clone()) {
+                    stack.push(parent.clone());
+                }
+            }
+        }
+        related.remove(rid);
+        related
+    }
+
+    fn descendants(&self, tenant_id: &str, rid: &str) -> HashSet<String> {
+        let mut descendants = HashSet::new();
+        let mut stack = vec![rid.to_string()];
+        while let Some(id) = stack.pop() {
+            for (tid, parent, child) in &self.relations {
+                if tid == tenant_id && *parent == id && descendants.insert(child.clone()) {
+                    stack.push(child.clone());
+                }
+            }
+        }
+        descendants
+    }
+
+    /// sum of the quotes of every resource of the reservation, resources without a rate card are free
+    fn price(&self, rsvp: &abi::Reservation) -> Result<i64, abi::Error> {
+        let (start, end) = window(rsvp);
+        let cards = occupies(rsvp).filter_map(|rid| {
+            self.resources
+                .get(&(rsvp.tenant_id.clone(), rid.to_string()))
+                .and_then(|resource| resource.rate_card.as_ref())
+        });
+        abi::RateCard::quote_all(cards, start, end)
+    }
+
+    /// reservations the query finds, in no particular order and without the cursor
+    fn matching(&self, query: &abi::ReservationQuery) -> Vec<&abi::Reservation> {
+        let statuses = query.get_statuses();
+        let time_match = TimeMatch::from_i32(query.time_match).unwrap_or(TimeMatch::ContainedIn);
+        let query_window = query.get_window();
+        let terms = query.get_search_terms();
+        self.reservations
+            .values()
+            .filter(|rsvp| {
+                rsvp.tenant_id == query.tenant_id
+                    && matches_window(time_match, query_window, window(rsvp))
+                    && (statuses.is_empty()
+                        || statuses.iter().any(|status| *status as i32 == rsvp.status))
+                    && (query.user_id.is_empty() || rsvp.user_id == query.user_id)
+                    && relevance(&terms, rsvp).is_some()
+                    && (query.resource_id.is_empty()
+                        || (rsvp.status != ReservationStatus::Rejected as i32
+                            && occupies(rsvp).any(|rid| rid == query.resource_id)))
+            })
+            .collect()
+    }
+
+    fn find(&self, tenant_id: &str, id: Uuid) -> Result<&abi::Reservation, abi::Error> {
+        self.reservations
+            .get(&id)
+            .filter(|rsvp| rsvp.tenant_id == tenant_id)
+            .ok_or(abi::Error::NotFound)
+    }
+
+    /// make sure the reservation is still at the expected version
+    fn check_version(
+        &self,
+        tenant_id: &str,
+        id: Uuid,
+        expected: Option<i64>,
+    ) -> Result<(), abi::Error> {
+        let expected = match expected {
+            Some(expected) => expected,
+            None => return Ok(()),
+        };
+        let actual = self.find(tenant_id, id)?.version;
+        if actual != expected {
+            return Err(abi::Error::PreconditionFailed { expected, actual });
+        }
+        Ok(())
+    }
+
+    /// the result stored by an earlier call of op with the same key and request
+    fn replay(
+        &self,
+        tenant_id: &str,
+        key: Option<&str>,
+        op: &str,
+        request: &[u8],
+    ) -> Result<Option<abi::Reservation>, abi::Error> {
+        let key = match key {
+            Some(key) => key,
+            None => return Ok(None),
+        };
+        validate_key(key)?;
+        match self
+            .idempotency_keys
+            .get(&(tenant_id.to_string(), key.to_string()))
+        {
+            None => Ok(None),
+            Some(stored) if stored.op == op && stored.request == request => {
+                Ok(Some(stored.rsvp.clone()))
+            }
+            Some(_) => Err(abi::Error::IdempotencyKeyReused(key.to_string())),
+        }
+    }
+
+    fn save_response(
+        &mut self,
+        tenant_id: &str,
+        key: Option<IdempotencyKey>,
+        op: &'static str,
+        request: Vec<u8>,
+        rsvp: &abi::Reservation,
+    ) {
+        if let Some(key) = key {
+            self.idempotency_keys.insert(
+                (tenant_id.to_string(), key),
+                StoredResponse {
+                    op,
+                    request,
+                    rsvp: rsvp.clone(),
+                    created_at: Utc::now(),
+                },
+            );
+        }
+    }
+}
+
+fn parse_id(id: ReservationId) -> Result<Uuid, abi::Error> {
+    Uuid::parse_str(&id).map_err(|_| abi::Error::InvalidReservationId(id))
+}
+
+fn window(rsvp: &abi::Reservation) -> (DateTime<Utc>, DateTime<Utc>) {
+    (
+        convert_to_utc_time(rsvp.start.clone().unwrap()),
+        convert_to_utc_time(rsvp.end.clone().unwrap()),
+    )
+}
+
+/// whether the window of a reservation matches the window of a query, open where it's none
+fn matches_window(
+    time_match: TimeMatch,
+    (start, end): (Option<DateTime<Utc>>, Option<DateTime<Utc>>),
+    (rsvp_start, rsvp_end): (DateTime<Utc>, DateTime<Utc>),
+) -> bool {
+    let starts_after = |t: DateTime<Utc>| start.iter().all(|start| *start <= t);
+    let ends_before = |t: DateTime<Utc>| end.iter().all(|end| t <= *end);
+    match time_match {
+        TimeMatch::ContainedIn => starts_after(rsvp_start) && ends_before(rsvp_end),
+        TimeMatch::Overlaps => {
+            start.iter().all(|start| *start < rsvp_end) && end.iter().all(|end| rsvp_start < *end)
+        }
+        TimeMatch::Contains => matches!(
+            (start, end),
+            (Some(start), Some(end)) if rsvp_start <= start && end <= rsvp_end
+        ),
+        TimeMatch::StartsWithin => {
+            starts_after(rsvp_start) && end.iter().all(|end| rsvp_start < *end)
+        }
+    }
+}
+
+/// how many words of the note are searched for, none if it lacks any of them
+fn relevance(terms: &[String], rsvp: &abi::Reservation) -> Option<usize> {
+    let words: Vec<String> = split_words(&rsvp.note).collect();
+    terms
+        .iter()
+        .all(|term| words.contains(term))
+        .then(|| words.iter().filter(|word| terms.contains(word)).count())
+}
+
+/// resource_id followed by the extra resources
+fn occupies(rsvp: &abi::Reservation) -> impl Iterator<Item = &str> {
+    std::iter::once(rsvp.resource_id.as_str())
+        .chain(rsvp.extra_resource_ids.iter().map(String::as_str))
+}
+
+fn is_active(rsvp: &abi::Reservation) -> bool {
+    rsvp.status == ReservationStatus::Pending as i32
+        || rsvp.status == ReservationStatus::Confirmed as i32
+}
+
+fn new_token() -> String {
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use abi::ReservationQuery;
+
+    fn rsvp(uid: &str, rid: &str, start: &str, end: &str) -> abi::Reservation {
+        abi::Reservation::new_pending(
+            "tenant_id",
+            uid,
+            rid,
+            start.parse().unwrap(),
+            end.parse().unwrap(),
+            "note",
+        )
+    }
+
+    #[tokio::test]
+    async fn overlapping_and_related_reservations_should_conflict() {
+        let manager = InMemoryRsvp::new();
+        manager
+            .link_resources("tenant_id".into(), "hall".into(), "hall-a".into())
+            .await
+            .unwrap();
+        manager
+            .reserve(
+                rsvp(
+                    "alice",
+                    "hall-a",
+                    "2022-12-25T12:00:00-0700",
+                    "2022-12-26T12:00:00-0700",
+                ),
+                None,
+            )
+            .await
+            .unwrap();
+
+        // adjacent windows don't overlap
+        manager
+            .reserve(
+                rsvp(
+                    "bob",
+                    "hall-a",
+                    "2022-12-26T12:00:00-0700",
+                    "2022-12-27T12:00:00-0700",
+                ),
+                None,
+            )
+            .await
+            .unwrap();
+        let mut booking = rsvp(
+            "bob",
+            "hall",
+            "2022-12-25T18:00:00-0700",
+            "2022-12-26T18:00:00-0700",
+        );
+        booking.extra_resource_ids = vec!["room-1".into()];
+        let err = manager.reserve(booking.clone(), None).await.unwrap_err();
+        if let abi::Error::ConflictReservation(ReservationConflictInfo::Parsed(info)) = err {
+            assert_eq!(info.new.rid, "hall");
+            assert_eq!(info.exist.rid, "hall-a");
+        } else {
+            panic!("expect conflict reservation error");
+        }
+
+        // a rejected booking no longer holds its resources
+        manager
+            .update_resource(abi::Resource::new("tenant_id", "room-1", true))
+            .await
+            .unwrap();
+        booking.resource_id = "room-2".into();
+        let pending = manager.reserve(booking.clone(), None).await.unwrap();
+        assert_eq!(pending.status, ReservationStatus::AwaitingApproval as i32);
+        assert!(manager.reserve(booking.clone(), None).await.is_err());
+        manager
+            .reject(
+                "tenant_id".into(),
+                pending.id,
+                "admin".into(),
+                "no".into(),
+                Some(1),
+                None,
+            )
+            .await
+            .unwrap();
+        manager.reserve(booking, None).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn query_should_filter_and_paginate() {
+        let manager = InMemoryRsvp::new();
+        for day in 25..30 {
+            let start = format!("2022-12-{}T12:00:00-0700", day);
+            let end = format!("2022-12-{}T13:00:00-0700", day);
+            manager
+                .reserve(rsvp("alice", "room-1", &start, &end), None)
+                .await
+                .unwrap();
+        }
+        manager
+            .reserve(
+                rsvp(
+                    "bob",
+                    "room-1",
+                    "2022-12-30T12:00:00-0700",
+                    "2022-12-30T13:00:00-0700",
+                ),
+                None,
+            )
+            .await
+            .unwrap();
+
+        let query = ReservationQuery::new(
+            "tenant_id",
+            "alice",
+            "room-1",
+            "2022-12-01T00:00:00Z".parse().unwrap(),
+            "2023-01-01T00:00:00Z".parse().unwrap(),
+            ReservationStatus::Pending,
+            2,
+            2,
+            true,
+        );
+        let rsvps = manager.query(query.clone()).await.unwrap();
+        let days: Vec<_> = rsvps
+            .iter()
+            .map(|r| window(r).0.format("%d").to_string())
+            .collect();
+        assert_eq!(days, vec!["27", "26"]);
+
+        let confirmed = ReservationQuery {
+            status: ReservationStatus::Confirmed as i32,
+            ..query
+        };
+        assert!(manager.query(confirmed).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,177 @@
+use sqlx::{PgPool, Row};
+
+/// a change written to the outbox in the transaction that made it
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutboxEvent {
+    pub id: i64,
+    pub txid: i64,
+    pub event: abi::ListenResponse,
+}
+
+/// reads the outbox in commit order for named consumers, each consumer stores its own offset.
+/// events are read again until their offset is committed, so delivery is at least once
+#[derive(Debug, Clone)]
+pub struct Outbox {
+    pool: PgPool,
+}
+
+impl Outbox {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// up to limit events after the consumer's offset.
+    /// only transactions older than every running one are read, a later commit can't slip behind the offset
+    pub async fn read(&self, consumer: &str, limit: i64) -> Result<Vec<OutboxEvent>, abi::Error> {
+        let rows = sqlx::query(
+            "WITH offsets AS (
+                SELECT COALESCE((SELECT txid FROM rsvp.outbox_offsets WHERE consumer = $1), 0) AS txid,
+                    COALESCE((SELECT id FROM rsvp.outbox_offsets WHERE consumer = $1), 0) AS id
+            )
+            SELECT o.id AS event_id, o.txid, o.op::text AS op, r.*
+            FROM rsvp.outbox o, offsets,
+            jsonb_populate_record(NULL::rsvp.reservations, o.reservation) r
+            WHERE (o.txid, o.id) > (offsets.txid, offsets.id)
+                AND o.txid < txid_snapshot_xmin(txid_current_snapshot())
+            ORDER BY o.txid, o.id
+            LIMIT $2",
+        )
+        .bind(consumer)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                let op: String = row.get("op");
+                Ok(OutboxEvent {
+                    id: row.get("event_id"),
+                    txid: row.get("txid"),
+                    event: abi::ListenResponse {
+                        op: abi::ReservationUpdateType::from(op.as_str()) as i32,
+                        reservation: Some(sqlx::FromRow::from_row(row)?),
+                    },
+                })
+            })
+            .collect()
+    }
+
+    /// store the consumer's offset, events up to and including this one are not read again
+    pub async fn commit(&self, consumer: &str, event: &OutboxEvent) -> Result<(), abi::Error> {
+        sqlx::query(
+            "INSERT INTO rsvp.outbox_offsets (consumer, txid, id) VALUES ($1, $2, $3)
+            ON CONFLICT (consumer) DO UPDATE SET txid = EXCLUDED.txid, id = EXCLUDED.id, updated_at = now()",
+        )
+        .bind(consumer)
+        .bind(event.txid)
+        .bind(event.id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// delete the events every consumer has committed and return how many there were.
+    /// nothing is deleted until a consumer commits, one added later starts from the events still kept
+    pub async fn prune(&self) -> Result<u64, abi::Error> {
+        let pruned = sqlx::query(
+            "DELETE FROM rsvp.outbox o
+            USING (SELECT txid, id FROM rsvp.outbox_offsets ORDER BY txid, id LIMIT 1) slowest
+            WHERE (o.txid, o.id) <= (slowest.txid, slowest.id)",
+        )
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        Ok(pruned)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ReservationManager, Rsvp};
+    use abi::ReservationUpdateType;
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn outbox_should_be_read_in_order_from_offset() {
+        let manager = ReservationManager::new(migrated_pool.clone());
+        let rsvp = abi::Reservation::new_pending(
+            "tenant_id",
+            "user_id1",
+            "resource_id",
+            "2022-12-25T12:00:00-0700".parse().unwrap(),
+            "2022-12-31T12:00:00-0700".parse().unwrap(),
+            "Test note1",
+        );
+        let rsvp = manager.reserve(rsvp, None).await.unwrap();
+        let updated = manager
+            .update_note(
+                "tenant_id".into(),
+                rsvp.id.clone(),
+                "new".into(),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        manager
+            .delete("tenant_id".into(), rsvp.id.clone(), None, None)
+            .await
+            .unwrap();
+
+        let outbox = Outbox::new(migrated_pool.clone());
+        let events = outbox.read("stdout", 10).await.unwrap();
+        let ops: Vec<_> = events.iter().map(|e| e.event.op).collect();
+        assert_eq!(
+            ops,
+            vec![
+                ReservationUpdateType::Create as i32,
+                ReservationUpdateType::Update as i32,
+                ReservationUpdateType::Delete as i32
+            ]
+        );
+        assert_eq!(events[0].event.reservation.as_ref(), Some(&rsvp));
+        assert_eq!(events[1].event.reservation.as_ref(), Some(&updated));
+
+        // offsets are kept per consumer
+        outbox.commit("stdout", &events[1]).await.unwrap();
+        assert_eq!(outbox.read("stdout", 10).await.unwrap(), events[2..]);
+        assert_eq!(outbox.read("file", 10).await.unwrap(), events);
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn outbox_should_prune_events_every_consumer_committed() {
+        let manager = ReservationManager::new(migrated_pool.clone());
+        let rsvp = abi::Reservation::new_pending(
+            "tenant_id",
+            "user_id1",
+            "resource_id",
+            "2022-12-25T12:00:00-0700".parse().unwrap(),
+            "2022-12-31T12:00:00-0700".parse().unwrap(),
+            "Test note1",
+        );
+        let rsvp = manager.reserve(rsvp, None).await.unwrap();
+        for note in ["a", "b"] {
+            manager
+                .update_note("tenant_id".into(), rsvp.id.clone(), note.into(), None, None)
+                .await
+                .unwrap();
+        }
+
+        let outbox = Outbox::new(migrated_pool.clone());
+        let events = outbox.read("stdout", 10).await.unwrap();
+        assert_eq!(events.len(), 3);
+        // no consumer has committed anything yet
+        assert_eq!(outbox.prune().await.unwrap(), 0);
+
+        // the slowest consumer decides
+        outbox.commit("stdout", &events[2]).await.unwrap();
+        outbox.commit("file", &events[0]).await.unwrap();
+        assert_eq!(outbox.prune().await.unwrap(), 1);
+        assert_eq!(outbox.read("file", 10).await.unwrap(), events[1..]);
+        assert!(outbox.read("stdout", 10).await.unwrap().is_empty());
+
+        outbox.commit("file", &events[2]).await.unwrap();
+        assert_eq!(outbox.prune().await.unwrap(), 2);
+        assert_eq!(outbox.prune().await.unwrap(), 0);
+    }
+}
@@ -0,0 +1,163 @@
+use chrono::Duration;
+use sqlx::{PgPool, Row};
+
+/// a reminder that the reservation starts in lead_time
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reminder {
+    pub id: i64,
+    pub lead_time: Duration,
+    pub reservation: abi::Reservation,
+}
+
+/// reminders scheduled by the reservation manager, waiting to be sent
+#[derive(Debug, Clone)]
+pub struct Reminders {
+    pool: PgPool,
+}
+
+impl Reminders {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// claim up to limit reminders that are due, they are offered again after lease unless marked sent.
+    /// reminders of reservations that already started or are no longer active are never offered
+    pub async fn due(&self, limit: i64, lease: Duration) -> Result<Vec<Reminder>, abi::Error> {
+        let rows = sqlx::query(
+            "WITH claimed AS (
+                UPDATE rsvp.reminders SET locked_until = now() + $2
+                WHERE id IN (
+                    SELECT m.id FROM rsvp.reminders m JOIN rsvp.reservations r ON r.id = m.reservation_id
+                    WHERE m.sent_at IS NULL AND m.remind_at <= now() AND (m.locked_until IS NULL OR m.locked_until <= now())
+                        AND now() < lower(r.timespan) AND r.status IN ('pending', 'confirmed')
+                    ORDER BY m.remind_at LIMIT $1 FOR UPDATE OF m SKIP LOCKED
+                )
+                RETURNING id, reservation_id, lead_time, remind_at
+            )
+            SELECT claimed.id AS reminder_id, extract(epoch FROM claimed.lead_time)::bigint AS lead_seconds, r.*
+            FROM claimed JOIN rsvp.reservations r ON r.id = claimed.reservation_id
+            ORDER BY claimed.remind_at",
+        )
+        .bind(limit)
+        .bind(lease)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(Reminder {
+                    id: row.get("reminder_id"),
+                    lead_time: Duration::seconds(row.get("lead_seconds")),
+                    reservation: sqlx::FromRow::from_row(row)?,
+                })
+            })
+            .collect()
+    }
+
+    pub async fn sent(&self, reminder: &Reminder) -> Result<(), abi::Error> {
+        sqlx::query("UPDATE rsvp.reminders SET sent_at = now() WHERE id = $1")
+            .bind(reminder.id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ReminderPolicy, ReservationManager, Rsvp};
+    use chrono::Utc;
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn reminders_should_follow_reservation_lifecycle() {
+        let manager =
+            ReservationManager::new(migrated_pool.clone()).with_reminder_policy(ReminderPolicy {
+                before: vec![Duration::hours(24), Duration::minutes(15)],
+            });
+        let reminders = Reminders::new(migrated_pool.clone());
+        let now = Utc::now();
+        // the 24h reminder would be in the past, only the 15min one is due in a minute
+        let rsvp = abi::Reservation::new_pending(
+            "tenant_id",
+            "user_id1",
+            "room-1",
+            (now + Duration::minutes(16)).into(),
+            (now + Duration::hours(1)).into(),
+            "Test note1",
+        );
+        let rsvp = manager.reserve(rsvp, None).await.unwrap();
+        let scheduled: Vec<i64> = sqlx::query(
+            "SELECT extract(epoch FROM lead_time)::bigint FROM rsvp.reminders WHERE reservation_id = $1::uuid",
+        )
+        .bind(&rsvp.id)
+        .fetch_all(&migrated_pool)
+        .await
+        .unwrap()
+        .iter()
+        .map(|row| row.get(0))
+        .collect();
+        assert_eq!(scheduled, vec![15 * 60]);
+        assert!(reminders
+            .due(10, Duration::minutes(1))
+            .await
+            .unwrap()
+            .is_empty());
+
+        // moved closer, the reminder is due now
+        let rsvp = manager
+            .reschedule(
+                "tenant_id".into(),
+                rsvp.id,
+                now + Duration::minutes(10),
+                now + Duration::hours(1),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        let scheduled: i64 = sqlx::query("SELECT count(*) FROM rsvp.reminders")
+            .fetch_one(&migrated_pool)
+            .await
+            .unwrap()
+            .get(0);
+        assert_eq!(scheduled, 0);
+        let rsvp = manager
+            .reschedule(
+                "tenant_id".into(),
+                rsvp.id,
+                now + Duration::minutes(20),
+                now + Duration::hours(1),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        sqlx::query("UPDATE rsvp.reminders SET remind_at = now()")
+            .execute(&migrated_pool)
+            .await
+            .unwrap();
+        let due = reminders.due(10, Duration::minutes(1)).await.unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].lead_time, Duration::minutes(15));
+        assert_eq!(due[0].reservation, rsvp);
+        reminders.sent(&due[0]).await.unwrap();
+        assert!(reminders
+            .due(10, Duration::zero())
+            .await
+            .unwrap()
+            .is_empty());
+
+        // cancelling drops the reminders
+        manager
+            .delete("tenant_id".into(), rsvp.id, None, None)
+            .await
+            .unwrap();
+        let scheduled: i64 = sqlx::query("SELECT count(*) FROM rsvp.reminders")
+            .fetch_one(&migrated_pool)
+            .await
+            .unwrap()
+            .get(0);
+        assert_eq!(scheduled, 0);
+    }
+}
@@ -0,0 +1,1200 @@
+use crate::{
+    fingerprint, validate_key, version_part, BulkReserve, CheckInPolicy, IdempotencyKey,
+    ReservationId, ResourceId, Rsvp, TenantId, UserId,
+};
+use abi::{
+    convert_to_timestamp, convert_to_utc_time, AggregateGroup, ReservationConflict,
+    ReservationConflictInfo, ReservationStatus, ReservationWindow, TimeMatch,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, TimeZone, Utc};
+use prost::Message;
+use sqlx::{
+    sqlite::SqliteRow, types::Uuid, Acquire, Executor, QueryBuilder, Row, Sqlite, SqlitePool,
+    Transaction,
+};
+
+/// `Rsvp` stored in SQLite, for small sites that don't run Postgres.
+/// SQLite has no range types or exclusion constraints, conflicts are checked by the queries of
+/// each write transaction instead. write transactions take SQLite's single write lock before
+/// they read, so they run one after another and the later one sees the earlier one's rows
+#[derive(Debug, Clone)]
+pub struct SqliteRsvp {
+    pool: SqlitePool,
+    check_in: CheckInPolicy,
+}
+
+#[async_trait]
+impl Rsvp for SqliteRsvp {
+    async fn reserve(
+        &self,
+        rsvp: abi::Reservation,
+        key: Option<IdempotencyKey>,
+    ) -> Result<abi::Reservation, abi::Error> {
+        rsvp.validate()?;
+
+        let request = fingerprint(&[&rsvp.encode_to_vec()]);
+        let mut tx = self.begin_write().await?;
+        if let Some(rsvp) = replay(
+            &mut tx,
+            &rsvp.tenant_id,
+            key.as_deref(),
+            "reserve",
+            &request,
+        )
+        .await?
+        {
+            return Ok(rsvp);
+        }
+
+        let rsvp = insert(&mut tx, rsvp).await?;
+        save_response(&mut tx, &rsvp.tenant_id, key.as_deref(), &rsvp).await?;
+        tx.commit().await?;
+        Ok(rsvp)
+    }
+
+    async fn bulk_reserve(
+        &self,
+        rsvps: Vec<abi::Reservation>,
+        dry_run: bool,
+    ) -> Result<BulkReserve, abi::Error> {
+        let mut result = BulkReserve::default();
+        let mut tx = self.begin_write().await?;
+        for (row, rsvp) in rsvps.into_iter().enumerate() {
+            if let Err(e) = rsvp.validate().and_then(|_| rsvp.requested_status()) {
+                result.rejected.push((row, e));
+                continue;
+            }
+
+            // earlier rows are visible to later ones, a conflicting row only rolls back its own savepoint
+            let mut savepoint = tx.begin().await?;
+            match insert(&mut savepoint, rsvp).await {
+                Ok(rsvp) => {
+                    savepoint.commit().await?;
+                    result.reserved.push(rsvp);
+                }
+                Err(
+                    e @ (abi::Error::ConflictReservation(_) | abi::Error::ConflictReservations(_)),
+                ) => {
+                    savepoint.rollback().await?;
+                    result.rejected.push((row, e));
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        if dry_run {
+            tx.rollback().await?;
+            for rsvp in &mut result.reserved {
+                rsvp.id.clear();
+                rsvp.version = 0;
+            }
+        } else {
+            tx.commit().await?;
+        }
+        Ok(result)
+    }
+
+    async fn change_status(
+        &self,
+        tenant_id: TenantId,
+        id: ReservationId,
+        expected_version: Option<i64>,
+        key: Option<IdempotencyKey>,
+    ) -> Result<abi::Reservation, abi::Error> {
+        let id = parse_id(id)?;
+
+        let request = fingerprint(&[id.as_bytes(), &version_part(expected_version)]);
+        let mut tx = self.begin_write().await?;
+        if let Some(rsvp) = replay(&mut tx, &tenant_id, key.as_deref(), "confirm", &request).await?
+        {
+            return Ok(rsvp);
+        }
+        check_version(&mut tx, &tenant_id, &id, expected_version).await?;
+
+        let row = sqlx::query(
+            "UPDATE reservations SET status = 'confirmed', version = version + 1 WHERE id = ?1 AND tenant_id = ?2 AND status = 'pending' RETURNING *",
+        )
+        .bind(&id)
+        .bind(&tenant_id)
+        .fetch_one(&mut tx)
+        .await?;
+        let rsvp = to_reservation(&row)?;
+
+        save_response(&mut tx, &tenant_id, key.as_deref(), &rsvp).await?;
+        tx.commit().await?;
+        Ok(rsvp)
+    }
+
+    async fn update_note(
+        &self,
+        tenant_id: TenantId,
+        id: ReservationId,
+        note: String,
+        expected_version: Option<i64>,
+        key: Option<IdempotencyKey>,
+    ) -> Result<abi::Reservation, abi::Error> {
+        let id = parse_id(id)?;
+
+        let request = fingerprint(&[
+            id.as_bytes(),
+            note.as_bytes(),
+            &version_part(expected_version),
+        ]);
+        let mut tx = self.begin_write().await?;
+        if let Some(rsvp) = replay(&mut tx, &tenant_id, key.as_deref(), "update", &request).await? {
+            return Ok(rsvp);
+        }
+        check_version(&mut tx, &tenant_id, &id, expected_version).await?;
+
+        let row = sqlx::query(
+            "UPDATE reservations SET note = ?1, version = version + 1 WHERE id = ?2 AND tenant_id = ?3 RETURNING *",
+        )
+        .bind(note)
+        .bind(&id)
+        .bind(&tenant_id)
+        .fetch_one(&mut tx)
+        .await?;
+        let rsvp = to_reservation(&row)?;
+
+        save_response(&mut tx, &tenant_id, key.as_deref(), &rsvp).await?;
+        tx.commit().await?;
+        Ok(rsvp)
+    }
+
+    async fn delete(
+        &self,
+        tenant_id: TenantId,
+        id: ReservationId,
+        expected_version: Option<i64>,
+        key: Option<IdempotencyKey>,
+    ) -> Result<abi::Reservation, abi::Error> {
+        let id = parse_id(id)?;
+
+        let request = fingerprint(&[id.as_bytes(), &version_part(expected_version)]);
+        let mut tx = self.begin_write().await?;
+        if let Some(rsvp) = replay(&mut tx, &tenant_id, key.as_deref(), "cancel", &request).await? {
+            return Ok(rsvp);
+        }
+        check_version(&mut tx, &tenant_id, &id, expected_version).await?;
+
+        let row =
+            sqlx::query("DELETE FROM reservations WHERE id = ?1 AND tenant_id = ?2 RETURNING *")
+                .bind(&id)
+                .bind(&tenant_id)
+                .fetch_one(&mut tx)
+                .await?;
+        let rsvp = to_reservation(&row)?;
+
+        save_response(&mut tx, &tenant_id, key.as_deref(), &rsvp).await?;
+        tx.commit().await?;
+        Ok(rsvp)
+    }
+
+    async fn get(
+        &self,
+        tenant_id: TenantId,
+        id: ReservationId,
+    ) -> Result<abi::Reservation, abi::Error> {
+        let id = parse_id(id)?;
+        let row = sqlx::query("SELECT * FROM reservations WHERE id = ?1 AND tenant_id = ?2")
+            .bind(id)
+            .bind(tenant_id)
+            .fetch_one(&self.pool)
+            .await?;
+        to_reservation(&row)
+    }
+
+    async fn query(
+        &self,
+        query: abi::ReservationQuery,
+    ) -> Result<Vec<abi::Reservation>, abi::Error> {
+        query.validate()?;
+        let mut qb = QueryBuilder::new("SELECT reservations.*");
+        push_matching(&mut qb, &query, "");
+        if let Some(cursor) = query.get_cursor()? {
+            qb.push(if query.desc {
+                " AND (reservations.start_at, reservations.id) < ("
+            } else {
+                " AND (reservations.start_at, reservations.id) > ("
+            });
+            qb.push_bind(cursor.start.timestamp_micros());
+            qb.push(", ");
+            qb.push_bind(cursor.id.to_string());
+            qb.push(")");
+        }
+
+        qb.push(" ORDER BY ");
+        if query.by_relevance && !query.get_search_terms().is_empty() {
+            // bm25 is lower for better matches
+            qb.push("hits.score, ");
+        }
+        let order = if query.desc { "DESC" } else { "ASC" };
+        qb.push(format!(
+            "reservations.start_at {order}, reservations.id {order} LIMIT "
+        ));
+        qb.push_bind(query.page_size);
+        qb.push(" OFFSET ");
+        qb.push_bind(query.offset());
+
+        let rows = qb.build().fetch_all(&self.pool).await?;
+        rows.iter().map(to_reservation).collect()
+    }
+
+    async fn aggregate(
+        &self,
+        query: abi::ReservationQuery,
+        group_by: AggregateGroup,
+        utc_offset_minutes: i32,
+    ) -> Result<Vec<abi::Aggregate>, abi::Error> {
+        query.validate()?;
+        let utc_offset = abi::utc_offset(utc_offset_minutes)?;
+        let (start, end) = micros_window(&query);
+
+        let mut qb = QueryBuilder::new("SELECT ");
+        match group_by {
+            AggregateGroup::None => qb.push("''"),
+            AggregateGroup::Resource => qb.push("held.value"),
+            AggregateGroup::User => qb.push("reservations.user_id"),
+            AggregateGroup::Status => qb.push("reservations.status"),
+            AggregateGroup::Day => {
+                qb.push("strftime('%Y-%m-%d', reservations.start_at / 1000000 + ");
+                qb.push_bind(utc_offset.local_minus_utc());
+                qb.push(", 'unixepoch')")
+            }
+        };
+        qb.push(" AS key, count(*) AS count, COALESCE(sum(max(0, min(reservations.end_at, ");
+        qb.push_bind(end);
+        qb.push(") - max(reservations.start_at, ");
+        qb.push_bind(start);
+        qb.push("))), 0) / 1000000 AS seconds");
+        let tables = match group_by {
+            AggregateGroup::Resource => {
+                ", json_each(json_insert(reservations.extra_resource_ids, '$[#]', reservations.resource_id)) AS held"
+            }
+            _ => "",
+        };
+        push_matching(&mut qb, &query, tables);
+        // a query for one resource only counts that one, not the others its reservations hold
+        if group_by == AggregateGroup::Resource && !query.resource_id.is_empty() {
+            qb.push(" AND held.value = ");
+            qb.push_bind(query.resource_id.clone());
+        }
+        if group_by != AggregateGroup::None {
+            qb.push(" GROUP BY 1 ORDER BY 1");
+        }
+
+        let rows = qb.build().fetch_all(&self.pool).await?;
+        Ok(rows
+            .iter()
+            .map(|row| {
+                abi::Aggregate::new(
+                    row.get::<String, _>("key"),
+                    row.get("count"),
+                    row.get("seconds"),
+                )
+            })
+            .collect())
+    }
+
+    async fn link_resources(
+        &self,
+        tenant_id: TenantId,
+        parent_id: ResourceId,
+        child_id: ResourceId,
+    ) -> Result<(), abi::Error> {
+        if parent_id.is_empty() {
+            return Err(abi::Error::InvalidResourceId(parent_id));
+        }
+        if child_id.is_empty() {
+            return Err(abi::Error::InvalidResourceId(child_id));
+        }
+
+        let mut tx = self.begin_write().await?;
+        // parent must not be child itself or one of its descendants
+        let cycle: bool = sqlx::query(
+            "WITH RECURSIVE descendants(id) AS (
+                SELECT ?2
+                UNION
+                SELECT r.child_id FROM resource_relations r JOIN descendants d ON r.parent_id = d.id WHERE r.tenant_id = ?1
+            ) SELECT EXISTS (SELECT 1 FROM descendants WHERE id = ?3)",
+        )
+        .bind(&tenant_id)
+        .bind(&child_id)
+        .bind(&parent_id)
+        .fetch_one(&mut tx)
+        .await?
+        .get(0);
+        if cycle {
+            return Err(abi::Error::ResourceCycle {
+                parent: parent_id,
+                child: child_id,
+            });
+        }
+
+        sqlx::query(
+            "INSERT INTO resource_relations (tenant_id, parent_id, child_id) VALUES (?1, ?2, ?3) ON CONFLICT DO NOTHING",
+        )
+        .bind(&tenant_id)
+        .bind(&parent_id)
+        .bind(&child_id)
+        .execute(&mut tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn unlink_resources(
+        &self,
+        tenant_id: TenantId,
+        parent_id: ResourceId,
+        child_id: ResourceId,
+    ) -> Result<(), abi::Error> {
+        let deleted = sqlx::query(
+            "DELETE FROM resource_relations WHERE tenant_id = ?1 AND parent_id = ?2 AND child_id = ?3",
+        )
+        .bind(tenant_id)
+        .bind(parent_id)
+        .bind(child_id)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        if deleted == 0 {
+            return Err(abi::Error::NotFound);
+        }
+        Ok(())
+    }
+
+    async fn update_resource(&self, resource: abi::Resource) -> Result<abi::Resource, abi::Error> {
+        resource.validate()?;
+        sqlx::query(
+            "INSERT INTO resources (tenant_id, id, requires_approval, rate_card) VALUES (?1, ?2, ?3, ?4) ON CONFLICT (tenant_id, id) DO UPDATE SET requires_approval = excluded.requires_approval, rate_card = excluded.rate_card",
+        )
+        .bind(&resource.tenant_id)
+        .bind(&resource.id)
+        .bind(resource.requires_approval)
+        .bind(resource.rate_card.as_ref().map(|card| card.encode_to_vec()))
+        .execute(&self.pool)
+        .await?;
+        Ok(resource)
+    }
+
+    async fn approve(
+        &self,
+        tenant_id: TenantId,
+        id: ReservationId,
+        approver_id: UserId,
+        reason: String,
+        expected_version: Option<i64>,
+        key: Option<IdempotencyKey>,
+    ) -> Result<abi::Reservation, abi::Error> {
+        self.review(
+            tenant_id,
+            id,
+            ReservationStatus::Pending,
+            approver_id,
+            reason,
+            expected_version,
+            key,
+        )
+        .await
+    }
+
+    async fn reject(
+        &self,
+        tenant_id: TenantId,
+        id: ReservationId,
+        approver_id: UserId,
+        reason: String,
+        expected_version: Option<i64>,
+        key: Option<IdempotencyKey>,
+    ) -> Result<abi::Reservation, abi::Error> {
+        self.review(
+            tenant_id,
+            id,
+            ReservationStatus::Rejected,
+            approver_id,
+            reason,
+            expected_version,
+            key,
+        )
+        .await
+    }
+
+    async fn check_in(
+        &self,
+        tenant_id: TenantId,
+        id: ReservationId,
+        expected_version: Option<i64>,
+        key: Option<IdempotencyKey>,
+    ) -> Result<abi::Reservation, abi::Error> {
+        let id = parse_id(id)?;
+
+        let request = fingerprint(&[id.as_bytes(), &version_part(expected_version)]);
+        let mut tx = self.begin_write().await?;
+        if let Some(rsvp) =
+            replay(&mut tx, &tenant_id, key.as_deref(), "check_in", &request).await?
+        {
+            return Ok(rsvp);
+        }
+        check_version(&mut tx, &tenant_id, &id, expected_version).await?;
+
+        let row = sqlx::query(
+            "UPDATE reservations SET checked_in_at = COALESCE(checked_in_at, ?1), version = version + 1 WHERE id = ?2 AND tenant_id = ?3 AND status IN ('pending', 'confirmed') AND ?1 BETWEEN start_at - ?4 AND start_at + ?5 RETURNING *",
+        )
+        .bind(Utc::now().timestamp_micros())
+        .bind(&id)
+        .bind(&tenant_id)
+        .bind(self.check_in.before.num_microseconds())
+        .bind(self.check_in.after.num_microseconds())
+        .fetch_optional(&mut tx)
+        .await?;
+        let rsvp = match row {
+            Some(row) => to_reservation(&row)?,
+            None => {
+                // tell a closed check-in window from a missing reservation
+                sqlx::query("SELECT 1 FROM reservations WHERE id = ?1 AND tenant_id = ?2")
+                    .bind(&id)
+                    .bind(&tenant_id)
+                    .fetch_one(&mut tx)
+                    .await?;
+                return Err(abi::Error::CheckInNotAllowed);
+            }
+        };
+
+        save_response(&mut tx, &tenant_id, key.as_deref(), &rsvp).await?;
+        tx.commit().await?;
+        Ok(rsvp)
+    }
+
+    async fn release_no_shows(&self) -> Result<Vec<abi::Reservation>, abi::Error> {
+        let now = Utc::now().timestamp_micros();
+        let mut tx = self.begin_write().await?;
+        let rows = sqlx::query(
+            "UPDATE reservations SET status = 'no_show', end_at = MIN(end_at, ?1), version = version + 1
+            WHERE checked_in_at IS NULL AND status IN ('pending', 'confirmed')
+                AND start_at < ?1 - ?2
+            RETURNING *",
+        )
+        .bind(now)
+        .bind(self.check_in.after.num_microseconds())
+        .fetch_all(&mut tx)
+        .await?;
+
+        let mut rsvps = vec![];
+        for row in rows {
+            let rsvp = to_reservation(&row)?;
+            sqlx::query("UPDATE reservation_resources SET end_at = ?1 WHERE reservation_id = ?2")
+                .bind(window(&rsvp).1)
+                .bind(&rsvp.id)
+                .execute(&mut tx)
+                .await?;
+            sqlx::query(
+                "INSERT INTO user_no_shows (tenant_id, user_id, count) VALUES (?1, ?2, 1) ON CONFLICT (tenant_id, user_id) DO UPDATE SET count = count + 1",
+            )
+            .bind(&rsvp.tenant_id)
+            .bind(&rsvp.user_id)
+            .execute(&mut tx)
+            .await?;
+            rsvps.push(rsvp);
+        }
+        tx.commit().await?;
+        Ok(rsvps)
+    }
+
+    async fn no_show_count(&self, tenant_id: TenantId, user_id: UserId) -> Result<i64, abi::Error> {
+        let count =
+            sqlx::query("SELECT count FROM user_no_shows WHERE tenant_id = ?1 AND user_id = ?2")
+                .bind(tenant_id)
+                .bind(user_id)
+                .fetch_optional(&self.pool)
+                .await?
+                .map(|row| row.get(0))
+                .unwrap_or(0);
+        Ok(count)
+    }
+
+    async fn expire_idempotency_keys(&self, before: DateTime<Utc>) -> Result<u64, abi::Error> {
+        // created_at is in seconds
+        let expired = sqlx::query("DELETE FROM idempotency_keys WHERE created_at < ?1")
+            .bind(before.timestamp())
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+        Ok(expired)
+    }
+
+    async fn quote(&self, rsvp: abi::Reservation) -> Result<i64, abi::Error> {
+        rsvp.validate()?;
+        price(&self.pool, &rsvp).await
+    }
+
+    async fn feed_token(&self, tenant_id: TenantId, user_id: UserId) -> Result<String, abi::Error> {
+        if user_id.is_empty() {
+            return Err(abi::Error::InvalidUserId(user_id));
+        }
+        // the no-op update makes RETURNING yield the existing token
+        let token = sqlx::query(
+            "INSERT INTO calendar_feeds (tenant_id, user_id, token) VALUES (?1, ?2, ?3) ON CONFLICT (tenant_id, user_id) DO UPDATE SET token = token RETURNING token",
+        )
+        .bind(tenant_id)
+        .bind(user_id)
+        .bind(new_token())
+        .fetch_one(&self.pool)
+        .await?
+        .get(0);
+        Ok(token)
+    }
+
+    async fn reset_feed_token(
+        &self,
+        tenant_id: TenantId,
+        user_id: UserId,
+    ) -> Result<String, abi::Error> {
+        if user_id.is_empty() {
+            return Err(abi::Error::InvalidUserId(user_id));
+        }
+        let token = sqlx::query(
+            "INSERT INTO calendar_feeds (tenant_id, user_id, token) VALUES (?1, ?2, ?3) ON CONFLICT (tenant_id, user_id) DO UPDATE SET token = excluded.token, created_at = excluded.created_at RETURNING token",
+        )
+        .bind(tenant_id)
+        .bind(user_id)
+        .bind(new_token())
+        .fetch_one(&self.pool)
+        .await?
+        .get(0);
+        Ok(token)
+    }
+
+    async fn feed_owner(&self, token: String) -> Result<(TenantId, UserId), abi::Error> {
+        let row = sqlx::query("SELECT tenant_id, user_id FROM calendar_feeds WHERE token = ?1")
+            .bind(token)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok((row.get("tenant_id"), row.get("user_id")))
+    }
+
+    async fn reschedule(
+        &self,
+        tenant_id: TenantId,
+        id: ReservationId,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        expected_version: Option<i64>,
+        key: Option<IdempotencyKey>,
+    ) -> Result<abi::Reservation, abi::Error> {
+        let id = parse_id(id)?;
+        if start >= end {
+            return Err(abi::Error::InvalidTime);
+        }
+
+        let request = fingerprint(&[
+            id.as_bytes(),
+            start.to_rfc3339().as_bytes(),
+            end.to_rfc3339().as_bytes(),
+            &version_part(expected_version),
+        ]);
+        let mut tx = self.begin_write().await?;
+        if let Some(rsvp) =
+            replay(&mut tx, &tenant_id, key.as_deref(), "reschedule", &request).await?
+        {
+            return Ok(rsvp);
+        }
+        check_version(&mut tx, &tenant_id, &id, expected_version).await?;
+
+        // rejected and no-show reservations are over, they can't be moved
+        let row = sqlx::query(
+            "SELECT * FROM reservations WHERE id = ?1 AND tenant_id = ?2 AND status IN ('pending', 'confirmed', 'awaiting_approval')",
+        )
+        .bind(&id)
+        .bind(&tenant_id)
+        .fetch_one(&mut tx)
+        .await?;
+        let mut rsvp = to_reservation(&row)?;
+        rsvp.start = Some(convert_to_timestamp(start));
+        rsvp.end = Some(convert_to_timestamp(end));
+        let price = price(&mut tx, &rsvp).await?;
+        occupy(&mut tx, &id, &rsvp).await?;
+
+        let row = sqlx::query(
+            "UPDATE reservations SET start_at = ?1, end_at = ?2, price = ?3, version = version + 1 WHERE id = ?4 AND tenant_id = ?5 RETURNING *",
+        )
+        .bind(start.timestamp_micros())
+        .bind(end.timestamp_micros())
+        .bind(price)
+        .bind(&id)
+        .bind(&tenant_id)
+        .fetch_one(&mut tx)
+        .await?;
+        let rsvp = to_reservation(&row)?;
+
+        save_response(&mut tx, &tenant_id, key.as_deref(), &rsvp).await?;
+        tx.commit().await?;
+        Ok(rsvp)
+    }
+}
+
+impl SqliteRsvp {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self {
+            pool,
+            check_in: CheckInPolicy::default(),
+        }
+    }
+
+    pub fn with_check_in_policy(mut self, policy: CheckInPolicy) -> Self {
+        self.check_in = policy;
+        self
+    }
+
+    /// begin a transaction that holds the write lock from its first statement, like BEGIN IMMEDIATE.
+    /// a transaction that reads first only asks for the lock at its first write, and SQLite fails
+    /// that upgrade with SQLITE_BUSY instead of waiting while another writer holds the lock
+    async fn begin_write(&self) -> Result<Transaction<'static, Sqlite>, abi::Error> {
+        let mut tx = self.pool.begin().await?;
+        // any write takes the lock, waiting for it up to the busy timeout, even if it changes nothing
+        sqlx::query("UPDATE reservations SET version = version WHERE 0")
+            .execute(&mut tx)
+            .await?;
+        Ok(tx)
+    }
+
+    /// create or upgrade the tables, the migrations live in migrations-sqlite
+    pub async fn migrate(&self) -> Result<(), abi::Error> {
+        sqlx::migrate!("../migrations-sqlite")
+            .run(&self.pool)
+            .await
+            .map_err(|e| abi::Error::DbError(e.into()))
+    }
+
+    /// move a reservation awaiting approval to the given status, recording the approver
+    #[allow(clippy::too_many_arguments)]
+    async fn review(
+        &self,
+        tenant_id: TenantId,
+        id: ReservationId,
+        status: ReservationStatus,
+        approver_id: UserId,
+        reason: String,
+        expected_version: Option<i64>,
+        key: Option<IdempotencyKey>,
+    ) -> Result<abi::Reservation, abi::Error> {
+        let id = parse_id(id)?;
+        if approver_id.is_empty() {
+            return Err(abi::Error::InvalidUserId(approver_id));
+        }
+
+        let op = match status {
+            ReservationStatus::Rejected => "reject",
+            _ => "approve",
+        };
+        let request = fingerprint(&[
+            id.as_bytes(),
+            approver_id.as_bytes(),
+            reason.as_bytes(),
+            &version_part(expected_version),
+        ]);
+        let mut tx = self.begin_write().await?;
+        if let Some(rsvp) = replay(&mut tx, &tenant_id, key.as_deref(), op, &request).await? {
+            return Ok(rsvp);
+        }
+        check_version(&mut tx, &tenant_id, &id, expected_version).await?;
+
+        let row = sqlx::query(
+            "UPDATE reservations SET status = ?1, approver_id = ?2, approval_reason = ?3, version = version + 1 WHERE id = ?4 AND tenant_id = ?5 AND status = 'awaiting_approval' RETURNING *",
+        )
+        .bind(status.to_string())
+        .bind(approver_id)
+        .bind(reason)
+        .bind(&id)
+        .bind(&tenant_id)
+        .fetch_one(&mut tx)
+        .await?;
+        let rsvp = to_reservation(&row)?;
+        // rejected reservations no longer hold their resources
+        occupy(&mut tx, &id, &rsvp).await?;
+
+        save_response(&mut tx, &tenant_id, key.as_deref(), &rsvp).await?;
+        tx.commit().await?;
+        Ok(rsvp)
+    }
+}
+
+/// insert a validated reservation, it awaits approval if any of its resources requires it
+async fn insert(
+    tx: &mut Transaction<'_, Sqlite>,
+    mut rsvp: abi::Reservation,
+) -> Result<abi::Reservation, abi::Error> {
+    let mut status = rsvp.requested_status()?;
+    if requires_approval(tx, &rsvp).await? {
+        status = ReservationStatus::AwaitingApproval;
+    }
+    rsvp.status = status as i32;
+    rsvp.price = price(&mut *tx, &rsvp).await?;
+
+    let id = Uuid::new_v4().to_string();
+    sqlx::query(
+        "INSERT INTO reservations (id, tenant_id, user_id, resource_id, extra_resource_ids, start_at, end_at, note, status, price) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+    )
+    .bind(&id)
+    .bind(&rsvp.tenant_id)
+    .bind(&rsvp.user_id)
+    .bind(&rsvp.resource_id)
+    .bind(encode_ids(&rsvp.extra_resource_ids)?)
+    .bind(window(&rsvp).0)
+    .bind(window(&rsvp).1)
+    .bind(&rsvp.note)
+    .bind(status.to_string())
+    .bind(rsvp.price)
+    .execute(&mut *tx)
+    .await?;
+    occupy(tx, &id, &rsvp).await?;
+
+    rsvp.id = id;
+    rsvp.version = 1;
+    Ok(rsvp)
+}
+
+/// replace the resources held by the reservation, failing if any of them, their ancestors or
+/// descendants is held by another reservation at the same time. each conflicting resource is reported once
+async fn occupy(
+    tx: &mut Transaction<'_, Sqlite>,
+    id: &str,
+    rsvp: &abi::Reservation,
+) -> Result<(), abi::Error> {
+    sqlx::query("DELETE FROM reservation_resources WHERE reservation_id = ?1")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    if rsvp.status == ReservationStatus::Rejected as i32 {
+        return Ok(());
+    }
+
+    let (start, end) = window(rsvp);
+    let mut infos = vec![];
+    for rid in std::iter::once(&rsvp.resource_id).chain(&rsvp.extra_resource_ids) {
+        let exist = sqlx::query(
+            "WITH RECURSIVE ancestors(id) AS (
+                SELECT parent_id FROM resource_relations WHERE tenant_id = ?1 AND child_id = ?2
+                UNION
+                SELECT r.parent_id FROM resource_relations r JOIN ancestors a ON r.child_id = a.id WHERE r.tenant_id = ?1
+            ), descendants(id) AS (
+                SELECT child_id FROM resource_relations WHERE tenant_id = ?1 AND parent_id = ?2
+                UNION
+                SELECT r.child_id FROM resource_relations r JOIN descendants d ON r.parent_id = d.id WHERE r.tenant_id = ?1
+            )
+            SELECT resource_id, start_at, end_at FROM reservation_resources
+            WHERE tenant_id = ?1 AND reservation_id <> ?3 AND start_at < ?5 AND ?4 < end_at
+                AND (resource_id = ?2 OR resource_id IN (SELECT id FROM ancestors) OR resource_id IN (SELECT id FROM descendants))
+            ORDER BY start_at LIMIT 1",
+        )
+        .bind(&rsvp.tenant_id)
+        .bind(rid)
+        .bind(id)
+        .bind(start)
+        .bind(end)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        match exist {
+            Some(exist) => infos.push(ReservationConflictInfo::Parsed(ReservationConflict {
+                new: ReservationWindow {
+                    rid: rid.clone(),
+                    start: to_utc(start),
+                    end: to_utc(end),
+                },
+                exist: ReservationWindow {
+                    rid: exist.get("resource_id"),
+                    start: to_utc(exist.get("start_at")),
+                    end: to_utc(exist.get("end_at")),
+                },
+            })),
+            None => {
+                sqlx::query(
+                    "INSERT INTO reservation_resources (reservation_id, tenant_id, resource_id, start_at, end_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+                )
+                .bind(id)
+                .bind(&rsvp.tenant_id)
+                .bind(rid)
+                .bind(start)
+                .bind(end)
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+    }
+
+    match infos.len() {
+        0 => Ok(()),
+        1 => Err(abi::Error::ConflictReservation(infos.pop().unwrap())),
+        _ => Err(abi::Error::ConflictReservations(infos)),
+    }
+}
+
+/// whether any resource of the reservation needs an approver
+async fn requires_approval(
+    tx: &mut Transaction<'_, Sqlite>,
+    rsvp: &abi::Reservation,
+) -> Result<bool, abi::Error> {
+    let required = sqlx::query(
+        "SELECT EXISTS (SELECT 1 FROM resources WHERE tenant_id = ?1 AND (id = ?2 OR id IN (SELECT value FROM json_each(?3))) AND requires_approval)",
+    )
+    .bind(&rsvp.tenant_id)
+    .bind(&rsvp.resource_id)
+    .bind(encode_ids(&rsvp.extra_resource_ids)?)
+    .fetch_one(&mut *tx)
+    .await?
+    .get(0);
+    Ok(required)
+}
+
+/// sum of the quotes of every resource of the reservation, resources without a rate card are free
+async fn price<'c>(
+    executor: impl Executor<'c, Database = Sqlite>,
+    rsvp: &abi::Reservation,
+) -> Result<i64, abi::Error> {
+    let rows = sqlx::query(
+        "SELECT id, rate_card FROM resources WHERE tenant_id = ?1 AND (id = ?2 OR id IN (SELECT value FROM json_each(?3))) AND rate_card IS NOT NULL",
+    )
+    .bind(&rsvp.tenant_id)
+    .bind(&rsvp.resource_id)
+    .bind(encode_ids(&rsvp.extra_resource_ids)?)
+    .fetch_all(executor)
+    .await?;
+
+    let start = convert_to_utc_time(rsvp.start.clone().unwrap());
+    let end = convert_to_utc_time(rsvp.end.clone().unwrap());
+    let mut cards = vec![];
+    for row in rows {
+        let id: String = row.try_get("id")?;
+        let bytes: Vec<u8> = row.try_get("rate_card")?;
+        let card = abi::RateCard::decode(bytes.as_slice())
+            .map_err(|e| abi::Error::InvalidRateCard(format!("{}: {}", id, e)))?;
+        // quoting assumes a valid card, like the ones update_resource accepts
+        card.validate()?;
+        cards.push(card);
+    }
+    abi::RateCard::quote_all(&cards, start, end)
+}
+
+/// claim the idempotency key for the request, or return the result stored by an earlier call
+/// with the same key. the key can't be reused for another op or another request
+async fn replay(
+    tx: &mut Transaction<'_, Sqlite>,
+    tenant_id: &str,
+    key: Option<&str>,
+    op: &str,
+    request: &[u8],
+) -> Result<Option<abi::Reservation>, abi::Error> {
+    let key = match key {
+        Some(key) => key,
+        None => return Ok(None),
+    };
+    validate_key(key)?;
+
+    let claimed = sqlx::query(
+        "INSERT INTO idempotency_keys (tenant_id, key, op, fingerprint) VALUES (?1, ?2, ?3, ?4) ON CONFLICT DO NOTHING",
+    )
+    .bind(tenant_id)
+    .bind(key)
+    .bind(op)
+    .bind(request)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+    if claimed == 1 {
+        return Ok(None);
+    }
+
+    let row = sqlx::query(
+        "SELECT op, fingerprint, response FROM idempotency_keys WHERE tenant_id = ?1 AND key = ?2",
+    )
+    .bind(tenant_id)
+    .bind(key)
+    .fetch_one(&mut *tx)
+    .await?;
+    let stored_op: String = row.get("op");
+    let stored_request: Vec<u8> = row.get("fingerprint");
+    let response: Option<Vec<u8>> = row.get("response");
+    match response {
+        Some(response) if stored_op == op && stored_request == request => Ok(Some(
+            abi::Reservation::decode(response.as_slice()).map_err(|_| abi::Error::Unknown)?,
+        )),
+        _ => Err(abi::Error::IdempotencyKeyReused(key.to_string())),
+    }
+}
+
+/// make sure the reservation is still at the expected version
+async fn check_version(
+    tx: &mut Transaction<'_, Sqlite>,
+    tenant_id: &str,
+    id: &str,
+    expected: Option<i64>,
+) -> Result<(), abi::Error> {
+    let expected = match expected {
+        Some(expected) => expected,
+        None => return Ok(()),
+    };
+
+    let actual: i64 =
+        sqlx::query("SELECT version FROM reservations WHERE id = ?1 AND tenant_id = ?2")
+            .bind(id)
+            .bind(tenant_id)
+            .fetch_one(&mut *tx)
+            .await?
+            .get(0);
+    if actual != expected {
+        return Err(abi::Error::PreconditionFailed { expected, actual });
+    }
+    Ok(())
+}
+
+/// store the result of the op for the claimed idempotency key
+async fn save_response(
+    tx: &mut Transaction<'_, Sqlite>,
+    tenant_id: &str,
+    key: Option<&str>,
+    rsvp: &abi::Reservation,
+) -> Result<(), abi::Error> {
+    if let Some(key) = key {
+        sqlx::query("UPDATE idempotency_keys SET response = ?1 WHERE tenant_id = ?2 AND key = ?3")
+            .bind(rsvp.encode_to_vec())
+            .bind(tenant_id)
+            .bind(key)
+            .execute(&mut *tx)
+            .await?;
+    }
+    Ok(())
+}
+
+fn to_reservation(row: &SqliteRow) -> Result<abi::Reservation, abi::Error> {
+    let status: String = row.get("status");
+    let extra_resource_ids: String = row.get("extra_resource_ids");
+    Ok(abi::Reservation {
+        id: row.get("id"),
+        tenant_id: row.get("tenant_id"),
+        user_id: row.get("user_id"),
+        status: parse_status(&status)? as i32,
+        resource_id: row.get("resource_id"),
+        extra_resource_ids: serde_json::from_str(&extra_resource_ids)
+            .map_err(|_| abi::Error::Unknown)?,
+        start: Some(convert_to_timestamp(to_utc(row.get("start_at")))),
+        end: Some(convert_to_timestamp(to_utc(row.get("end_at")))),
+        note: row.get::<Option<String>, _>("note").unwrap_or_default(),
+        version: row.get("version"),
+        approver_id: row
+            .get::<Option<String>, _>("approver_id")
+            .unwrap_or_default(),
+        approval_reason: row
+            .get::<Option<String>, _>("approval_reason")
+            .unwrap_or_default(),
+        checked_in_at: row
+            .get::<Option<i64>, _>("checked_in_at")
+            .map(|at| convert_to_timestamp(to_utc(at))),
+        price: row.get("price"),
+    })
+}
+
+/// statuses are stored by the names rsvp.reservation_status uses
+fn parse_status(s: &str) -> Result<ReservationStatus, abi::Error> {
+    Ok(match s {
+        "unknown" => ReservationStatus::Unknown,
+        "pending" => ReservationStatus::Pending,
+        "confirmed" => ReservationStatus::Confirmed,
+        "blocked" => ReservationStatus::Blocked,
+        "awaiting_approval" => ReservationStatus::AwaitingApproval,
+        "rejected" => ReservationStatus::Rejected,
+        "no_show" => ReservationStatus::NoShow,
+        _ => return Err(abi::Error::Unknown),
+    })
+}
+
+/// extra resource ids are stored as a JSON array
+fn encode_ids(ids: &[String]) -> Result<String, abi::Error> {
+    serde_json::to_string(ids)
+        .map_err(|e| abi::Error::DbError(sqlx::Error::Protocol(e.to_string())))
+}
+
+fn parse_id(id: ReservationId) -> Result<String, abi::Error> {
+    Uuid::parse_str(&id)
+        .map(|id| id.to_string())
+        .map_err(|_| abi::Error::InvalidReservationId(id))
+}
+
+/// FROM and WHERE of the reservations the query finds, without the cursor.
+/// tables are joined to every reservation, search matches are joined as hits
+fn push_matching<'a>(
+    qb: &mut QueryBuilder<'a, Sqlite>,
+    query: &abi::ReservationQuery,
+    tables: &str,
+) {
+    qb.push(" FROM reservations");
+    qb.push(tables);
+
+    // quoted words are taken as they are, fts5 requires all of them
+    let terms = query.get_search_terms();
+    if !terms.is_empty() {
+        let search: Vec<String> = terms.iter().map(|term| format!("\"{term}\"")).collect();
+        qb.push(
+            " JOIN (SELECT reservation_id, bm25(reservation_notes) AS score FROM reservation_notes WHERE reservation_notes MATCH ",
+        );
+        qb.push_bind(search.join(" "));
+        qb.push(" AND tenant_id = ");
+        qb.push_bind(query.tenant_id.clone());
+        qb.push(") AS hits ON hits.reservation_id = reservations.id");
+    }
+
+    qb.push(" WHERE reservations.tenant_id = ");
+    qb.push_bind(query.tenant_id.clone());
+
+    let (start, end) = micros_window(query);
+    let (before, between, after) =
+        match TimeMatch::from_i32(query.time_match).unwrap_or(TimeMatch::ContainedIn) {
+            TimeMatch::ContainedIn => (
+                " AND ",
+                " <= reservations.start_at AND reservations.end_at <= ",
+                "",
+            ),
+            TimeMatch::Overlaps => (
+                " AND ",
+                " < reservations.end_at AND reservations.start_at < ",
+                "",
+            ),
+            TimeMatch::Contains => (
+                " AND reservations.start_at <= ",
+                " AND ",
+                " <= reservations.end_at",
+            ),
+            TimeMatch::StartsWithin => (
+                " AND ",
+                " <= reservations.start_at AND reservations.start_at < ",
+                "",
+            ),
+        };
+    qb.push(before);
+    qb.push_bind(start);
+    qb.push(between);
+    qb.push_bind(end);
+    qb.push(after);
+
+    let statuses: Vec<String> = query.get_statuses().iter().map(|s| s.to_string()).collect();
+    if !statuses.is_empty() {
+        qb.push(" AND reservations.status IN (");
+        let mut separated = qb.separated(", ");
+        for status in statuses {
+            separated.push_bind(status);
+        }
+        separated.push_unseparated(")");
+    }
+    if !query.user_id.is_empty() {
+        qb.push(" AND reservations.user_id = ");
+        qb.push_bind(query.user_id.clone());
+    }
+    // like the manager, a reservation is found by any of the resources it holds
+    if !query.resource_id.is_empty() {
+        qb.push(" AND reservations.id IN (SELECT reservation_id FROM reservation_resources WHERE tenant_id = ");
+        qb.push_bind(query.tenant_id.clone());
+        qb.push(" AND resource_id = ");
+        qb.push_bind(query.resource_id.clone());
+        qb.push(")");
+    }
+}
+
+/// window of the query in microseconds, an open side is the earliest or latest time there is
+fn micros_window(query: &abi::ReservationQuery) -> (i64, i64) {
+    let (start, end) = query.get_window();
+    (
+        start.map_or(i64::MIN, |t| t.timestamp_micros()),
+        end.map_or(i64::MAX, |t| t.timestamp_micros()),
+    )
+}
+
+/// start and end of the reservation in microseconds since the epoch
+fn window(rsvp: &abi::Reservation) -> (i64, i64) {
+    (
+        convert_to_utc_time(rsvp.start.clone().unwrap()).timestamp_micros(),
+        convert_to_utc_time(rsvp.end.clone().unwrap()).timestamp_micros(),
+    )
+}
+
+fn to_utc(micros: i64) -> DateTime<Utc> {
+    Utc.timestamp_nanos(micros * 1_000)
+}
+
+fn new_token() -> String {
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+
+    /// a database file removed with its journal when the test ends, whether it passed or not
+    struct TempDb(std::path::PathBuf);
+
+    impl Drop for TempDb {
+        fn drop(&mut self) {
+            for suffix in ["", "-journal", "-wal", "-shm"] {
+                let mut path = self.0.clone().into_os_string();
+                path.push(suffix);
+                std::fs::remove_file(path).ok();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn concurrent_reservations_should_not_double_book() {
+        let db = TempDb(std::env::temp_dir().join(format!("rsvp-{}.db", Uuid::new_v4())));
+        let options = SqliteConnectOptions::new()
+            .filename(&db.0)
+            .create_if_missing(true);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(4)
+            .connect_with(options)
+            .await
+            .unwrap();
+        let manager = SqliteRsvp::new(pool);
+        manager.migrate().await.unwrap();
+
+        let tasks: Vec<_> = (0..8)
+            .map(|i| {
+                let manager = manager.clone();
+                tokio::spawn(async move {
+                    let rsvp = abi::Reservation::new_pending(
+                        "tenant_id",
+                        format!("user-{}", i),
+                        "room",
+                        "2022-12-25T12:00:00-0700".parse().unwrap(),
+                        "2022-12-26T12:00:00-0700".parse().unwrap(),
+                        "note",
+                    );
+                    manager.reserve(rsvp, None).await
+                })
+            })
+            .collect();
+        let mut reserved = 0;
+        for task in tasks {
+            // writers wait for each other, every loser sees the winner's row
+            match task.await.unwrap() {
+                Ok(_) => reserved += 1,
+                Err(abi::Error::ConflictReservation(_)) => {}
+                Err(e) => panic!("unexpected error {:?}", e),
+            }
+        }
+        assert_eq!(reserved, 1);
+
+        let query = abi::ReservationQuery::new(
+            "tenant_id",
+            "",
+            "room",
+            "2022-12-01T00:00:00Z".parse().unwrap(),
+            "2023-01-01T00:00:00Z".parse().unwrap(),
+            ReservationStatus::Pending,
+            1,
+            10,
+            false,
+        );
+        assert_eq!(manager.query(query).await.unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,401 @@
+use crate::{Rsvp, TenantId};
+use abi::{
+    convert_to_utc_time, QueryPageResponse, ReservationQuery, ReservationStatus,
+    ResourceUtilization, TimeMatch, UtilizationRequest, UtilizationSlot, WeeklyPeriod,
+};
+use serde_json::json;
+use std::collections::BTreeMap;
+
+const PAGE_SIZE: i32 = 100;
+const SECONDS_PER_HOUR: i64 = 3600;
+const SECONDS_PER_DAY: i64 = 24 * SECONDS_PER_HOUR;
+
+/// statuses whose reservations hold their resources
+const BOOKED: [ReservationStatus; 5] = [
+    ReservationStatus::Pending,
+    ReservationStatus::Confirmed,
+    ReservationStatus::Blocked,
+    ReservationStatus::AwaitingApproval,
+    ReservationStatus::NoShow,
+];
+
+/// share of each resource's open time that is booked over the period, by hour of day and weekday
+pub async fn utilization(
+    rsvp: &(impl Rsvp + Sync),
+    tenant_id: TenantId,
+    request: UtilizationRequest,
+) -> Result<Vec<ResourceUtilization>, abi::Error> {
+    request.validate()?;
+    let start = convert_to_utc_time(request.start.clone().unwrap());
+    let end = convert_to_utc_time(request.end.clone().unwrap());
+    let offset = request.utc_offset_minutes as i64 * 60;
+    let hours = &request.opening_hours;
+
+    let mut booked: BTreeMap<String, Tally> = request
+        .resource_ids
+        .iter()
+        .map(|rid| (rid.clone(), Tally::default()))
+        .collect();
+    let mut cursor = String::new();
+    loop {
+        let query = ReservationQuery::new(
+            &tenant_id,
+            "",
+            "",
+            start,
+            end,
+            ReservationStatus::Unknown,
+            1,
+            PAGE_SIZE,
+            false,
+        )
+        .with_statuses(BOOKED)
+        .with_time_match(TimeMatch::Overlaps)
+        .with_cursor(cursor);
+        let page = QueryPageResponse::new(rsvp.query(query).await?, PAGE_SIZE)?;
+        for rsvp in &page.reservations {
+            let from = convert_to_utc_time(rsvp.start.clone().unwrap()).max(start);
+            let to = convert_to_utc_time(rsvp.end.clone().unwrap()).min(end);
+            for rid in std::iter::once(&rsvp.resource_id).chain(&rsvp.extra_resource_ids) {
+                if request.resource_ids.is_empty() || request.resource_ids.contains(rid) {
+                    booked.entry(rid.clone()).or_default().add(
+                        hours,
+                        from.timestamp() + offset,
+                        to.timestamp() + offset,
+                    );
+                }
+            }
+        }
+        if page.next_cursor.is_empty() {
+            break;
+        }
+        cursor = page.next_cursor;
+    }
+
+    let mut open = Tally::default();
+    open.add(hours, start.timestamp() + offset, end.timestamp() + offset);
+    Ok(booked
+        .into_iter()
+        .map(|(rid, booked)| {
+            let (open_seconds, booked_seconds) = (open.total(), booked.total());
+            ResourceUtilization {
+                resource_id: rid,
+                open_seconds,
+                booked_seconds,
+                ratio: ratio(booked_seconds, open_seconds),
+                by_hour: slots(0, &open.by_hour, &booked.by_hour),
+                by_weekday: slots(1, &open.by_weekday, &booked.by_weekday),
+            }
+        })
+        .collect())
+}
+
+/// one row per resource and slot, the totals of a resource have an empty breakdown
+pub fn utilization_csv(report: &[ResourceUtilization]) -> Result<String, abi::Error> {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    let csv_error = |e: csv::Error| abi::Error::ExportFailed(e.to_string());
+    writer
+        .write_record([
+            "resource_id",
+            "breakdown",
+            "key",
+            "open_seconds",
+            "booked_seconds",
+            "ratio",
+        ])
+        .map_err(csv_error)?;
+    for resource in report {
+        let total = (
+            "",
+            0,
+            resource.open_seconds,
+            resource.booked_seconds,
+            resource.ratio,
+        );
+        let hours = resource
+            .by_hour
+            .iter()
+            .map(|s| ("hour", s.key, s.open_seconds, s.booked_seconds, s.ratio));
+        let weekdays = resource
+            .by_weekday
+            .iter()
+            .map(|s| ("weekday", s.key, s.open_seconds, s.booked_seconds, s.ratio));
+        for (breakdown, key, open, booked, ratio) in
+            std::iter::once(total).chain(hours).chain(weekdays)
+        {
+            let key = if breakdown.is_empty() {
+                String::new()
+            } else {
+                key.to_string()
+            };
+            writer
+                .write_record([
+                    resource.resource_id.clone(),
+                    breakdown.to_string(),
+                    key,
+                    open.to_string(),
+                    booked.to_string(),
+                    format!("{:.4}", ratio),
+                ])
+                .map_err(csv_error)?;
+        }
+    }
+    let bytes = writer
+        .into_inner()
+        .map_err(|e| csv_error(e.into_error().into()))?;
+    String::from_utf8(bytes).map_err(|e| abi::Error::ExportFailed(e.to_string()))
+}
+
+pub fn utilization_json(report: &[ResourceUtilization]) -> Result<String, abi::Error> {
+    let slots = |slots: &[UtilizationSlot]| {
+        slots
+            .iter()
+            .map(|s| {
+                json!({
+                    "key": s.key,
+                    "open_seconds": s.open_seconds,
+                    "booked_seconds": s.booked_seconds,
+                    "ratio": s.ratio,
+                })
+            })
+            .collect::<Vec<_>>()
+    };
+    let resources: Vec<_> = report
+        .iter()
+        .map(|r| {
+            json!({
+                "resource_id": r.resource_id,
+                "open_seconds": r.open_seconds,
+                "booked_seconds": r.booked_seconds,
+                "ratio": r.ratio,
+                "by_hour": slots(&r.by_hour),
+                "by_weekday": slots(&r.by_weekday),
+            })
+        })
+        .collect();
+    serde_json::to_string_pretty(&resources).map_err(|e| abi::Error::ExportFailed(e.to_string()))
+}
+
+/// seconds within opening hours, by local hour of day and weekday
+#[derive(Debug, Default)]
+struct Tally {
+    by_hour: [i64; 24],
+    by_weekday: [i64; 7],
+}
+
+impl Tally {
+    /// count the open part of a span given in local seconds since the epoch
+    fn add(&mut self, hours: &[WeeklyPeriod], from: i64, to: i64) {
+        let mut current = from;
+        // within an hour the day and the hour don't change
+        while current < to {
+            let next = ((current.div_euclid(SECONDS_PER_HOUR) + 1) * SECONDS_PER_HOUR).min(to);
+            let day = current.div_euclid(SECONDS_PER_DAY);
+            // 1970-01-01 was a thursday
+            let weekday = (day + 3).rem_euclid(7) as usize;
+            let since_midnight = current - day * SECONDS_PER_DAY;
+            let seconds = open_seconds(
+                hours,
+                weekday as i32 + 1,
+                since_midnight,
+                since_midnight + next - current,
+            );
+            self.by_hour[(since_midnight / SECONDS_PER_HOUR) as usize] += seconds;
+            self.by_weekday[weekday] += seconds;
+            current = next;
+        }
+    }
+
+    fn total(&self) -> i64 {
+        self.by_hour.iter().sum()
+    }
+}
+
+/// how much of the span of a day is within opening hours, overlapping opening hours count once
+fn open_seconds(hours: &[WeeklyPeriod], weekday: i32, from: i64, to: i64) -> i64 {
+    if hours.is_empty() {
+        return to - from;
+    }
+    let mut ranges: Vec<(i64, i64)> = hours
+        .iter()
+        .filter(|h| h.applies_on(weekday))
+        .map(|h| (h.start_minute as i64 * 60, h.end_minute as i64 * 60))
+        .collect();
+    ranges.sort_unstable();
+
+    let (mut seconds, mut counted) = (0, from);
+    for (start, end) in ranges {
+        let (start, end) = (start.max(counted), end.min(to));
+        if start < end {
+            seconds += end - start;
+            counted = end;
+        }
+    }
+    seconds
+}
+
+fn slots(first_key: i32, open: &[i64], booked: &[i64]) -> Vec<UtilizationSlot> {
+    open.iter()
+        .zip(booked)
+        .enumerate()
+        .map(|(i, (open, booked))| UtilizationSlot {
+            key: first_key + i as i32,
+            open_seconds: *open,
+            booked_seconds: *booked,
+            ratio: ratio(*booked, *open),
+        })
+        .collect()
+}
+
+fn ratio(booked: i64, open: i64) -> f64 {
+    if open == 0 {
+        0.0
+    } else {
+        booked as f64 / open as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InMemoryRsvp;
+    use abi::{convert_to_timestamp, Reservation};
+
+    fn request(start: &str, end: &str) -> UtilizationRequest {
+        UtilizationRequest {
+            start: Some(convert_to_timestamp(start.parse().unwrap())),
+            end: Some(convert_to_timestamp(end.parse().unwrap())),
+            ..Default::default()
+        }
+    }
+
+    async fn reserve(rsvp: &InMemoryRsvp, rid: &str, start: &str, end: &str) -> Reservation {
+        let input = Reservation::new_pending(
+            "tenant",
+            "alice",
+            rid,
+            start.parse().unwrap(),
+            end.parse().unwrap(),
+            "",
+        );
+        rsvp.reserve(input, None).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn utilization_should_count_booked_time_within_opening_hours() {
+        let rsvp = InMemoryRsvp::new();
+        // 2022-12-26 is a monday
+        reserve(
+            &rsvp,
+            "room-1",
+            "2022-12-26T08:00:00Z",
+            "2022-12-26T10:30:00Z",
+        )
+        .await;
+        reserve(
+            &rsvp,
+            "room-1",
+            "2022-12-27T16:00:00Z",
+            "2022-12-27T18:00:00Z",
+        )
+        .await;
+        reserve(
+            &rsvp,
+            "room-2",
+            "2022-12-31T10:00:00Z",
+            "2022-12-31T11:00:00Z",
+        )
+        .await;
+        // a week of weekdays from 9 to 17
+        let request = UtilizationRequest {
+            opening_hours: vec![WeeklyPeriod::new([1, 2, 3, 4, 5], 9 * 60, 17 * 60)],
+            ..request("2022-12-26T00:00:00Z", "2023-01-02T00:00:00Z")
+        };
+        let report = utilization(&rsvp, "tenant".into(), request).await.unwrap();
+        assert_eq!(report.len(), 2);
+
+        let room = &report[0];
+        assert_eq!(room.resource_id, "room-1");
+        assert_eq!(room.open_seconds, 5 * 8 * 3600);
+        // 9:00 - 10:30 on monday and 16:00 - 17:00 on tuesday
+        assert_eq!(room.booked_seconds, 3600 + 1800 + 3600);
+        assert_eq!(room.by_hour[9].open_seconds, 5 * 3600);
+        assert_eq!(room.by_hour[9].booked_seconds, 3600);
+        assert_eq!(room.by_hour[8].open_seconds, 0);
+        assert_eq!(room.by_hour[8].booked_seconds, 0);
+        assert_eq!(room.by_hour[10].booked_seconds, 1800);
+        assert_eq!(room.by_hour[16].booked_seconds, 3600);
+        assert_eq!(room.by_hour[17].booked_seconds, 0);
+        assert_eq!(room.by_weekday[0].key, 1);
+        assert_eq!(room.by_weekday[0].booked_seconds, 5400);
+        assert_eq!(room.by_weekday[1].booked_seconds, 3600);
+        assert!((room.by_weekday[0].ratio - 5400.0 / 28800.0).abs() < 1e-9);
+        assert_eq!(room.by_weekday[6].open_seconds, 0);
+        assert_eq!(room.by_weekday[6].ratio, 0.0);
+
+        // saturday is closed
+        assert_eq!(report[1].resource_id, "room-2");
+        assert_eq!(report[1].booked_seconds, 0);
+    }
+
+    #[tokio::test]
+    async fn utilization_should_use_local_time() {
+        let rsvp = InMemoryRsvp::new();
+        reserve(
+            &rsvp,
+            "room-1",
+            "2022-12-25T23:00:00Z",
+            "2022-12-26T01:00:00Z",
+        )
+        .await;
+
+        // around the clock, 1:00 - 3:00 on monday at UTC+2
+        let request = UtilizationRequest {
+            resource_ids: vec!["room-1".into(), "room-2".into()],
+            utc_offset_minutes: 120,
+            ..request("2022-12-26T00:00:00Z", "2022-12-27T00:00:00Z")
+        };
+        let report = utilization(&rsvp, "tenant".into(), request).await.unwrap();
+        let room = &report[0];
+        assert_eq!(room.open_seconds, 24 * 3600);
+        assert_eq!(room.booked_seconds, 3600);
+        assert_eq!(room.by_hour[2].booked_seconds, 3600);
+        assert_eq!(room.by_hour[1].booked_seconds, 0);
+        assert_eq!(room.by_weekday[0].booked_seconds, 3600);
+        // requested resources are reported even if they're never booked
+        assert_eq!(report[1].resource_id, "room-2");
+        assert_eq!(report[1].open_seconds, 24 * 3600);
+
+        let csv = utilization_csv(&report).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next(),
+            Some("resource_id,breakdown,key,open_seconds,booked_seconds,ratio")
+        );
+        assert_eq!(lines.next(), Some("room-1,,,86400,3600,0.0417"));
+        assert_eq!(lines.next(), Some("room-1,hour,0,3600,0,0.0000"));
+        assert_eq!(csv.lines().count(), 1 + 2 * (1 + 24 + 7));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&utilization_json(&report).unwrap()).unwrap();
+        assert_eq!(json[0]["resource_id"], "room-1");
+        assert_eq!(json[0]["by_hour"][2]["booked_seconds"], 3600);
+        assert_eq!(json[1]["by_weekday"][0]["key"], 1);
+    }
+
+    #[tokio::test]
+    async fn invalid_opening_hours_should_reject() {
+        let request = UtilizationRequest {
+            opening_hours: vec![WeeklyPeriod::new([8], 9 * 60, 17 * 60)],
+            ..request("2022-12-26T00:00:00Z", "2023-01-02T00:00:00Z")
+        };
+        let err = utilization(&InMemoryRsvp::new(), "tenant".into(), request)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, abi::Error::InvalidOpeningHours(_)),
+            "{:?}",
+            err
+        );
+    }
+}
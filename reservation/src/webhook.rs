@@ -0,0 +1,277 @@
+use crate::{TenantId, WebhookId};
+use chrono::Duration;
+use sqlx::{types::Uuid, PgPool, Row};
+
+/// deliveries are retried with exponential backoff starting at `base` and capped at `max_delay`,
+/// after `max_attempts` failures they are moved to the dead letters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_attempts: i32,
+    pub base: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 8,
+            base: Duration::seconds(30),
+            max_delay: Duration::hours(1),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// how long to wait after the given number of failed attempts
+    pub fn delay(&self, attempts: i32) -> Duration {
+        let factor = 1i32 << (attempts - 1).clamp(0, 20);
+        (self.base * factor).min(self.max_delay)
+    }
+}
+
+/// a change to be posted to a webhook
+#[derive(Debug, Clone, PartialEq)]
+pub struct Delivery {
+    pub id: i64,
+    pub webhook_id: WebhookId,
+    pub url: String,
+    pub secret: String,
+    pub attempts: i32,
+    pub event: abi::ListenResponse,
+}
+
+/// webhook subscriptions and their pending deliveries, deliveries are queued in the database
+/// in the same transaction as the change they report
+#[derive(Debug, Clone)]
+pub struct Webhooks {
+    pool: PgPool,
+    retry: RetryPolicy,
+}
+
+impl Webhooks {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    pub async fn subscribe(&self, webhook: abi::Webhook) -> Result<abi::Webhook, abi::Error> {
+        webhook.validate()?;
+        let ops: Vec<String> = webhook.ops().map(|op| op.to_string()).collect();
+        let webhook = sqlx::query_as(
+            "INSERT INTO rsvp.webhooks (tenant_id, url, secret, resource_id, user_id, ops) VALUES ($1, $2, $3, $4, $5, $6::rsvp.reservation_update_type[]) RETURNING id, tenant_id, url, secret, resource_id, user_id, ops::text[]",
+        )
+        .bind(webhook.tenant_id)
+        .bind(webhook.url)
+        .bind(webhook.secret)
+        .bind(str_to_option(webhook.resource_id))
+        .bind(str_to_option(webhook.user_id))
+        .bind(ops)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(webhook)
+    }
+
+    /// remove the subscription together with its pending deliveries and dead letters
+    pub async fn unsubscribe(&self, tenant_id: TenantId, id: WebhookId) -> Result<(), abi::Error> {
+        let id = Uuid::parse_str(&id).map_err(|_| abi::Error::NotFound)?;
+        let deleted = sqlx::query("DELETE FROM rsvp.webhooks WHERE id = $1 AND tenant_id = $2")
+            .bind(id)
+            .bind(tenant_id)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+        if deleted == 0 {
+            return Err(abi::Error::NotFound);
+        }
+        Ok(())
+    }
+
+    /// claim up to limit deliveries that are due, they are offered again after lease
+    /// unless marked delivered or failed before
+    pub async fn due(&self, limit: i64, lease: Duration) -> Result<Vec<Delivery>, abi::Error> {
+        let rows = sqlx::query(
+            "WITH claimed AS (
+                UPDATE rsvp.webhook_deliveries SET next_attempt_at = now() + $2
+                WHERE id IN (
+                    SELECT id FROM rsvp.webhook_deliveries WHERE next_attempt_at <= now()
+                    ORDER BY id LIMIT $1 FOR UPDATE SKIP LOCKED
+                )
+                RETURNING id, webhook_id, change_id, attempts
+            )
+            SELECT claimed.id AS delivery_id, claimed.webhook_id, claimed.attempts, w.url, w.secret, c.op::text AS op, r.*
+            FROM claimed
+            JOIN rsvp.webhooks w ON w.id = claimed.webhook_id
+            JOIN rsvp.reservation_changes c ON c.id = claimed.change_id,
+            jsonb_populate_record(NULL::rsvp.reservations, c.reservation) r
+            ORDER BY claimed.id",
+        )
+        .bind(limit)
+        .bind(lease)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                let webhook_id: Uuid = row.get("webhook_id");
+                let op: String = row.get("op");
+                Ok(Delivery {
+                    id: row.get("delivery_id"),
+                    webhook_id: webhook_id.to_string(),
+                    url: row.get("url"),
+                    secret: row.get("secret"),
+                    attempts: row.get("attempts"),
+                    event: abi::ListenResponse {
+                        op: abi::ReservationUpdateType::from(op.as_str()) as i32,
+                        reservation: Some(sqlx::FromRow::from_row(row)?),
+                    },
+                })
+            })
+            .collect()
+    }
+
+    pub async fn delivered(&self, delivery: &Delivery) -> Result<(), abi::Error> {
+        sqlx::query("DELETE FROM rsvp.webhook_deliveries WHERE id = $1")
+            .bind(delivery.id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// schedule a retry, or move the delivery to the dead letters once it ran out of attempts
+    pub async fn failed(&self, delivery: &Delivery, error: String) -> Result<(), abi::Error> {
+        let attempts = delivery.attempts + 1;
+        if attempts >= self.retry.max_attempts {
+            sqlx::query(
+                "WITH dead AS (
+                    DELETE FROM rsvp.webhook_deliveries WHERE id = $1 RETURNING id, webhook_id, change_id
+                ) INSERT INTO rsvp.webhook_dead_letters (id, webhook_id, change_id, attempts, last_error)
+                SELECT id, webhook_id, change_id, $2, $3 FROM dead",
+            )
+            .bind(delivery.id)
+            .bind(attempts)
+            .bind(error)
+            .execute(&self.pool)
+            .await?;
+        } else {
+            sqlx::query(
+                "UPDATE rsvp.webhook_deliveries SET attempts = $2, next_attempt_at = now() + $3, last_error = $4 WHERE id = $1",
+            )
+            .bind(delivery.id)
+            .bind(attempts)
+            .bind(self.retry.delay(attempts))
+            .bind(error)
+            .execute(&self.pool)
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// number of deliveries of the webhook that gave up
+    pub async fn dead_letter_count(
+        &self,
+        tenant_id: TenantId,
+        id: WebhookId,
+    ) -> Result<i64, abi::Error> {
+        let id = Uuid::parse_str(&id).map_err(|_| abi::Error::NotFound)?;
+        let count = sqlx::query(
+            "SELECT count(*) FROM rsvp.webhook_dead_letters d JOIN rsvp.webhooks w ON w.id = d.webhook_id WHERE w.id = $1 AND w.tenant_id = $2",
+        )
+        .bind(id)
+        .bind(tenant_id)
+        .fetch_one(&self.pool)
+        .await?
+        .get(0);
+        Ok(count)
+    }
+}
+
+fn str_to_option(s: String) -> Option<String> {
+    if s.is_empty() {
+        None
+    } else {
+        Some(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ReservationManager, Rsvp};
+    use abi::ReservationUpdateType;
+
+    #[test]
+    fn retry_delay_should_back_off() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.delay(1), Duration::seconds(30));
+        assert_eq!(policy.delay(3), Duration::minutes(2));
+        assert_eq!(policy.delay(30), Duration::hours(1));
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn matching_changes_should_be_delivered_then_dead_lettered() {
+        let webhooks = Webhooks::new(migrated_pool.clone()).with_retry_policy(RetryPolicy {
+            max_attempts: 2,
+            base: Duration::zero(),
+            max_delay: Duration::zero(),
+        });
+        let mut webhook = abi::Webhook::new("tenant_id", "https://example.com/hook", "secret");
+        webhook.resource_id = "room-1".into();
+        webhook.ops = vec![ReservationUpdateType::Create as i32];
+        let webhook = webhooks.subscribe(webhook).await.unwrap();
+
+        let manager = ReservationManager::new(migrated_pool.clone());
+        let mut rsvps = vec![];
+        for rid in ["room-1", "room-2"] {
+            let rsvp = abi::Reservation::new_pending(
+                "tenant_id",
+                "user_id1",
+                rid,
+                "2022-12-25T12:00:00-0700".parse().unwrap(),
+                "2022-12-31T12:00:00-0700".parse().unwrap(),
+                "Test note1",
+            );
+            rsvps.push(manager.reserve(rsvp, None).await.unwrap());
+        }
+        // updates are filtered out by op
+        manager
+            .change_status("tenant_id".into(), rsvps[0].id.clone(), None, None)
+            .await
+            .unwrap();
+
+        let due = webhooks.due(10, Duration::minutes(5)).await.unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].webhook_id, webhook.id);
+        assert_eq!(due[0].event.op, ReservationUpdateType::Create as i32);
+        assert_eq!(due[0].event.reservation.as_ref(), Some(&rsvps[0]));
+        // claimed deliveries are not offered again during the lease
+        assert!(webhooks
+            .due(10, Duration::minutes(5))
+            .await
+            .unwrap()
+            .is_empty());
+
+        webhooks.failed(&due[0], "503".into()).await.unwrap();
+        let due = webhooks.due(10, Duration::minutes(5)).await.unwrap();
+        assert_eq!(due[0].attempts, 1);
+        webhooks.failed(&due[0], "503".into()).await.unwrap();
+        assert!(webhooks
+            .due(10, Duration::minutes(5))
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            webhooks
+                .dead_letter_count("tenant_id".into(), webhook.id)
+                .await
+                .unwrap(),
+            1
+        );
+    }
+}
@@ -0,0 +1,51 @@
+//! bulk import reservations from a CSV or JSON Lines file
+//!
+//! usage: rsvp-import [--dry-run] <tenant_id> <file.csv|file.jsonl>
+
+use anyhow::{bail, Result};
+use reservation::ReservationManager;
+use sqlx::PgPool;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let dry_run = args.iter().any(|arg| arg == "--dry-run");
+    args.retain(|arg| arg != "--dry-run");
+    let (tid, path) = match args.as_slice() {
+        [tid, path] => (tid, path),
+        _ => bail!("usage: rsvp-import [--dry-run] <tenant_id> <file.csv|file.jsonl>"),
+    };
+
+    let input = std::fs::read_to_string(path)?;
+    let rows = if path.ends_with(".csv") {
+        reservation::parse_csv(tid, input.as_bytes())
+    } else {
+        reservation::parse_json_lines(tid, &input)
+    };
+
+    let url = std::env::var("DATABASE_URL")?;
+    let manager = ReservationManager::new(PgPool::connect(&url).await?);
+    let result = reservation::bulk_reserve_rows(&manager, rows, dry_run).await?;
+
+    // rows are numbered from 1, not counting the csv header
+    for (row, e) in &result.rejected {
+        println!("row {}: {}", row + 1, describe(e));
+    }
+    let verb = if dry_run { "would reserve" } else { "reserved" };
+    println!(
+        "{} {} reservations, {} rows rejected",
+        verb,
+        result.reserved.len(),
+        result.rejected.len()
+    );
+    Ok(())
+}
+
+fn describe(e: &abi::Error) -> String {
+    match e {
+        abi::Error::InvalidRow { reason, .. } => reason.clone(),
+        abi::Error::ConflictReservation(info) => format!("{}: {:?}", e, info),
+        abi::Error::ConflictReservations(infos) => format!("{}: {:?}", e, infos),
+        _ => e.to_string(),
+    }
+}
@@ -0,0 +1,70 @@
+//! report how much of each resource's open time was booked over a period
+//!
+//! usage: rsvp-report [--json] [--utc-offset MINUTES] [--open 1-5@09:00-17:00]... [--resource ID]... <tenant_id> <start> <end>
+
+use abi::{convert_to_timestamp, UtilizationRequest, WeeklyPeriod};
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Utc};
+use reservation::ReservationManager;
+use sqlx::PgPool;
+
+const USAGE: &str = "usage: rsvp-report [--json] [--utc-offset MINUTES] [--open 1-5@09:00-17:00]... [--resource ID]... <tenant_id> <start> <end>";
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let mut json = false;
+    let mut request = UtilizationRequest::default();
+    let mut positional = vec![];
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| anyhow!(USAGE));
+        match arg.as_str() {
+            "--json" => json = true,
+            "--utc-offset" => request.utc_offset_minutes = value()?.parse()?,
+            "--open" => request.opening_hours.push(parse_opening_hours(&value()?)?),
+            "--resource" => request.resource_ids.push(value()?),
+            _ if arg.starts_with("--") => bail!(USAGE),
+            _ => positional.push(arg),
+        }
+    }
+    let (tid, start, end) = match positional.as_slice() {
+        [tid, start, end] => (tid.clone(), start, end),
+        _ => bail!(USAGE),
+    };
+    request.start = Some(convert_to_timestamp(start.parse::<DateTime<Utc>>()?));
+    request.end = Some(convert_to_timestamp(end.parse::<DateTime<Utc>>()?));
+
+    let url = std::env::var("DATABASE_URL")?;
+    let manager = ReservationManager::new(PgPool::connect(&url).await?);
+    let report = reservation::utilization(&manager, tid, request).await?;
+    if json {
+        println!("{}", reservation::utilization_json(&report)?);
+    } else {
+        print!("{}", reservation::utilization_csv(&report)?);
+    }
+    Ok(())
+}
+
+/// weekdays from 1 for monday, as a range or a comma separated list, then the local hours, e.g. 1-5@09:00-17:00
+fn parse_opening_hours(spec: &str) -> Result<WeeklyPeriod> {
+    let invalid = || {
+        anyhow!(
+            "invalid opening hours {}, expected e.g. 1-5@09:00-17:00",
+            spec
+        )
+    };
+    let (days, hours) = spec.split_once('@').ok_or_else(invalid)?;
+    let mut weekdays = vec![];
+    for part in days.split(',') {
+        match part.split_once('-') {
+            Some((from, to)) => weekdays.extend(from.parse::<i32>()?..=to.parse::<i32>()?),
+            None => weekdays.push(part.parse()?),
+        }
+    }
+    let (start, end) = hours.split_once('-').ok_or_else(invalid)?;
+    let minute = |time: &str| -> Result<i32> {
+        let (h, m) = time.split_once(':').ok_or_else(invalid)?;
+        Ok(h.parse::<i32>()? * 60 + m.parse::<i32>()?)
+    };
+    Ok(WeeklyPeriod::new(weekdays, minute(start)?, minute(end)?))
+}
@@ -0,0 +1,29 @@
+use abi::{convert_to_utc_time, ReservationStatus, ReservationUpdateType};
+use chrono::SecondsFormat;
+use serde_json::{json, Value};
+
+/// JSON of the ListenResponse, enums as lowercase names and times as RFC 3339
+pub fn to_json(event: &abi::ListenResponse) -> Value {
+    let op = ReservationUpdateType::from_i32(event.op).unwrap_or(ReservationUpdateType::Unknown);
+    let reservation = event.reservation.as_ref().map(|rsvp| {
+        let status = ReservationStatus::from_i32(rsvp.status).unwrap_or(ReservationStatus::Unknown);
+        let time = |ts: &Option<prost_types::Timestamp>| {
+            ts.clone()
+                .map(|ts| convert_to_utc_time(ts).to_rfc3339_opts(SecondsFormat::Secs, true))
+        };
+        json!({
+            "id": rsvp.id,
+            "tenant_id": rsvp.tenant_id,
+            "user_id": rsvp.user_id,
+            "status": status.to_string(),
+            "resource_id": rsvp.resource_id,
+            "extra_resource_ids": rsvp.extra_resource_ids,
+            "start": time(&rsvp.start),
+            "end": time(&rsvp.end),
+            "note": rsvp.note,
+            "version": rsvp.version,
+            "price": rsvp.price,
+        })
+    });
+    json!({ "op": op.to_string(), "reservation": reservation })
+}
@@ -0,0 +1,117 @@
+use abi::{QueryPageResponse, ReservationQuery, ReservationStatus, TimeMatch};
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use chrono::{Duration, Utc};
+use reservation::Rsvp;
+use std::sync::Arc;
+use tracing::warn;
+
+/// how far ahead the calendar feed looks
+const HORIZON_DAYS: i64 = 180;
+const PAGE_SIZE: i32 = 100;
+
+/// calendar feeds at /calendar/{token}.ics, the secret token identifies the user
+pub fn router<T: Rsvp + Send + Sync + 'static>(rsvp: T) -> Router {
+    Router::new()
+        .route("/calendar/:token", get(calendar::<T>))
+        .with_state(Arc::new(rsvp))
+}
+
+async fn calendar<T: Rsvp + Send + Sync>(
+    State(rsvp): State<Arc<T>>,
+    Path(token): Path<String>,
+) -> Response {
+    let token = token.strip_suffix(".ics").unwrap_or(&token).to_string();
+    match upcoming(rsvp.as_ref(), token).await {
+        Ok(ics) => (
+            [(header::CONTENT_TYPE, "text/calendar; charset=utf-8")],
+            ics,
+        )
+            .into_response(),
+        Err(abi::Error::NotFound) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => {
+            warn!("failed to build calendar feed: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// iCalendar of the reservations of the token's owner that haven't ended yet
+async fn upcoming<T: Rsvp + Sync>(rsvp: &T, token: String) -> Result<String, abi::Error> {
+    let (tid, uid) = rsvp.feed_owner(token).await?;
+    let start = Utc::now();
+    let end = start + Duration::days(HORIZON_DAYS);
+    let mut rsvps = Vec::new();
+    let mut cursor = String::new();
+    loop {
+        let query = ReservationQuery::new(
+            &tid,
+            &uid,
+            "",
+            start,
+            end,
+            ReservationStatus::Unknown,
+            1,
+            PAGE_SIZE,
+            false,
+        )
+        .with_statuses([
+            ReservationStatus::Pending,
+            ReservationStatus::Confirmed,
+            ReservationStatus::AwaitingApproval,
+        ])
+        // one that already started is still on the calendar
+        .with_time_match(TimeMatch::Overlaps)
+        .with_cursor(cursor);
+        let page = QueryPageResponse::new(rsvp.query(query).await?, PAGE_SIZE)?;
+        rsvps.extend(page.reservations);
+        if page.next_cursor.is_empty() {
+            break;
+        }
+        cursor = page.next_cursor;
+    }
+    Ok(abi::to_ics(&rsvps))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reservation::InMemoryRsvp;
+
+    #[tokio::test]
+    async fn upcoming_should_include_reservations_in_progress() {
+        let rsvp = InMemoryRsvp::new();
+        let now = Utc::now();
+        for (rid, start, end) in [
+            (
+                "started",
+                now - Duration::hours(1),
+                now + Duration::hours(1),
+            ),
+            (
+                "later",
+                now + Duration::days(1),
+                now + Duration::days(1) + Duration::hours(1),
+            ),
+            ("ended", now - Duration::hours(2), now - Duration::hours(1)),
+        ] {
+            let new =
+                abi::Reservation::new_pending("tenant", "alice", rid, start.into(), end.into(), "");
+            rsvp.reserve(new, None).await.unwrap();
+        }
+        let token = rsvp
+            .feed_token("tenant".into(), "alice".into())
+            .await
+            .unwrap();
+
+        let ics = upcoming(&rsvp, token).await.unwrap();
+        assert!(ics.contains("LOCATION:started"), "{}", ics);
+        assert!(ics.contains("LOCATION:later"), "{}", ics);
+        assert!(!ics.contains("LOCATION:ended"), "{}", ics);
+    }
+}
@@ -0,0 +1,56 @@
+use chrono::Utc;
+use reservation::{CachedRsvp, Outbox, Rsvp};
+use sqlx::PgPool;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// periodically mark reservations nobody checked in as no-shows and release their remaining time
+pub async fn release_no_shows(rsvp: impl Rsvp, interval: Duration) {
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        match rsvp.release_no_shows().await {
+            Ok(released) if !released.is_empty() => {
+                info!("released {} no-show reservations", released.len())
+            }
+            Ok(_) => {}
+            Err(e) => warn!("failed to release no-show reservations: {:?}", e),
+        }
+    }
+}
+
+/// periodically forget idempotency keys first used longer than ttl ago
+pub async fn expire_idempotency_keys(rsvp: impl Rsvp, ttl: chrono::Duration, interval: Duration) {
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        match rsvp.expire_idempotency_keys(Utc::now() - ttl).await {
+            Ok(expired) if expired > 0 => info!("expired {} idempotency keys", expired),
+            Ok(_) => {}
+            Err(e) => warn!("failed to expire idempotency keys: {:?}", e),
+        }
+    }
+}
+
+/// periodically delete the outbox events every consumer has published
+pub async fn prune_outbox(outbox: Outbox, interval: Duration) {
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        match outbox.prune().await {
+            Ok(pruned) if pruned > 0 => info!("pruned {} outbox events", pruned),
+            Ok(_) => {}
+            Err(e) => warn!("failed to prune outbox events: {:?}", e),
+        }
+    }
+}
+
+/// keep the cache in line with the changes of every instance, listening again after a failure
+pub async fn invalidate_cache<T>(cache: CachedRsvp<T>, pool: PgPool, retry: Duration) {
+    loop {
+        if let Err(e) = cache.listen(&pool).await {
+            warn!("lost reservation change notifications: {:?}", e);
+        }
+        tokio::time::sleep(retry).await;
+    }
+}
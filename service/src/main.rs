@@ -1,3 +1,74 @@
-fn main() {
-    println!("Hello, world!");
+mod events;
+mod feed;
+mod jobs;
+mod notifier;
+mod outbox;
+mod webhooks;
+
+use anyhow::Result;
+use reservation::{CachedRsvp, Outbox, Reminders, ReservationManager, Webhooks};
+use sqlx::PgPool;
+use std::{net::SocketAddr, time::Duration};
+use tracing::info;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    tracing_subscriber::fmt::init();
+
+    let url = std::env::var("DATABASE_URL")?;
+    let addr: SocketAddr = std::env::var("HTTP_ADDR")
+        .unwrap_or_else(|_| "0.0.0.0:8080".to_string())
+        .parse()?;
+    let pool = PgPool::connect(&url).await?;
+    // reads are served from memory, changes of every instance are announced by the database
+    let manager = CachedRsvp::new(ReservationManager::new(pool.clone()));
+    tokio::spawn(jobs::invalidate_cache(
+        manager.clone(),
+        pool.clone(),
+        Duration::from_secs(1),
+    ));
+
+    tokio::spawn(jobs::release_no_shows(
+        manager.clone(),
+        Duration::from_secs(60),
+    ));
+    // retries with a key are replayed for a day
+    tokio::spawn(jobs::expire_idempotency_keys(
+        manager.clone(),
+        chrono::Duration::days(1),
+        Duration::from_secs(3600),
+    ));
+
+    // e.g. stdout, file:/var/log/reservations.jsonl or an http url,
+    // an http sink stores its offset under the name given by OUTBOX_CONSUMER
+    if let Ok(spec) = std::env::var("OUTBOX_SINK") {
+        let consumer = std::env::var("OUTBOX_CONSUMER").ok();
+        tokio::spawn(outbox::relay(
+            Outbox::new(pool.clone()),
+            outbox::sink(&spec, consumer)?,
+            Duration::from_secs(1),
+        ));
+    }
+    // the relays of every instance share the outbox, events are kept until all of them passed
+    tokio::spawn(jobs::prune_outbox(
+        Outbox::new(pool.clone()),
+        Duration::from_secs(60),
+    ));
+    // SMTP_URL, REMINDER_FROM and REMINDER_DOMAIN configure the mail server
+    tokio::spawn(notifier::send_reminders(
+        Reminders::new(pool.clone()),
+        notifier::notifier()?,
+        Duration::from_secs(30),
+    ));
+    tokio::spawn(webhooks::deliver_webhooks(
+        Webhooks::new(pool),
+        webhooks::client()?,
+        Duration::from_secs(5),
+    ));
+
+    info!("serving calendar feeds on {}", addr);
+    axum::Server::bind(&addr)
+        .serve(feed::router(manager).into_make_service())
+        .await?;
+    Ok(())
 }
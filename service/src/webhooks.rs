@@ -0,0 +1,112 @@
+use crate::events::to_json;
+use anyhow::Result;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use reservation::{Delivery, Webhooks};
+use sha2::Sha256;
+use std::time::Duration;
+use tokio::task::JoinSet;
+use tracing::{info, warn};
+
+const BATCH_SIZE: i64 = 100;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+// a delivery still in flight after this is offered again. a batch is posted all at once,
+// so the lease only has to outlast a single request
+const LEASE_SECONDS: i64 = 300;
+const SIGNATURE_HEADER: &str = "X-Reservation-Signature";
+const TIMESTAMP_HEADER: &str = "X-Reservation-Timestamp";
+const DELIVERY_HEADER: &str = "X-Reservation-Delivery";
+
+/// http client that gives up on a webhook after the request timeout
+pub fn client() -> Result<reqwest::Client> {
+    Ok(reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()?)
+}
+
+/// periodically post pending webhook deliveries, failures are retried by the webhooks' retry policy
+pub async fn deliver_webhooks(webhooks: Webhooks, client: reqwest::Client, interval: Duration) {
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        let deliveries = match webhooks
+            .due(BATCH_SIZE, chrono::Duration::seconds(LEASE_SECONDS))
+            .await
+        {
+            Ok(deliveries) => deliveries,
+            Err(e) => {
+                warn!("failed to load webhook deliveries: {:?}", e);
+                continue;
+            }
+        };
+
+        let mut posts = JoinSet::new();
+        for delivery in deliveries {
+            let (client, webhooks) = (client.clone(), webhooks.clone());
+            posts.spawn(async move { deliver(&client, &webhooks, &delivery).await });
+        }
+        while posts.join_next().await.is_some() {}
+    }
+}
+
+/// post the delivery and record the outcome
+async fn deliver(client: &reqwest::Client, webhooks: &Webhooks, delivery: &Delivery) {
+    let result = match post(client, delivery).await {
+        Ok(()) => webhooks.delivered(delivery).await,
+        Err(e) => {
+            info!("webhook delivery {} failed: {}", delivery.id, e);
+            webhooks.failed(delivery, e).await
+        }
+    };
+    if let Err(e) = result {
+        warn!("failed to record webhook delivery {}: {:?}", delivery.id, e);
+    }
+}
+
+async fn post(client: &reqwest::Client, delivery: &Delivery) -> Result<(), String> {
+    let body = to_json(&delivery.event).to_string();
+    let timestamp = Utc::now().timestamp();
+    let response = client
+        .post(&delivery.url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(SIGNATURE_HEADER, sign(&delivery.secret, timestamp, &body))
+        .header(TIMESTAMP_HEADER, timestamp.to_string())
+        .header(DELIVERY_HEADER, delivery.id.to_string())
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("status {}", response.status()));
+    }
+    Ok(())
+}
+
+/// `sha256=` followed by the hex HMAC-SHA256 of `<timestamp>.<body>`. the timestamp is sent
+/// in its own header, receivers should drop deliveries whose timestamp is too old
+fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("hmac accepts keys of any size");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sign_should_match_known_vector() {
+        assert_eq!(
+            sign("whsec_test", 1671700000, r#"{"op":"create"}"#),
+            "sha256=da07a6818a0515a26ceffa2c4dda11f4013d6b372488d387200e8345105ccd2f"
+        );
+        // the same body at another time gets another signature
+        assert_ne!(
+            sign("whsec_test", 1671700001, r#"{"op":"create"}"#),
+            sign("whsec_test", 1671700000, r#"{"op":"create"}"#)
+        );
+    }
+}